@@ -4,6 +4,7 @@ use talk::crypto::primitives::{hash, hash::HASH_LENGTH};
 
 const INTERNAL_FLAG: u8 = 0;
 const LEAF_FLAG: u8 = 1;
+const INDEX_FLAG: u8 = 2;
 const EMPTY_HASH: Bytes = Bytes([0; HASH_LENGTH]);
 
 pub(crate) fn empty() -> Bytes {
@@ -17,3 +18,7 @@ pub(crate) fn internal(left: Bytes, right: Bytes) -> Bytes {
 pub(crate) fn leaf(key: Bytes, value: Bytes) -> Bytes {
     hash::hash(&(LEAF_FLAG, key, value)).unwrap().into()
 }
+
+pub(crate) fn index(record: Bytes, left: Bytes, right: Bytes) -> Bytes {
+    hash::hash(&(INDEX_FLAG, record, left, right)).unwrap().into()
+}
@@ -0,0 +1,132 @@
+use crate::common::{data::Bytes, store::hash};
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::Hash as StdHash,
+};
+
+/// A node of a Merkle tree, as seen by [`diff`].
+pub(crate) enum Visit<Ref, Key, Value> {
+    Empty,
+    Internal(Ref, Ref),
+    Leaf(Key, Value),
+    Unknown,
+}
+
+/// A Merkle tree whose nodes can be compared by hash with those of another
+/// tree, regardless of where (i.e., in which `Store`, or in a `Map`) the two
+/// trees are stored.
+pub(crate) trait Tree {
+    type Ref;
+    type Key;
+    type Value;
+
+    fn hash(&self, node: &Self::Ref) -> Bytes;
+    fn visit(&mut self, node: Self::Ref) -> Visit<Self::Ref, Self::Key, Self::Value>;
+}
+
+pub(crate) type Diff<Key, Value> = HashMap<Key, (Option<Value>, Option<Value>)>;
+
+/// Returns, for each key whose value differs between the trees rooted at
+/// `lho_root` (in `lho`) and `rho_root` (in `rho`), its values in both trees.
+/// Only subtrees whose hashes differ are visited.
+///
+/// Returns `None` if a differing subtree is `Unknown`.
+pub(crate) fn diff<L, R, Key, Value>(
+    lho: &mut L,
+    lho_root: L::Ref,
+    rho: &mut R,
+    rho_root: R::Ref,
+) -> Option<Diff<Key, Value>>
+where
+    L: Tree<Key = Key, Value = Value>,
+    R: Tree<Key = Key, Value = Value>,
+    Key: Eq + StdHash,
+    Value: Eq,
+{
+    let mut lho_candidates = Vec::new();
+    let mut rho_candidates = Vec::new();
+
+    recur(
+        lho,
+        Some(lho_root),
+        &mut lho_candidates,
+        rho,
+        Some(rho_root),
+        &mut rho_candidates,
+    )?;
+
+    let mut diff: Diff<Key, Value> = lho_candidates
+        .into_iter()
+        .map(|(key, value)| (key, (Some(value), None)))
+        .collect();
+
+    for (key, value) in rho_candidates {
+        match diff.entry(key) {
+            Entry::Occupied(mut entry) => {
+                // The same record can lie at different depths in the two trees
+                if entry.get().0.as_ref() == Some(&value) {
+                    entry.remove_entry();
+                } else {
+                    entry.get_mut().1 = Some(value);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((None, Some(value)));
+            }
+        }
+    }
+
+    Some(diff)
+}
+
+fn recur<L, R, Key, Value>(
+    lho: &mut L,
+    lho_node: Option<L::Ref>,
+    lho_candidates: &mut Vec<(Key, Value)>,
+    rho: &mut R,
+    rho_node: Option<R::Ref>,
+    rho_candidates: &mut Vec<(Key, Value)>,
+) -> Option<()>
+where
+    L: Tree<Key = Key, Value = Value>,
+    R: Tree<Key = Key, Value = Value>,
+{
+    let lho_hash = lho_node.as_ref().map_or(hash::empty(), |node| lho.hash(node));
+    let rho_hash = rho_node.as_ref().map_or(hash::empty(), |node| rho.hash(node));
+
+    if lho_hash == rho_hash {
+        return Some(());
+    }
+
+    let (lho_left, lho_right) = expand(lho, lho_node, lho_candidates)?;
+    let (rho_left, rho_right) = expand(rho, rho_node, rho_candidates)?;
+
+    if lho_left.is_some() || rho_left.is_some() {
+        recur(lho, lho_left, lho_candidates, rho, rho_left, rho_candidates)?;
+        recur(lho, lho_right, lho_candidates, rho, rho_right, rho_candidates)?;
+    }
+
+    Some(())
+}
+
+// Collects `node` if it is a leaf, returns its children if it is internal
+#[allow(clippy::type_complexity)]
+fn expand<T>(
+    tree: &mut T,
+    node: Option<T::Ref>,
+    candidates: &mut Vec<(T::Key, T::Value)>,
+) -> Option<(Option<T::Ref>, Option<T::Ref>)>
+where
+    T: Tree,
+{
+    match node.map(|node| tree.visit(node)) {
+        Some(Visit::Internal(left, right)) => Some((Some(left), Some(right))),
+        Some(Visit::Leaf(key, value)) => {
+            candidates.push((key, value));
+            Some((None, None))
+        }
+        Some(Visit::Empty) | None => Some((None, None)),
+        Some(Visit::Unknown) => None,
+    }
+}
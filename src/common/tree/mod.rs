@@ -1,9 +1,11 @@
+mod diff;
 mod direction;
 mod path;
 mod prefix;
 
 use path::PathIterator;
 
+pub(crate) use diff::{diff, Diff, Tree, Visit};
 pub(crate) use direction::Direction;
 pub(crate) use path::Path;
 pub(crate) use prefix::Prefix;
@@ -0,0 +1,159 @@
+use crate::database::errors::CertificateError;
+
+use doomstack::{here, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::{
+    primitives::{hash::Hash, sign::Signature},
+    KeyCard, KeyChain, Statement,
+};
+
+/// A signed commitment to the contents of a [`Table`].
+///
+/// A `Certificate` binds a [`Table`]'s commitment (see [`Table::commit`]) to
+/// the [`Table`]'s name, a version and the [`KeyChain`] that produced it.
+/// Versions issued by a [`Database`] for the same name are strictly
+/// increasing, even across restarts.
+///
+/// Certificates are produced by [`Table::certify`] and checked by a
+/// [`TableReceiver`] through [`TableReceiver::expect_certified`].
+///
+/// [`Table`]: crate::database::Table
+/// [`Table::commit`]: crate::database::Table::commit
+/// [`Table::certify`]: crate::database::Table::certify
+/// [`Database`]: crate::database::Database
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`TableReceiver::expect_certified`]: crate::database::TableReceiver::expect_certified
+/// [`KeyChain`]: talk::crypto::KeyChain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Certificate {
+    name: String,
+    version: u64,
+    commitment: Hash,
+    signature: Signature,
+}
+
+#[derive(Serialize)]
+enum Header {
+    TableCommitment,
+}
+
+#[derive(Serialize)]
+struct Commitment<'a> {
+    name: &'a str,
+    version: u64,
+    commitment: &'a Hash,
+}
+
+impl Statement for Commitment<'_> {
+    type Header = Header;
+    const HEADER: Header = Header::TableCommitment;
+}
+
+impl Certificate {
+    pub(crate) fn new(
+        keychain: &KeyChain,
+        name: String,
+        version: u64,
+        commitment: Hash,
+    ) -> Result<Self, Top<CertificateError>> {
+        let signature = keychain
+            .sign(&Commitment {
+                name: &name,
+                version,
+                commitment: &commitment,
+            })
+            .pot(CertificateError::SignFailed, here!())?;
+
+        Ok(Certificate {
+            name,
+            version,
+            commitment,
+            signature,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn commitment(&self) -> Hash {
+        self.commitment
+    }
+
+    /// Checks that the `Certificate` was produced by the owner of `keycard`.
+    pub fn verify(&self, keycard: &KeyCard) -> Result<(), Top<CertificateError>> {
+        self.signature
+            .verify(
+                keycard,
+                &Commitment {
+                    name: &self.name,
+                    version: self.version,
+                    commitment: &self.commitment,
+                },
+            )
+            .pot(CertificateError::InvalidSignature, here!())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+
+    use super::*;
+
+    #[test]
+    fn versions_increase_across_restarts() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+        let keychain = KeyChain::random();
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.table_with_records((0..16).map(|i| (i, i)));
+
+            let first = table.certify(&keychain).unwrap();
+            let second = table.certify(&keychain).unwrap();
+
+            assert_eq!(first.name(), "test");
+            assert!(second.version() > first.version());
+        }
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.get_table("test").unwrap();
+
+            let third = table.certify(&keychain).unwrap();
+
+            assert_eq!(third.version(), 2);
+            assert_eq!(third.commitment(), table.commit());
+            third.verify(&keychain.keycard()).unwrap();
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn tampered() {
+        Database::<u32, u32>::test_database(|database| {
+            let keychain = KeyChain::random();
+
+            let table = database.table_with_records((0..16).map(|i| (i, i)));
+
+            let certificate = table.certify(&KeyChain::random()).unwrap();
+            assert!(certificate.verify(&keychain.keycard()).is_err());
+
+            let mut certificate = table.certify(&keychain).unwrap();
+            certificate.version += 1;
+            assert!(certificate.verify(&keychain.keycard()).is_err());
+
+            let mut certificate = table.certify(&keychain).unwrap();
+            certificate.name = "other".to_string();
+            assert!(certificate.verify(&keychain.keycard()).is_err());
+        });
+    }
+}
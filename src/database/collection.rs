@@ -1,8 +1,13 @@
 use crate::{
     common::store::Field,
-    database::{CollectionResponse, CollectionSender, CollectionTransaction, Table},
+    database::{
+        errors::QueryError, CollectionResponse, CollectionSender, CollectionTransaction, Table,
+        TableTransaction,
+    },
 };
 
+use doomstack::Top;
+
 use std::{collections::HashSet, hash::Hash as StdHash, sync::Arc};
 
 use talk::crypto::primitives::hash::Hash;
@@ -48,6 +53,77 @@ where
 
         (lho_minus_rho, rho_minus_lho)
     }
+
+    /// Returns the items to add to `lho` to obtain the union of `lho` and
+    /// `rho` (see [`Family::union`]).
+    ///
+    /// [`Family::union`]: crate::database::Family::union
+    pub(crate) fn union_transaction(
+        lho: &Collection<Item>,
+        rho: &Collection<Item>,
+    ) -> Result<TableTransaction<Item, ()>, Top<QueryError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let (_, rho_minus_lho) = Collection::diff(lho, rho);
+        let mut transaction = TableTransaction::default();
+
+        for item in rho_minus_lho {
+            transaction.set(item, ())?;
+        }
+
+        Ok(transaction)
+    }
+
+    /// Returns the items to remove from `lho` to obtain the intersection of
+    /// `lho` and `rho` (see [`Family::intersection`]).
+    ///
+    /// [`Family::intersection`]: crate::database::Family::intersection
+    pub(crate) fn intersection_transaction(
+        lho: &Collection<Item>,
+        rho: &Collection<Item>,
+    ) -> Result<TableTransaction<Item, ()>, Top<QueryError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let (lho_minus_rho, _) = Collection::diff(lho, rho);
+        let mut transaction = TableTransaction::default();
+
+        for item in lho_minus_rho {
+            transaction.remove(item)?;
+        }
+
+        Ok(transaction)
+    }
+
+    /// Returns the items to add to an empty collection to obtain the
+    /// difference between `lho` and `rho` (see [`Family::difference`]).
+    ///
+    /// [`Family::difference`]: crate::database::Family::difference
+    pub(crate) fn difference_transaction(
+        lho: &Collection<Item>,
+        rho: &Collection<Item>,
+    ) -> Result<TableTransaction<Item, ()>, Top<QueryError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let (lho_minus_rho, _) = Collection::diff(lho, rho);
+        let mut transaction = TableTransaction::default();
+
+        for item in lho_minus_rho {
+            transaction.set(item, ())?;
+        }
+
+        Ok(transaction)
+    }
+
+    /// Returns `true` if every item held by `self` is also held by `other`.
+    pub fn is_subset(&self, other: &Collection<Item>) -> bool
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        Table::diff_iter(&self.0, &other.0).all(|(_, in_self, _)| in_self.is_none())
+    }
 }
 
 impl<Item> Clone for Collection<Item>
@@ -58,3 +134,115 @@ where
         Collection(self.0.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::Family;
+
+    fn collection(
+        family: &Family<u32>,
+        name: &str,
+        items: impl Iterator<Item = u32>,
+    ) -> Collection<u32> {
+        let mut collection = family.empty_collection(name);
+        let mut transaction = CollectionTransaction::default();
+
+        for item in items {
+            transaction.insert(item).unwrap();
+        }
+
+        collection.execute(transaction);
+        collection
+    }
+
+    #[test]
+    fn algebra() {
+        let path = format!("test/{}", rand::random::<u64>());
+
+        {
+            let family: Family<u32> = Family::new(&path);
+
+            let lho = collection(&family, "lho", 0..512);
+            let rho = collection(&family, "rho", 256..768);
+
+            let union = family.union("union", &lho, &rho).unwrap();
+            let intersection = family.intersection("intersection", &lho, &rho).unwrap();
+            let difference = family.difference("difference", &lho, &rho).unwrap();
+
+            assert_eq!(union.commit(), collection(&family, "a", 0..768).commit());
+            assert_eq!(intersection.commit(), collection(&family, "b", 256..512).commit());
+            assert_eq!(difference.commit(), collection(&family, "c", 0..256).commit());
+
+            assert!(intersection.is_subset(&lho));
+            assert!(intersection.is_subset(&rho));
+            assert!(lho.is_subset(&union));
+            assert!(!lho.is_subset(&rho));
+            assert!(!union.is_subset(&difference));
+
+            // Operands are left untouched
+            assert_eq!(lho.commit(), collection(&family, "d", 0..512).commit());
+
+            // Names cannot be reused
+            assert!(family.union("union", &lho, &rho).is_err());
+        }
+
+        {
+            // Results are registered with the family, and restored
+            let family: Family<u32> = Family::new(&path);
+
+            let union = family.0.get_table("union").unwrap();
+            assert_eq!(union.commit(), collection(&family, "e", 0..768).commit());
+
+            let intersection = family.0.get_table("intersection").unwrap();
+            assert_eq!(intersection.commit(), collection(&family, "f", 256..512).commit());
+
+            let difference = family.0.get_table("difference").unwrap();
+            assert_eq!(difference.commit(), collection(&family, "g", 0..256).commit());
+
+            let lho = family.0.get_table("lho").unwrap();
+            assert_eq!(lho.commit(), collection(&family, "h", 0..512).commit());
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn algebra_across_families() {
+        let paths = [
+            format!("test/{}", rand::random::<u64>()),
+            format!("test/{}", rand::random::<u64>()),
+            format!("test/{}", rand::random::<u64>()),
+        ];
+
+        {
+            let alice: Family<u32> = Family::new(&paths[0]);
+            let bob: Family<u32> = Family::new(&paths[1]);
+
+            let lho = collection(&alice, "lho", 0..256);
+            let rho = collection(&bob, "rho", 128..384);
+
+            let union = alice.union("union", &lho, &rho).unwrap();
+            assert!(union.0.belongs_to(&alice.0.store));
+            assert_eq!(union.commit(), collection(&alice, "a", 0..384).commit());
+
+            // The operand of the family is shared, whichever side it is on
+            let intersection = bob.intersection("intersection", &lho, &rho).unwrap();
+            assert!(intersection.0.belongs_to(&bob.0.store));
+            assert_eq!(intersection.commit(), collection(&bob, "b", 128..256).commit());
+
+            let difference = bob.difference("difference", &rho, &lho).unwrap();
+            assert!(difference.0.belongs_to(&bob.0.store));
+            assert_eq!(difference.commit(), collection(&bob, "c", 256..384).commit());
+
+            // Neither operand can be shared by a third family
+            let carol: Family<u32> = Family::new(&paths[2]);
+            assert!(carol.union("union", &lho, &rho).is_err());
+        }
+
+        for path in paths {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    common::store::Field,
+    database::{errors::SyncError, Collection, Reconciliation, TableDiffer},
+};
+
+use doomstack::Top;
+
+use std::{collections::HashSet, hash::Hash as StdHash, sync::Arc};
+
+/// Reconciles a local [`Collection`] with the [`Collection`] of a remote
+/// [`Family`].
+///
+/// Reconciliation is symmetric: each peer creates a `CollectionReconciler`
+/// (see [`Family::reconcile`]) for its own [`Collection`], feeds it the
+/// [`CollectionAnswer`]s of the peer's [`CollectionSender`], and answers the
+/// peer's [`Question`]s with its own [`CollectionSender`]. As for any
+/// [`TableDiffer`], only the subtrees where the two [`Collection`]s differ
+/// are transferred. Once complete, each peer obtains a [`Reconciliation`],
+/// i.e., the two-sided difference between the [`Collection`]s.
+///
+/// [`Family`]: crate::database::Family
+/// [`Family::reconcile`]: crate::database::Family::reconcile
+/// [`CollectionAnswer`]: crate::database::CollectionAnswer
+/// [`CollectionSender`]: crate::database::CollectionSender
+/// [`Question`]: crate::database::Question
+pub type CollectionReconciler<Item> = TableDiffer<Item, ()>;
+
+impl<Item> TableDiffer<Item, ()>
+where
+    Item: Field,
+{
+    /// Returns the [`Reconciliation`] between the local and the remote
+    /// [`Collection`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`learn`] has not returned `None` yet.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`TableDiffer::finish`] does.
+    ///
+    /// [`learn`]: TableDiffer::learn
+    pub fn reconcile(self) -> Result<Reconciliation<Item>, Top<SyncError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let (diff, remote) = self.finish_remote()?;
+
+        let mut missing = HashSet::new();
+        let mut extra = HashSet::new();
+
+        for (item, (local, _)) in diff {
+            if local.is_some() {
+                extra.insert(item);
+            } else {
+                missing.insert(item);
+            }
+        }
+
+        Ok(Reconciliation {
+            missing,
+            extra,
+            remote: Collection(Arc::new(remote)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{CollectionSender, CollectionTransaction, Family};
+
+    fn collection(family: &Family<u32>, items: impl IntoIterator<Item = u32>) -> Collection<u32> {
+        let mut collection = family.empty_collection("test");
+        let mut transaction = CollectionTransaction::default();
+
+        for item in items {
+            transaction.insert(item).unwrap();
+        }
+
+        collection.execute(transaction);
+        collection
+    }
+
+    fn reconcile(
+        mut reconciler: CollectionReconciler<u32>,
+        sender: &CollectionSender<u32>,
+    ) -> (Reconciliation<u32>, usize) {
+        let mut answer = sender.hello();
+        let mut steps = 1;
+
+        while let Some(question) = reconciler.learn(answer).unwrap() {
+            steps += 1;
+            answer = sender.answer(&question).unwrap();
+        }
+
+        (reconciler.reconcile().unwrap(), steps)
+    }
+
+    fn families() -> (Family<u32>, Family<u32>, [String; 2]) {
+        let paths = [
+            format!("test/{}", rand::random::<u64>()),
+            format!("test/{}", rand::random::<u64>()),
+        ];
+
+        (Family::new(&paths[0]), Family::new(&paths[1]), paths)
+    }
+
+    #[test]
+    fn symmetric() {
+        let (alice, bob, paths) = families();
+
+        let alice_items = collection(&alice, 0..768);
+        let bob_items = collection(&bob, 256..1024);
+
+        let alice_sender = alice_items.clone().send();
+        let bob_sender = bob_items.clone().send();
+
+        let (alice_view, _) = reconcile(alice.reconcile(&alice_items), &bob_sender);
+        let (bob_view, _) = reconcile(bob.reconcile(&bob_items), &alice_sender);
+
+        assert_eq!(alice_view.missing, (768..1024).collect::<HashSet<_>>());
+        assert_eq!(alice_view.extra, (0..256).collect::<HashSet<_>>());
+
+        assert_eq!(alice_view.missing, bob_view.extra);
+        assert_eq!(alice_view.extra, bob_view.missing);
+
+        assert_eq!(alice_view.remote.commit(), bob_items.commit());
+        assert_eq!(bob_view.remote.commit(), alice_items.commit());
+
+        drop((alice_view, bob_view, alice_sender, bob_sender));
+        drop((alice_items, bob_items, alice, bob));
+
+        for path in paths {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn identical() {
+        let (alice, bob, paths) = families();
+
+        let alice_items = collection(&alice, 0..1024);
+        let bob_items = collection(&bob, 0..1024);
+
+        let bob_sender = bob_items.clone().send();
+        let (reconciliation, steps) = reconcile(alice.reconcile(&alice_items), &bob_sender);
+
+        // The root is held locally: nothing below it is asked for
+        assert_eq!(steps, 1);
+        assert!(reconciliation.missing.is_empty());
+        assert!(reconciliation.extra.is_empty());
+
+        drop((reconciliation, bob_sender));
+        drop((alice_items, bob_items, alice, bob));
+
+        for path in paths {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn few_differences() {
+        let (alice, bob, paths) = families();
+
+        let alice_items = collection(&alice, (0..1024).filter(|item| *item != 3));
+        let bob_items = collection(&bob, (0..1024).filter(|item| *item != 1000));
+
+        let alice_sender = alice_items.clone().send();
+
+        let mut reconciler = bob.reconcile(&bob_items);
+        reconciler.receiver.settings.window = usize::MAX;
+
+        let mut answer = alice_sender.hello();
+        let mut asked = 0;
+
+        while let Some(question) = reconciler.learn(answer).unwrap() {
+            asked += question.len();
+            answer = alice_sender.answer(&question).unwrap();
+        }
+
+        let reconciliation = reconciler.reconcile().unwrap();
+
+        assert_eq!(reconciliation.missing, HashSet::from([1000]));
+        assert_eq!(reconciliation.extra, HashSet::from([3]));
+
+        // Only the paths to the two differences are explored
+        assert!(asked < 64);
+
+        drop((reconciliation, alice_sender));
+        drop((alice_items, bob_items, alice, bob));
+
+        for path in paths {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+}
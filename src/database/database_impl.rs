@@ -1,13 +1,20 @@
-use std::{sync::{RwLock, Arc}, path::Path, io::{Write, Read}, collections::HashMap};
+use std::{sync::{RwLock, Arc}, path::Path, io::{ErrorKind, Write}, collections::HashMap};
 use crate::{
     common::store::Field,
     database::{
-        store::{Cell, Store},
-        Table, TableReceiver,
+        errors::{SyncError, TableError},
+        store::{Cell, Extractor, Handle, Store},
+        PersistentVector, Table, TableDiffer, TableReceiver, TableTransaction,
     },
+    map::Map,
+    vector::errors::VectorError,
 };
 
-use talk::sync::lenders::AtomicLender;
+use doomstack::{here, Doom, ResultExt, Top};
+
+use rocksdb::DB;
+
+use talk::{crypto::primitives::hash::Hash, sync::lenders::AtomicLender};
 
 /// A datastrucure for memory-efficient storage and transfer of maps with a
 /// large degree of similarity (% of key-pairs in common).
@@ -91,7 +98,14 @@ where
     Key: Field,
     Value: Field,
 {
-    /// Creates an empty `Database`.
+    /// Creates an empty `Database`, or restores the `Database` backed up at
+    /// `backup_path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the backup cannot be opened or restored (see [`open`]).
+    ///
+    /// [`open`]: Database::open
     ///
     /// # Examples
     ///
@@ -100,59 +114,89 @@ where
     /// let mut database: Database<String, i32> = Database::new("test");
     /// ```
     pub fn new(backup_path: &str) -> Self {
-        let store = Cell::new(AtomicLender::new(Store::new(backup_path)));
-        std::fs::create_dir_all(backup_path).unwrap();
-        //check if file exists and if not create it
-        if !Path::new(backup_path).join("tables").exists() {
-            let _ = std::fs::File::create(Path::new(backup_path).join("tables")).unwrap();
+        match Self::open(backup_path) {
+            Ok(database) => database,
+            Err(error) => panic!("failed to open `Database`: {:?}", error),
         }
-        let mut file = std::fs::File::open(Path::new(backup_path).join("tables")).unwrap();
+    }
 
-        let mut serialized = Vec::new();
-        file.read_to_end(&mut serialized).unwrap();
+    /// Like [`new`], failing instead of panicking if the backup cannot be
+    /// opened or restored.
+    ///
+    /// # Errors
+    ///
+    /// If the backup cannot be read, [`StorageError`] is returned. If some of
+    /// its records cannot be decoded, [`BackupCorrupt`] is returned.
+    ///
+    /// [`new`]: Database::new
+    /// [`StorageError`]: crate::database::errors::TableError::StorageError
+    /// [`BackupCorrupt`]: crate::database::errors::TableError::BackupCorrupt
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::Database;
+    /// let database: Database<String, i32> = Database::open("test").unwrap();
+    /// ```
+    pub fn open(backup_path: &str) -> Result<Self, Top<TableError>> {
+        std::fs::create_dir_all(backup_path).pot(TableError::StorageError, here!())?;
 
-        // check if serialized is empty
-        if !serialized.is_empty() {
-            let tables = bincode::deserialize::<Vec<String>>(&serialized).unwrap()
-            .iter().map(|f|(f.to_string(), Arc::new(Table::empty(store.clone(), f.to_string())))).collect::<HashMap<String, Arc<Table<Key, Value>>>>();
-            
-            let (new_store, table_transactions) = store.take().restore_backup(&tables);
-            store.restore(new_store);
+        let store = Cell::new(AtomicLender::new(Store::new(backup_path)?));
 
-            for (_, (table, transaction)) in table_transactions {
-                table.execute(transaction);
-            }
+        // A missing list of tables is that of an empty `Database`
+        let serialized = match std::fs::read(Path::new(backup_path).join("tables")) {
+            Ok(serialized) => serialized,
+            Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error).pot(TableError::StorageError, here!()),
+        };
 
-            Database {
-                store: store.clone(),
-                tables: RwLock::new(tables.values().cloned().collect()),
-                backup_path: backup_path.to_string(),
-            }
+        let names = if serialized.is_empty() {
+            Vec::new()
         } else {
-            Database {
-                store: store.clone(),
-                tables: RwLock::new(Vec::new()),
-                backup_path: backup_path.to_string(),
-            }
+            bincode::deserialize::<Vec<String>>(&serialized)
+                .pot(TableError::BackupCorrupt, here!())?
+        };
+
+        let tables = names
+            .iter()
+            .map(|name| (name.clone(), Arc::new(Table::empty(store.clone(), name.clone()))))
+            .collect::<HashMap<String, Arc<Table<Key, Value>>>>();
+
+        let restore = {
+            let taken = store.take();
+            let restore = taken.restore_backup(&tables);
+            store.restore(taken);
+            restore
+        };
+
+        for (_, (table, transaction)) in restore? {
+            table.execute(transaction);
         }
 
+        let tables = names.iter().map(|name| tables[name].clone()).collect();
 
+        Ok(Database {
+            store,
+            tables: RwLock::new(tables),
+            backup_path: backup_path.to_string(),
+        })
     }
 
     /// Adds a [`Table`] to the `Database` and store it on the disk.
     pub(crate) fn add_table(&self, table: Arc<Table<Key, Value>>) {
-        let table_exists = self.tables.read().unwrap().iter().any(|f|f.get_name() == table.get_name());
-        if table_exists {
+        let mut tables = self.tables.write().unwrap();
+
+        if tables.iter().any(|f| f.get_name() == table.get_name()) {
             return;
         }
 
-        self.tables.write().unwrap().push(table);
+        tables.push(table);
+        self.write_tables(&tables);
+    }
 
-        let tables = self.tables.read()
-        .unwrap()
-        .iter()
-        .map(|f|f.get_name())
-        .collect::<Vec<String>>();
+    /// Stores the names of `tables` on the disk.
+    fn write_tables(&self, tables: &[Arc<Table<Key, Value>>]) {
+        let tables = tables.iter().map(|f| f.get_name()).collect::<Vec<String>>();
 
         let serialized = bincode::serialize(&tables).unwrap();
         let mut file: std::fs::File = std::fs::File::create(Path::new(&self.backup_path).join("tables")).unwrap();
@@ -179,6 +223,274 @@ where
         table
     }
 
+    /// Creates and assigns to the `Database` a [`Table`] holding the records of
+    /// `map`. The nodes of `map` are stored as they are, without hashing its
+    /// keys and values again; nodes already in the `Database` are shared.
+    ///
+    /// See [`Table::to_map`] for the converse.
+    ///
+    /// # Errors
+    ///
+    /// If the `Database` already has a table named `name`, [`NameTaken`] is
+    /// returned. If a branch of `map` is replaced by a `Stub` (see
+    /// [`Map::is_complete`]), [`BranchUnknown`] is returned. If the records of
+    /// `map` cannot be backed up, [`BackupFailed`] is returned. In all cases,
+    /// no table is added and nothing is written to the backup.
+    ///
+    /// [`Table::to_map`]: crate::database::Table::to_map
+    /// [`Map::is_complete`]: crate::map::Map::is_complete
+    /// [`NameTaken`]: crate::database::errors::TableError::NameTaken
+    /// [`BranchUnknown`]: crate::database::errors::TableError::BranchUnknown
+    /// [`BackupFailed`]: crate::database::errors::TableError::BackupFailed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::{database::Database, map::Map};
+    ///
+    /// # let path = format!("test/{}", rand::random::<u64>());
+    /// let database: Database<u32, u32> = Database::new(&path);
+    /// let map: Map<u32, u32> = (0..4).map(|key| (key, key + 1)).collect();
+    ///
+    /// let table = database.table_from_map("test", &map).unwrap();
+    /// assert_eq!(table.commit(), map.commit());
+    ///
+    /// assert!(database.table_from_map("test", &map).is_err());
+    ///
+    /// let export = map.export([0]).unwrap();
+    /// assert!(database.table_from_map("test2", &export).is_err());
+    /// # std::fs::remove_dir_all(path).unwrap();
+    /// ```
+    pub fn table_from_map(
+        &self,
+        name: &str,
+        map: &Map<Key, Value>,
+    ) -> Result<Arc<Table<Key, Value>>, Top<TableError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        if !map.is_complete() {
+            return TableError::BranchUnknown.fail().spot(here!());
+        }
+
+        // `tables` stays locked until the table is added, so that no other
+        // table can take `name` after it is checked
+        let mut tables = self.tables.write().unwrap();
+
+        if tables.iter().any(|table| table.get_name() == name) {
+            return TableError::NameTaken.fail().spot(here!());
+        }
+
+        let handle = Handle::from_map(self.store.clone(), name.to_string(), map.root())?;
+        let table = Arc::new(Table::from_handle(handle, name.to_string()));
+
+        tables.push(table.clone());
+        self.write_tables(&tables);
+
+        Ok(table)
+    }
+
+    /// Creates and assigns to the `Database` a [`Table`] named `name`, holding
+    /// the records of `base` (if any) updated by `transaction`.
+    ///
+    /// The new table shares the nodes of `base`: only `transaction` is applied
+    /// to the tree. The backup of `base` is copied within RocksDB, in chunks,
+    /// rather than rebuilt from the tree. `base` must be assigned to the `Database`, otherwise
+    /// [`ForeignTable`] is returned. If the `Database` already has a table
+    /// named `name`, [`NameTaken`] is returned.
+    ///
+    /// [`ForeignTable`]: crate::database::errors::TableError::ForeignTable
+    /// [`NameTaken`]: crate::database::errors::TableError::NameTaken
+    pub(crate) fn derive_table(
+        &self,
+        name: &str,
+        base: Option<&Table<Key, Value>>,
+        transaction: TableTransaction<Key, Value>,
+    ) -> Result<Arc<Table<Key, Value>>, Top<TableError>> {
+        // `tables` stays locked until the table is added, so that no other
+        // table can take `name` after it is checked
+        let mut tables = self.tables.write().unwrap();
+
+        if tables.iter().any(|table| table.get_name() == name) {
+            return TableError::NameTaken.fail().spot(here!());
+        }
+
+        let table = match base {
+            Some(base) => {
+                let assigned = base.belongs_to(&self.store)
+                    && tables.iter().any(|table| table.get_name() == base.get_name());
+
+                if !assigned {
+                    return TableError::ForeignTable.fail().spot(here!());
+                }
+
+                base.fork(name)?
+            }
+            None => Table::empty(self.store.clone(), name.to_string()),
+        };
+
+        let table = Arc::new(table);
+        table.execute(transaction);
+
+        tables.push(table.clone());
+        self.write_tables(&tables);
+
+        Ok(table)
+    }
+
+    /// Returns `true` if `table` can be the base of `derive_table`.
+    pub(crate) fn is_assigned(&self, table: &Table<Key, Value>) -> bool {
+        table.belongs_to(&self.store) && self.get_table(&table.get_name()).is_some()
+    }
+
+    /// Merges `ours` and `theirs`, two tables forked from `base`, into a new
+    /// [`Table`] named `name`, assigned to the `Database`.
+    ///
+    /// Changes are merged as by [`Table::merge`]. The merged table shares the
+    /// nodes of `ours`, to which only the merged changes are applied, and its
+    /// backup starts from a copy of that of `ours`. `base`, `ours` and
+    /// `theirs` are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// If the `Database` already has a table named `name`, [`NameTaken`] is
+    /// returned. If `ours` is not assigned to the `Database`, [`ForeignTable`]
+    /// is returned. If a `Key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`Table::merge`]: crate::database::Table::merge
+    /// [`NameTaken`]: crate::database::errors::TableError::NameTaken
+    /// [`ForeignTable`]: crate::database::errors::TableError::ForeignTable
+    /// [`HashError`]: crate::database::errors::TableError::HashError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, Table, TableTransaction};
+    ///
+    /// # let path = format!("test/{}", rand::random::<u64>());
+    /// let database: Database<u32, u32> = Database::new(&path);
+    /// let base = database.empty_table("base");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 0).unwrap();
+    /// base.execute(transaction);
+    ///
+    /// let ours = database.empty_table("ours");
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 10).unwrap();
+    /// ours.execute(transaction);
+    ///
+    /// let theirs = database.empty_table("theirs");
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 0).unwrap();
+    /// transaction.set(1, 21).unwrap();
+    /// theirs.execute(transaction);
+    ///
+    /// let merged = database
+    ///     .merge_tables("merged", &base, &ours, &theirs, |_, _, ours, _| ours.cloned())
+    ///     .unwrap();
+    ///
+    /// let reference = Table::merge(&base, &ours, &theirs, |_, _, ours, _| ours.cloned()).unwrap();
+    ///
+    /// assert_eq!(merged.commit(), reference.commit());
+    /// assert!(database.get_table("merged").is_some());
+    /// # std::fs::remove_dir_all(path).unwrap();
+    /// ```
+    pub fn merge_tables<F>(
+        &self,
+        name: &str,
+        base: &Table<Key, Value>,
+        ours: &Table<Key, Value>,
+        theirs: &Table<Key, Value>,
+        resolver: F,
+    ) -> Result<Arc<Table<Key, Value>>, Top<TableError>>
+    where
+        Key: Clone + Eq,
+        Value: Clone + Eq,
+        F: FnMut(&Key, Option<&Value>, Option<&Value>, Option<&Value>) -> Option<Value>,
+    {
+        let transaction = Table::merge_transaction(base, ours, theirs, resolver)
+            .pot(TableError::HashError, here!())?;
+
+        self.derive_table(name, Some(ours), transaction)
+    }
+
+    /// Registers an index, named `index`, over the values of the table named
+    /// `table`: each record is indexed under the key returned by `extractor`,
+    /// enabling [`Table::lookup_by`].
+    ///
+    /// Entries are built from the records of `table`, then stored alongside
+    /// its backup, which [`Table::execute`] updates atomically. Registering an
+    /// index again rebuilds its entries.
+    ///
+    /// Extractors are not persisted: after restoring a `Database`, its indexes
+    /// must be registered again before [`Table::lookup_by`] can use them. The
+    /// entries left by a previous run are kept until then, and replaced on
+    /// registration.
+    ///
+    /// # Errors
+    ///
+    /// If the `Database` has no table named `table`, [`TableMissing`] is
+    /// returned. If the entries cannot be stored, [`BackupFailed`] is
+    /// returned, and if the records of `table` cannot be decoded,
+    /// [`BackupCorrupt`] is returned. In both cases, the index is not
+    /// registered.
+    ///
+    /// [`Table::lookup_by`]: crate::database::Table::lookup_by
+    /// [`Table::execute`]: crate::database::Table::execute
+    /// [`TableMissing`]: crate::database::errors::TableError::TableMissing
+    /// [`BackupFailed`]: crate::database::errors::TableError::BackupFailed
+    /// [`BackupCorrupt`]: crate::database::errors::TableError::BackupCorrupt
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableTransaction};
+    ///
+    /// # let path = format!("test/{}", rand::random::<u64>());
+    /// let database: Database<u32, u32> = Database::new(&path);
+    /// let table = database.empty_table("test");
+    ///
+    /// database.register_index("test", "parity", |_, value| value % 2).unwrap();
+    /// assert!(database.register_index("missing", "parity", |_, value| value % 2).is_err());
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// for key in 0..4 {
+    ///     transaction.set(key, key + 1).unwrap();
+    /// }
+    /// table.execute(transaction);
+    ///
+    /// let mut odd = table.lookup_by("parity", &1).unwrap();
+    /// odd.sort();
+    ///
+    /// assert_eq!(odd, vec![(0, 1), (2, 3)]);
+    /// # std::fs::remove_dir_all(path).unwrap();
+    /// ```
+    pub fn register_index<IndexKey, F>(
+        &self,
+        table: &str,
+        index: &str,
+        extractor: F,
+    ) -> Result<(), Top<TableError>>
+    where
+        IndexKey: Field,
+        F: Fn(&Key, &Value) -> IndexKey + Send + Sync + 'static,
+    {
+        if self.get_table(table).is_none() {
+            return TableError::TableMissing.fail().spot(here!());
+        }
+
+        let extractor: Extractor<Key, Value> =
+            Arc::new(move |key, value| bincode::serialize(&extractor(key, value)).unwrap());
+
+        let mut store = self.store.take();
+        let result = store.register_index(table, index, extractor);
+        self.store.restore(store);
+
+        result
+    }
+
     /// Creates a [`TableReceiver`] assigned to this `Database`. The
     /// receiver is used to efficiently receive a [`Table`]
     /// from other databases and add them this one.
@@ -199,6 +511,251 @@ where
     pub fn receive(&self) -> TableReceiver<Key, Value> {
         TableReceiver::new(self.store.clone())
     }
+
+    /// Creates a [`TableReceiver`] that only accepts the [`Table`] whose
+    /// commitment (see [`Table::commit`]) is `commit`.
+    ///
+    /// A sender serving a different table is rejected with
+    /// [`SyncError::RootMismatch`]. Since the root is known in advance, the
+    /// receiver can start asking right away (see [`TableReceiver::ask`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableStatus, TableTransaction};
+    ///
+    /// let alice: Database<u32, u32> = Database::new("test");
+    /// let bob: Database<u32, u32> = Database::new("test2");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 1).unwrap();
+    ///
+    /// let table = alice.empty_table("test");
+    /// table.execute(transaction);
+    ///
+    /// let sender = table.send();
+    /// let mut receiver = bob.receive_expecting(table.commit());
+    ///
+    /// let received = loop {
+    ///     let answer = sender.answer(&receiver.ask()).unwrap();
+    ///
+    ///     match receiver.learn(answer).unwrap() {
+    ///         TableStatus::Complete(table) => break table,
+    ///         TableStatus::Incomplete(next, _) => receiver = next,
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(received.commit(), table.commit());
+    /// ```
+    ///
+    /// [`Table::commit`]: crate::database::Table::commit
+    /// [`SyncError::RootMismatch`]: crate::database::errors::SyncError::RootMismatch
+    pub fn receive_expecting(&self, commit: Hash) -> TableReceiver<Key, Value> {
+        let mut receiver = TableReceiver::new(self.store.clone());
+        receiver.expect(commit.into());
+        receiver
+    }
+
+    /// Resumes the receive checkpointed under `id` (see
+    /// [`TableReceiver::checkpoint`]), e.g., after a restart. Returns `None`
+    /// if no such checkpoint exists, or if the receive already completed.
+    ///
+    /// Nodes acquired before the checkpoint are not asked again.
+    ///
+    /// # Errors
+    ///
+    /// If the checkpoint's state or some of its nodes are missing or cannot
+    /// be decoded, [`CorruptCheckpoint`] is returned. The checkpoint can then
+    /// be removed with [`discard_receive`]. If the storage cannot be read,
+    /// [`StorageError`] is returned.
+    ///
+    /// [`CorruptCheckpoint`]: crate::database::errors::SyncError::CorruptCheckpoint
+    /// [`StorageError`]: crate::database::errors::SyncError::StorageError
+    /// [`discard_receive`]: Database::discard_receive
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableStatus, TableTransaction};
+    ///
+    /// let alice: Database<u32, u32> = Database::new("test");
+    /// let bob: Database<u32, u32> = Database::new("test2");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 1).unwrap();
+    ///
+    /// let table = alice.empty_table("test");
+    /// table.execute(transaction);
+    ///
+    /// let sender = table.send();
+    /// let id = bob.receive_expecting(table.commit()).checkpoint().unwrap();
+    ///
+    /// // Later on...
+    ///
+    /// let mut receiver = bob.resume_receive(id).unwrap().unwrap();
+    ///
+    /// let received = loop {
+    ///     let answer = sender.answer(&receiver.ask()).unwrap();
+    ///
+    ///     match receiver.learn(answer).unwrap() {
+    ///         TableStatus::Complete(table) => break table,
+    ///         TableStatus::Incomplete(next, _) => receiver = next,
+    ///     }
+    /// };
+    ///
+    /// assert_eq!(received.commit(), table.commit());
+    /// assert!(bob.resume_receive(id).unwrap().is_none());
+    /// ```
+    pub fn resume_receive(
+        &self,
+        id: u64,
+    ) -> Result<Option<TableReceiver<Key, Value>>, Top<SyncError>> {
+        TableReceiver::resume(self.store.clone(), id)
+    }
+
+    /// Returns the ids of the receives checkpointed in the `Database`'s
+    /// storage (see [`TableReceiver::checkpoint`]), which can be resumed by
+    /// [`resume_receive`] or removed by [`discard_receive`].
+    ///
+    /// [`resume_receive`]: Database::resume_receive
+    /// [`discard_receive`]: Database::discard_receive
+    pub fn receive_checkpoints(&self) -> Vec<u64> {
+        TableReceiver::checkpoints(&self.store)
+    }
+
+    /// Removes the receive checkpointed under `id` from the `Database`'s
+    /// storage, along with the nodes it acquired, e.g., if the receive was
+    /// abandoned or its checkpoint is corrupt. Returns `false` if no such
+    /// checkpoint exists.
+    ///
+    /// A [`TableReceiver`] still checkpointing under `id` should be dropped
+    /// first, as its next checkpoint would miss the nodes removed.
+    ///
+    /// # Errors
+    ///
+    /// If the checkpoint cannot be removed from the storage, [`StorageError`]
+    /// is returned.
+    ///
+    /// [`StorageError`]: crate::database::errors::SyncError::StorageError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableTransaction};
+    ///
+    /// let alice: Database<u32, u32> = Database::new("test");
+    /// let bob: Database<u32, u32> = Database::new("test2");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 1).unwrap();
+    ///
+    /// let table = alice.empty_table("test");
+    /// table.execute(transaction);
+    ///
+    /// let id = bob.receive_expecting(table.commit()).checkpoint().unwrap();
+    ///
+    /// assert!(bob.receive_checkpoints().contains(&id));
+    /// assert!(bob.discard_receive(id).unwrap());
+    ///
+    /// assert!(!bob.receive_checkpoints().contains(&id));
+    /// assert!(bob.resume_receive(id).unwrap().is_none());
+    /// assert!(!bob.discard_receive(id).unwrap());
+    /// ```
+    pub fn discard_receive(&self, id: u64) -> Result<bool, Top<SyncError>> {
+        TableReceiver::discard(&self.store, id)
+    }
+
+    /// Creates a [`TableDiffer`] to compute the difference between `local`, a
+    /// [`Table`] of this `Database`, and a [`Table`] of a remote `Database`,
+    /// without copying the remote [`Table`] in full.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `local` belongs to a different `Database`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableTransaction};
+    ///
+    /// let alice: Database<u32, u32> = Database::new("test");
+    /// let bob: Database<u32, u32> = Database::new("test2");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 1).unwrap();
+    ///
+    /// let remote = alice.empty_table("test");
+    /// remote.execute(transaction);
+    ///
+    /// let local = bob.empty_table("test");
+    ///
+    /// let sender = remote.send();
+    /// let mut differ = bob.diff_remote(&local);
+    /// let mut answer = sender.hello();
+    ///
+    /// while let Some(question) = differ.learn(answer).unwrap() {
+    ///     answer = sender.answer(&question).unwrap();
+    /// }
+    ///
+    /// let diff = differ.finish().unwrap();
+    /// assert_eq!(diff.get(&0), Some(&(None, Some(1))));
+    /// ```
+    pub fn diff_remote(&self, local: &Table<Key, Value>) -> TableDiffer<Key, Value> {
+        if !local.belongs_to(&self.store) {
+            panic!("called `Database::diff_remote` with a `Table` of another `Database`");
+        }
+
+        TableDiffer::new(local.clone(), self.receive())
+    }
+
+    /// Creates a [`PersistentVector`] named `name` in the `Database`'s
+    /// storage, replacing any vector previously stored under that name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, PersistentVector};
+    /// let database: Database<String, i32> = Database::new("test");
+    ///
+    /// let log: PersistentVector<u32> = database.new_vector("log", vec![0, 1, 2]).unwrap();
+    /// ```
+    pub fn new_vector<Item, const PACKING: usize>(
+        &self,
+        name: &str,
+        items: Vec<Item>,
+    ) -> Result<PersistentVector<Item, PACKING>, Top<VectorError>>
+    where
+        Item: Field,
+    {
+        PersistentVector::new(self.db(), name, items)
+    }
+
+    /// Reopens the [`PersistentVector`] named `name`, if any.
+    ///
+    /// # Errors
+    ///
+    /// If the vector was stored with a different `PACKING`,
+    /// [`PackingMismatch`] is returned. If some of its items or hashes are
+    /// missing or cannot be decoded, [`CorruptStorage`] is returned.
+    ///
+    /// [`PackingMismatch`]: crate::vector::errors::VectorError::PackingMismatch
+    /// [`CorruptStorage`]: crate::vector::errors::VectorError::CorruptStorage
+    pub fn get_vector<Item, const PACKING: usize>(
+        &self,
+        name: &str,
+    ) -> Result<Option<PersistentVector<Item, PACKING>>, Top<VectorError>>
+    where
+        Item: Field,
+    {
+        PersistentVector::load(self.db(), name)
+    }
+
+    fn db(&self) -> Arc<DB> {
+        let store = self.store.take();
+        let db = store.db.clone();
+        self.store.restore(store);
+        db
+    }
 }
 
 impl<Key, Value> Default for Database<Key, Value>
@@ -442,4 +999,240 @@ mod tests {
         std::fs::remove_dir_all(path).unwrap();
 
     }
+
+    #[test]
+    fn table_from_map() {
+        Database::test_database(|database: Database<u32, u32>| {
+            let table = database.table_with_records((0..256).map(|i| (i, i)));
+
+            let map: Map<u32, u32> = (128..512).map(|i| (i, i)).collect();
+            let imported = database.table_from_map("imported", &map).unwrap();
+
+            assert_eq!(imported.commit(), map.commit());
+            imported.assert_records((128..512).map(|i| (i, i)));
+            database.check_correctness([table.as_ref(), imported.as_ref()], []);
+
+            let mut transaction = TableTransaction::default();
+            for i in 128..256 {
+                transaction.remove(i).unwrap();
+            }
+            imported.execute(transaction);
+
+            imported.assert_records((256..512).map(|i| (i, i)));
+            table.assert_records((0..256).map(|i| (i, i)));
+            database.check_correctness([table.as_ref(), imported.as_ref()], []);
+
+            let export = map.export([0]).unwrap();
+            assert!(database.table_from_map("export", &export).is_err());
+            assert!(database.get_table("export").is_none());
+        });
+    }
+
+    #[test]
+    fn table_from_map_is_restored() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+        let map: Map<u32, u32> = (0..256).map(|i| (i, i + 1)).collect();
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            database.table_from_map("imported", &map).unwrap();
+
+            // A taken name leaves the backup untouched
+            let other: Map<u32, u32> = (256..512).map(|i| (i, i)).collect();
+            assert!(database.table_from_map("imported", &other).is_err());
+        }
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.get_table("imported").unwrap();
+
+            assert_eq!(table.commit(), map.commit());
+            table.assert_records((0..256).map(|i| (i, i + 1)));
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn range_after_restore() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+        let map: Map<u32, u32> = (0..256).map(|i| (i, i + 1)).collect();
+
+        let commit = {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.table_from_map("imported", &map).unwrap();
+
+            assert_eq!(table.range(16..20), (16..20).map(|i| (i, i + 1)).collect::<Vec<_>>());
+            table.index_commit()
+        };
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.get_table("imported").unwrap();
+
+            // The index of a restored table is built again from its records
+            assert_eq!(table.range(16..20), (16..20).map(|i| (i, i + 1)).collect::<Vec<_>>());
+            assert_eq!(table.index_commit(), commit);
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    fn lookup(table: &Table<u32, u32>, index: &str, key: u32) -> Vec<(u32, u32)> {
+        let mut records = table.lookup_by(index, &key).unwrap();
+        records.sort_unstable();
+        records
+    }
+
+    #[test]
+    fn register_index() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.empty_table("indexed");
+
+            assert!(table.lookup_by("residue", &0u32).is_err());
+
+            let mut transaction = TableTransaction::default();
+            for key in 0..64 {
+                transaction.set(key, key).unwrap();
+            }
+            table.execute(transaction);
+
+            assert!(database.register_index("missing", "residue", |_, value| value % 4).is_err());
+
+            // Records executed before registration are indexed
+            database.register_index("indexed", "residue", |_, value| value % 4).unwrap();
+            assert_eq!(
+                lookup(&table, "residue", 1),
+                (1..64).step_by(4).map(|i| (i, i)).collect::<Vec<_>>()
+            );
+
+            let snapshot = (*table).clone();
+
+            let mut transaction = TableTransaction::default();
+            transaction.set(1, 2).unwrap();
+            transaction.set(64, 65).unwrap();
+            transaction.remove(5).unwrap();
+            table.execute(transaction);
+
+            let mut expected = (9..64).step_by(4).map(|i| (i, i)).collect::<Vec<_>>();
+            expected.push((64, 65));
+
+            assert_eq!(lookup(&table, "residue", 1), expected);
+            assert_eq!(lookup(&table, "residue", 2)[..2], [(1, 2), (2, 2)]);
+
+            // Records of `snapshot` changed since are not returned
+            assert_eq!(lookup(&snapshot, "residue", 1)[0], (9, 9));
+        }
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let table = database.get_table("indexed").unwrap();
+
+            assert!(table.lookup_by("residue", &0u32).is_err());
+
+            // Entries are rebuilt by the new extractor
+            database.register_index("indexed", "residue", |key, _| key % 2).unwrap();
+            assert_eq!(lookup(&table, "residue", 1).len(), 31); // Key 5 was removed
+            assert_eq!(lookup(&table, "residue", 0).len(), 33);
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn open_corrupt_backup() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            database.table_with_records((0..4).map(|i| (i, i)));
+        }
+
+        {
+            // A `u32` value is encoded on four bytes
+            let store = Store::<u32, u32>::new(&path).unwrap();
+            store.db.put(bincode::serialize(&("test", 0u32)).unwrap(), [0]).unwrap();
+        }
+
+        assert!(Database::<u32, u32>::open(&path).is_err());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    fn fork(
+        database: &Database<u32, u32>,
+        name: &str,
+        base: &Table<u32, u32>,
+    ) -> Arc<Table<u32, u32>> {
+        database
+            .derive_table(name, Some(base), TableTransaction::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn merge_tables_is_restored() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+            let base = database.table_with_records((0..64).map(|i| (i, i)));
+
+            let ours = fork(&database, "ours", &base);
+            let mut transaction = TableTransaction::default();
+            transaction.set(0, 1).unwrap();
+            ours.execute(transaction);
+
+            let theirs = fork(&database, "theirs", &base);
+            let mut transaction = TableTransaction::default();
+            transaction.set(1, 2).unwrap();
+            theirs.execute(transaction);
+
+            let merged = database
+                .merge_tables("merged", &base, &ours, &theirs, |_, _, ours, _| ours.cloned())
+                .unwrap();
+
+            // Names cannot be reused
+            assert!(database
+                .merge_tables("merged", &base, &ours, &theirs, |_, _, ours, _| ours.cloned())
+                .is_err());
+
+            // Merging into an unassigned table leaves the backup of `ours` untouched
+            let unassigned =
+                Table::merge(&base, &ours, &theirs, |_, _, ours, _| ours.cloned()).unwrap();
+            assert_eq!(unassigned.commit(), merged.commit());
+
+            let mut transaction = TableTransaction::default();
+            transaction.set(2, 3).unwrap();
+            unassigned.execute(transaction);
+
+            database.check_correctness(
+                [base.as_ref(), ours.as_ref(), theirs.as_ref(), merged.as_ref(), &unassigned],
+                [],
+            );
+
+            // Only tables assigned to the `Database` can be merged into
+            assert!(database
+                .merge_tables("foreign", &base, &unassigned, &theirs, |_, _, ours, _| {
+                    ours.cloned()
+                })
+                .is_err());
+        }
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+
+            let base = database.get_table("test").unwrap();
+            let ours = database.get_table("ours").unwrap();
+            let merged = database.get_table("merged").unwrap();
+
+            base.assert_records((0..64).map(|i| (i, i)));
+            ours.assert_records((0..64).map(|i| (i, if i == 0 { 1 } else { i })));
+            merged.assert_records((0..64).map(|i| (i, if i < 2 { i + 1 } else { i })));
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }
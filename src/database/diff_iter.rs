@@ -0,0 +1,360 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Path},
+    },
+    database::{
+        interact::diff,
+        store::{Handle, Label, Node, Store},
+    },
+};
+
+use std::{collections::VecDeque, ptr};
+
+/// A lazy iterator over the difference between two [`Table`]s, created by
+/// [`Table::diff_iter`].
+///
+/// Yields `(key, lho_value, rho_value)` for each key whose value differs,
+/// in path order (i.e., the order of the keys' leaves in the tree). Subtrees
+/// with matching labels are skipped, and only the subtrees along the
+/// current path are buffered: records are retrieved one at a time, as the
+/// iterator is advanced.
+///
+/// The iterator compares the versions of the two [`Table`]s at the time of
+/// its creation: [`Table::execute`]-ing on either table does not affect it.
+///
+/// [`Table`]: crate::database::Table
+/// [`Table::diff_iter`]: crate::database::Table::diff_iter
+/// [`Table::execute`]: crate::database::Table::execute
+pub struct DiffIter<Key: Field, Value: Field> {
+    lho: Handle<Key, Value>,
+    rho: Handle<Key, Value>,
+    stack: Vec<(u8, Label, Label)>,
+    ready: VecDeque<(Key, Option<Value>, Option<Value>)>,
+}
+
+enum Stores<Key: Field, Value: Field> {
+    Shared(Store<Key, Value>),
+    Split(Store<Key, Value>, Store<Key, Value>),
+}
+
+impl<Key, Value> DiffIter<Key, Value>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    pub(crate) fn new(lho: Handle<Key, Value>, rho: Handle<Key, Value>) -> Self {
+        let stack = vec![(0, *lho.root.read().unwrap(), *rho.root.read().unwrap())];
+
+        DiffIter {
+            lho,
+            rho,
+            stack,
+            ready: VecDeque::new(),
+        }
+    }
+
+    fn take_stores(&self) -> Stores<Key, Value> {
+        let lho = self.lho.cell.as_ref();
+        let rho = self.rho.cell.as_ref();
+
+        if ptr::eq(lho, rho) {
+            Stores::Shared(self.lho.cell.take())
+        } else if (lho as *const _ as usize) < (rho as *const _ as usize) {
+            // Cells are always taken in the same order (see `Handle::diff`)
+            let lho_store = self.lho.cell.take();
+            Stores::Split(lho_store, self.rho.cell.take())
+        } else {
+            let rho_store = self.rho.cell.take();
+            Stores::Split(self.lho.cell.take(), rho_store)
+        }
+    }
+
+    fn restore_stores(&self, stores: Stores<Key, Value>) {
+        match stores {
+            Stores::Shared(store) => self.lho.cell.restore(store),
+            Stores::Split(lho_store, rho_store) => {
+                self.lho.cell.restore(lho_store);
+                self.rho.cell.restore(rho_store);
+            }
+        }
+    }
+
+    fn step(&mut self, stores: &mut Stores<Key, Value>, depth: u8, lho: Label, rho: Label) {
+        if lho.hash() == rho.hash() {
+            return;
+        }
+
+        let lho_node = diff::get(stores.lho(), lho);
+        let rho_node = diff::get(stores.rho(), rho);
+
+        match (lho_node, rho_node) {
+            (Node::Internal(lho_left, lho_right), Node::Internal(rho_left, rho_right)) => {
+                self.stack.push((depth + 1, lho_right, rho_right));
+                self.stack.push((depth + 1, lho_left, rho_left));
+            }
+            (Node::Internal(lho_left, lho_right), rho_node) => {
+                let (rho_left, rho_right) = DiffIter::route(rho, &rho_node, depth);
+
+                self.stack.push((depth + 1, lho_right, rho_right));
+                self.stack.push((depth + 1, lho_left, rho_left));
+            }
+            (lho_node, Node::Internal(rho_left, rho_right)) => {
+                let (lho_left, lho_right) = DiffIter::route(lho, &lho_node, depth);
+
+                self.stack.push((depth + 1, lho_right, rho_right));
+                self.stack.push((depth + 1, lho_left, rho_left));
+            }
+            (Node::Leaf(lho_key, lho_value), Node::Leaf(rho_key, rho_value)) => {
+                if lho_key == rho_key {
+                    self.ready.push_back((
+                        (**lho_key.inner()).clone(),
+                        Some((**lho_value.inner()).clone()),
+                        Some((**rho_value.inner()).clone()),
+                    ));
+                } else {
+                    let lho_record = (
+                        (**lho_key.inner()).clone(),
+                        Some((**lho_value.inner()).clone()),
+                        None,
+                    );
+
+                    let rho_record = (
+                        (**rho_key.inner()).clone(),
+                        None,
+                        Some((**rho_value.inner()).clone()),
+                    );
+
+                    // `Direction::Left` is bit 1: the greater path comes first
+                    if Path::from(lho_key.digest()) > Path::from(rho_key.digest()) {
+                        self.ready.push_back(lho_record);
+                        self.ready.push_back(rho_record);
+                    } else {
+                        self.ready.push_back(rho_record);
+                        self.ready.push_back(lho_record);
+                    }
+                }
+            }
+            (Node::Leaf(key, value), Node::Empty) => {
+                self.ready.push_back((
+                    (**key.inner()).clone(),
+                    Some((**value.inner()).clone()),
+                    None,
+                ));
+            }
+            (Node::Empty, Node::Leaf(key, value)) => {
+                self.ready.push_back((
+                    (**key.inner()).clone(),
+                    None,
+                    Some((**value.inner()).clone()),
+                ));
+            }
+            (Node::Empty, Node::Empty) => unreachable!(),
+        }
+    }
+
+    // Pushes a leaf (or empty) node one level down, towards its key path
+    fn route(label: Label, node: &Node<Key, Value>, depth: u8) -> (Label, Label) {
+        match node {
+            Node::Leaf(key, _) => {
+                if Path::from(key.digest())[depth] == Direction::Left {
+                    (label, Label::Empty)
+                } else {
+                    (Label::Empty, label)
+                }
+            }
+            _ => (Label::Empty, Label::Empty),
+        }
+    }
+}
+
+impl<Key, Value> Stores<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn lho(&mut self) -> &mut Store<Key, Value> {
+        match self {
+            Stores::Shared(store) => store,
+            Stores::Split(lho, _) => lho,
+        }
+    }
+
+    fn rho(&mut self) -> &mut Store<Key, Value> {
+        match self {
+            Stores::Shared(store) => store,
+            Stores::Split(_, rho) => rho,
+        }
+    }
+}
+
+impl<Key, Value> Iterator for DiffIter<Key, Value>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    type Item = (Key, Option<Value>, Option<Value>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty() && !self.stack.is_empty() {
+            let mut stores = self.take_stores();
+
+            while self.ready.is_empty() {
+                match self.stack.pop() {
+                    Some((depth, lho, rho)) => self.step(&mut stores, depth, lho, rho),
+                    None => break,
+                }
+            }
+
+            self.restore_stores(stores);
+        }
+
+        self.ready.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::tree::Path,
+        database::{Database, Table, TableTransaction},
+    };
+
+    use std::{collections::HashMap, iter};
+
+    use talk::crypto::primitives::hash;
+
+    fn tables(
+        database: &Database<u32, u32>,
+        lho_records: impl Iterator<Item = (u32, u32)>,
+        rho_records: impl Iterator<Item = (u32, u32)>,
+    ) -> (Table<u32, u32>, Table<u32, u32>) {
+        let lho = database.empty_table("test");
+        let rho = database.empty_table("test2");
+
+        let mut transaction = TableTransaction::default();
+        for (key, value) in lho_records {
+            transaction.set(key, value).unwrap();
+        }
+        lho.execute(transaction);
+
+        let mut transaction = TableTransaction::default();
+        for (key, value) in rho_records {
+            transaction.set(key, value).unwrap();
+        }
+        rho.execute(transaction);
+
+        ((*lho).clone(), (*rho).clone())
+    }
+
+    fn check(lho: &Table<u32, u32>, rho: &Table<u32, u32>) {
+        let records = Table::diff_iter(lho, rho).collect::<Vec<_>>();
+
+        let paths = records
+            .iter()
+            .map(|(key, _, _)| Path::from(hash::hash(key).unwrap()))
+            .collect::<Vec<_>>();
+
+        assert!(paths.windows(2).all(|window| window[0] > window[1]));
+
+        let diff = records
+            .into_iter()
+            .map(|(key, lho_value, rho_value)| (key, (lho_value, rho_value)))
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(diff, Table::diff(lho, rho));
+    }
+
+    #[test]
+    fn empty() {
+        let database: Database<u32, u32> = Database::new("test");
+        let (lho, rho) = tables(&database, iter::empty(), iter::empty());
+
+        assert_eq!(Table::diff_iter(&lho, &rho).count(), 0);
+    }
+
+    #[test]
+    fn identical() {
+        let database: Database<u32, u32> = Database::new("test");
+        let (lho, rho) = tables(
+            &database,
+            (0..1024).map(|key| (key, key)),
+            (0..1024).map(|key| (key, key)),
+        );
+
+        assert_eq!(Table::diff_iter(&lho, &rho).count(), 0);
+    }
+
+    #[test]
+    fn overlap() {
+        let database: Database<u32, u32> = Database::new("test");
+        let (lho, rho) = tables(
+            &database,
+            (0..1024).map(|key| (key, key)),
+            (512..1536).map(|key| (key, if key < 768 { key + 1 } else { key })),
+        );
+
+        check(&lho, &rho);
+        check(&rho, &lho);
+
+        assert_eq!(Table::diff_iter(&lho, &rho).count(), 1280);
+    }
+
+    #[test]
+    fn sparse() {
+        let database: Database<u32, u32> = Database::new("test");
+        let (lho, rho) = tables(
+            &database,
+            (0..4).map(|key| (key, key)),
+            (0..1024).map(|key| (key, key + (key % 2))),
+        );
+
+        check(&lho, &rho);
+        check(&rho, &lho);
+    }
+
+    #[test]
+    fn snapshot() {
+        let database: Database<u32, u32> = Database::new("test");
+        let (lho, rho) = tables(
+            &database,
+            (0..256).map(|key| (key, key)),
+            (0..256).map(|key| (key, key + 1)),
+        );
+
+        let mut iter = Table::diff_iter(&lho, &rho);
+        let first = iter.next().unwrap();
+
+        let mut transaction = TableTransaction::default();
+        for key in 0..256 {
+            transaction.remove(key).unwrap();
+        }
+        lho.execute(transaction);
+
+        assert_eq!(first.1.unwrap() + 1, first.2.unwrap());
+        assert_eq!(iter.count(), 255);
+    }
+
+    #[test]
+    fn across_stores() {
+        let paths = [
+            format!("test/{}", rand::random::<u64>()),
+            format!("test/{}", rand::random::<u64>()),
+        ];
+
+        {
+            let alice: Database<u32, u32> = Database::new(&paths[0]);
+            let bob: Database<u32, u32> = Database::new(&paths[1]);
+
+            let (lho, _) = tables(&alice, (0..512).map(|key| (key, key)), iter::empty());
+            let (rho, _) = tables(&bob, (256..768).map(|key| (key, key)), iter::empty());
+
+            check(&lho, &rho);
+            assert_eq!(Table::diff_iter(&lho, &rho).count(), 512);
+        }
+
+        for path in paths {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+}
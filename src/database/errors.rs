@@ -6,6 +6,42 @@ pub enum QueryError {
     HashError,
     #[doom(description("Key collision within transaction"))]
     KeyCollision,
+    #[doom(description("Table has no such index"))]
+    IndexMissing,
+    #[doom(description("Index entry cannot be decoded"))]
+    IndexCorrupt,
+}
+
+#[derive(Doom)]
+pub enum TableError {
+    #[doom(description("A table with this name already exists"))]
+    NameTaken,
+    #[doom(description("No table with this name"))]
+    TableMissing,
+    #[doom(description("Failed to hash field"))]
+    HashError,
+    #[doom(description("Branch unknown: `Map` is incomplete"))]
+    BranchUnknown,
+    #[doom(description("Failed to back up table"))]
+    BackupFailed,
+    #[doom(description("Table is not assigned to this database"))]
+    ForeignTable,
+    #[doom(description("Failed to access table storage"))]
+    StorageError,
+    #[doom(description("Backed up records cannot be decoded"))]
+    BackupCorrupt,
+}
+
+#[derive(Doom, PartialEq, Eq)]
+pub enum RangeProofError {
+    #[doom(description("Root mismatch"))]
+    RootMismatch,
+    #[doom(description("Proof leaves out records in range"))]
+    Incomplete,
+    #[doom(description("Malformed proof"))]
+    Malformed,
+    #[doom(description("Failed to hash field"))]
+    HashError,
 }
 
 #[derive(Doom, PartialEq, Eq)]
@@ -14,4 +50,38 @@ pub enum SyncError {
     MalformedQuestion,
     #[doom(description("Malformed `Answer`"))]
     MalformedAnswer,
+    #[doom(description("`Answer` exceeds the sync limits"))]
+    AnswerTooLarge,
+    #[doom(description("Root does not match the expected commitment"))]
+    RootMismatch,
+    #[doom(description("Connection error"))]
+    ConnectionError,
+    #[doom(description("Timed out waiting for the peer"))]
+    Timeout,
+    #[doom(description("Message exceeds the maximum frame size"))]
+    FrameTooLarge,
+    #[doom(description("Malformed message"))]
+    MalformedMessage,
+    #[doom(description("No peer left to receive from"))]
+    PeersExhausted,
+    #[doom(description("Stored checkpoint is missing or corrupt"))]
+    CorruptCheckpoint,
+    #[doom(description("Failed to access checkpoint storage"))]
+    StorageError,
+    #[doom(description("Receiver has no expected root"))]
+    RootUnknown,
+    #[doom(description("Base table is not held by the sender"))]
+    BaseUnknown,
+    #[doom(description("Peer agreed to settings that exceed the local limits"))]
+    NegotiationFailed,
+}
+
+#[derive(Doom)]
+pub enum CertificateError {
+    #[doom(description("Failed to sign commitment"))]
+    SignFailed,
+    #[doom(description("Invalid certificate signature"))]
+    InvalidSignature,
+    #[doom(description("Failed to read or write the certified version"))]
+    VersionStorage,
 }
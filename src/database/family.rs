@@ -1,9 +1,15 @@
 
 use crate::{
     common::store::Field,
-    database::{Collection, CollectionReceiver, Database},
+    database::{
+        errors::TableError, Collection, CollectionReceiver, CollectionReconciler, Database,
+    },
 };
 
+use doomstack::{here, Doom, ResultExt, Top};
+
+use std::hash::Hash as StdHash;
+
 #[derive(Clone)]
 pub struct Family<Item: Field>(pub(crate) Database<Item, ()>);
 
@@ -22,4 +28,175 @@ where
     pub fn receive(&self) -> CollectionReceiver<Item> {
         CollectionReceiver(self.0.receive())
     }
+
+    /// Creates a [`CollectionReconciler`] to reconcile `local`, a
+    /// [`Collection`] of this `Family`, with a peer's [`Collection`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{CollectionTransaction, Family};
+    ///
+    /// let alice: Family<u32> = Family::new("test/alice");
+    /// let bob: Family<u32> = Family::new("test/bob");
+    ///
+    /// let mut alice_items = alice.empty_collection("items");
+    /// let mut bob_items = bob.empty_collection("items");
+    ///
+    /// let mut transaction = CollectionTransaction::default();
+    /// transaction.insert(0).unwrap();
+    /// transaction.insert(1).unwrap();
+    /// alice_items.execute(transaction);
+    ///
+    /// let mut transaction = CollectionTransaction::default();
+    /// transaction.insert(1).unwrap();
+    /// transaction.insert(2).unwrap();
+    /// bob_items.execute(transaction);
+    ///
+    /// // Alice's side of the reconciliation (Bob runs the same, symmetrically)
+    /// let sender = bob_items.clone().send();
+    /// let mut reconciler = alice.reconcile(&alice_items);
+    /// let mut answer = sender.hello();
+    ///
+    /// while let Some(question) = reconciler.learn(answer).unwrap() {
+    ///     answer = sender.answer(&question).unwrap();
+    /// }
+    ///
+    /// let reconciliation = reconciler.reconcile().unwrap();
+    /// assert_eq!(reconciliation.missing, [2].iter().copied().collect());
+    /// assert_eq!(reconciliation.extra, [0].iter().copied().collect());
+    /// ```
+    pub fn reconcile(&self, local: &Collection<Item>) -> CollectionReconciler<Item> {
+        CollectionReconciler::new(local.0.as_ref().clone(), self.0.receive())
+    }
+
+    /// Creates and assigns to the `Family` a [`Collection`] named `name`,
+    /// holding the items of both `lho` and `rho`.
+    ///
+    /// The result shares the nodes of `lho` (or of `rho`, if only `rho` is a
+    /// collection of the `Family`), to which the items held only by the other
+    /// operand are added. These are found by [`Collection::diff`], which skips
+    /// the subtrees shared by `lho` and `rho` without visiting their items.
+    /// The same holds for [`Family::intersection`] and [`Family::difference`].
+    /// `lho` and `rho` are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// If the `Family` already has a collection named `name`, [`NameTaken`]
+    /// is returned. If neither `lho` nor `rho` is a collection of the
+    /// `Family` (e.g., they belong to another `Family`, or were received and
+    /// never assigned a name), [`ForeignTable`] is returned. If an `Item`
+    /// cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`NameTaken`]: crate::database::errors::TableError::NameTaken
+    /// [`ForeignTable`]: crate::database::errors::TableError::ForeignTable
+    /// [`HashError`]: crate::database::errors::TableError::HashError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{CollectionTransaction, Family};
+    ///
+    /// # let path = format!("test/{}", rand::random::<u64>());
+    /// let family: Family<u32> = Family::new(&path);
+    ///
+    /// let mut lho = family.empty_collection("lho");
+    /// let mut transaction = CollectionTransaction::default();
+    /// transaction.insert(0).unwrap();
+    /// transaction.insert(1).unwrap();
+    /// lho.execute(transaction);
+    ///
+    /// let mut rho = family.empty_collection("rho");
+    /// let mut transaction = CollectionTransaction::default();
+    /// transaction.insert(1).unwrap();
+    /// transaction.insert(2).unwrap();
+    /// rho.execute(transaction);
+    ///
+    /// let union = family.union("union", &lho, &rho).unwrap();
+    ///
+    /// assert!(lho.is_subset(&union));
+    /// assert!(rho.is_subset(&union));
+    ///
+    /// let lhs = family.difference("lhs", &union, &lho).unwrap();
+    /// let rhs = family.difference("rhs", &rho, &lho).unwrap();
+    /// assert_eq!(lhs.commit(), rhs.commit());
+    /// # std::fs::remove_dir_all(path).unwrap();
+    /// ```
+    pub fn union(
+        &self,
+        name: &str,
+        lho: &Collection<Item>,
+        rho: &Collection<Item>,
+    ) -> Result<Collection<Item>, Top<TableError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let (lho, rho) = self.operands(lho, rho)?;
+
+        let transaction =
+            Collection::union_transaction(lho, rho).pot(TableError::HashError, here!())?;
+
+        self.0
+            .derive_table(name, Some(&lho.0), transaction)
+            .map(Collection)
+    }
+
+    /// Creates and assigns to the `Family` a [`Collection`] named `name`,
+    /// holding the items of `lho` that are also held by `rho`.
+    ///
+    /// See [`Family::union`] for more details.
+    pub fn intersection(
+        &self,
+        name: &str,
+        lho: &Collection<Item>,
+        rho: &Collection<Item>,
+    ) -> Result<Collection<Item>, Top<TableError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let (lho, rho) = self.operands(lho, rho)?;
+
+        let transaction =
+            Collection::intersection_transaction(lho, rho).pot(TableError::HashError, here!())?;
+
+        self.0
+            .derive_table(name, Some(&lho.0), transaction)
+            .map(Collection)
+    }
+
+    /// Creates and assigns to the `Family` a [`Collection`] named `name`,
+    /// holding the items of `lho` that are not held by `rho`.
+    ///
+    /// Unlike [`Family::union`], the result starts from an empty collection,
+    /// so `lho` and `rho` can both belong to other `Family`s.
+    pub fn difference(
+        &self,
+        name: &str,
+        lho: &Collection<Item>,
+        rho: &Collection<Item>,
+    ) -> Result<Collection<Item>, Top<TableError>>
+    where
+        Item: Clone + Eq + StdHash,
+    {
+        let transaction =
+            Collection::difference_transaction(lho, rho).pot(TableError::HashError, here!())?;
+
+        self.0.derive_table(name, None, transaction).map(Collection)
+    }
+
+    // Orders the operands of a symmetric operation so that the first is a
+    // collection of the `Family`, whose nodes the result can share
+    fn operands<'a>(
+        &self,
+        lho: &'a Collection<Item>,
+        rho: &'a Collection<Item>,
+    ) -> Result<(&'a Collection<Item>, &'a Collection<Item>), Top<TableError>> {
+        if self.0.is_assigned(&lho.0) {
+            Ok((lho, rho))
+        } else if self.0.is_assigned(&rho.0) {
+            Ok((rho, lho))
+        } else {
+            TableError::ForeignTable.fail().spot(here!())
+        }
+    }
 }
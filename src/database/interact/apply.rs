@@ -332,7 +332,7 @@ mod tests {
 
     #[test]
     fn single_static_tree() {
-        let mut store = Store::<u32, u32>::new("test");
+        let mut store = Store::<u32, u32>::new("test").unwrap();
         store.check_leaks([Label::Empty]);
 
         // {0: 0, 1: 1, 2: 2, 3: 3, 4: 4, 5: 5, 6: 6, 7: 7}
@@ -389,7 +389,7 @@ mod tests {
 
     #[test]
     fn single_dynamic_tree() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         // {0: 1}
 
@@ -452,7 +452,7 @@ mod tests {
 
     #[test]
     fn single_insert() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (mut store, root, _) = apply(store, Label::Empty, batch);
@@ -464,7 +464,7 @@ mod tests {
 
     #[test]
     fn single_insert_read_all() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -477,7 +477,7 @@ mod tests {
 
     #[test]
     fn single_insert_read_half() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -490,7 +490,7 @@ mod tests {
 
     #[test]
     fn single_insert_read_missing() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -503,7 +503,7 @@ mod tests {
 
     #[test]
     fn single_insert_read_overlap() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -516,7 +516,7 @@ mod tests {
 
     #[test]
     fn single_modify() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -531,7 +531,7 @@ mod tests {
 
     #[test]
     fn single_modify_read_overlap() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -547,7 +547,7 @@ mod tests {
 
     #[test]
     fn single_modify_overlap_same_value() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -563,7 +563,7 @@ mod tests {
 
     #[test]
     fn single_insert_hybrid_read_set() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..192).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -586,7 +586,7 @@ mod tests {
 
     #[test]
     fn single_remove_all() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -600,7 +600,7 @@ mod tests {
 
     #[test]
     fn single_remove_half() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -615,7 +615,7 @@ mod tests {
 
     #[test]
     fn single_remove_all_but_one() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -630,7 +630,7 @@ mod tests {
 
     #[test]
     fn single_remove_half_insert_half() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..64).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -649,7 +649,7 @@ mod tests {
 
     #[test]
     fn single_remove_half_modify_half() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -668,7 +668,7 @@ mod tests {
 
     #[test]
     fn single_remove_quarter_modify_quarter_insert_half() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..64).map(|i| set!(i, i)).collect());
         let (store, root, _) = apply(store, Label::Empty, batch);
@@ -689,7 +689,7 @@ mod tests {
     fn single_stress() {
         let mut record_reference = HashMap::new();
 
-        let mut store = Store::<u32, u32>::new("test");
+        let mut store = Store::<u32, u32>::new("test").unwrap();
         let mut root = Label::Empty;
 
         let mut rng = rand::thread_rng();
@@ -733,7 +733,7 @@ mod tests {
 
     #[test]
     fn multiple_distinct() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch);
@@ -752,7 +752,7 @@ mod tests {
 
     #[test]
     fn multiple_insert_then_match() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = || Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch());
@@ -769,7 +769,7 @@ mod tests {
 
     #[test]
     fn multiple_insert_then_overflow_by_one() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch);
@@ -788,7 +788,7 @@ mod tests {
 
     #[test]
     fn multiple_insert_then_double() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch);
@@ -807,7 +807,7 @@ mod tests {
 
     #[test]
     fn multiple_match_then_empty() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = || Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch());
@@ -827,7 +827,7 @@ mod tests {
 
     #[test]
     fn multiple_match_then_leave_one() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = || Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch());
@@ -847,7 +847,7 @@ mod tests {
 
     #[test]
     fn multiple_match_then_leave_half() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = || Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch());
@@ -867,7 +867,7 @@ mod tests {
 
     #[test]
     fn multiple_match_then_split() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
 
         let batch = || Batch::new((0..128).map(|i| set!(i, i)).collect());
         let (store, first_root, _) = apply(store, Label::Empty, batch());
@@ -893,7 +893,7 @@ mod tests {
         let mut first_record_reference = HashMap::new();
         let mut second_record_reference = HashMap::new();
 
-        let mut store = Store::<u32, u32>::new("test");
+        let mut store = Store::<u32, u32>::new("test").unwrap();
 
         let mut first_root = Label::Empty;
         let mut second_root = Label::Empty;
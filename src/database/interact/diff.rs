@@ -1,5 +1,9 @@
 use crate::{
-    common::store::Field,
+    common::{
+        data::Bytes,
+        store::Field,
+        tree::{Tree, Visit},
+    },
     database::store::{Label, Node, Split, Store, Wrap},
 };
 
@@ -10,7 +14,7 @@ use std::collections::{
 
 type Collector<Key, Value> = LinkedList<(Wrap<Key>, Wrap<Value>)>;
 
-fn get<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Node<Key, Value>
+pub(crate) fn get<Key, Value>(store: &mut Store<Key, Value>, label: Label) -> Node<Key, Value>
 where
     Key: Field,
     Value: Field,
@@ -173,3 +177,27 @@ where
 {
     recur(store, Some(lho_root), Some(rho_root))
 }
+
+impl<Key, Value> Tree for Store<Key, Value>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    type Ref = Label;
+    type Key = Key;
+    type Value = Value;
+
+    fn hash(&self, node: &Label) -> Bytes {
+        node.hash()
+    }
+
+    fn visit(&mut self, node: Label) -> Visit<Label, Key, Value> {
+        match get(self, node) {
+            Node::Internal(left, right) => Visit::Internal(left, right),
+            Node::Leaf(key, value) => {
+                Visit::Leaf((**key.inner()).clone(), (**value.inner()).clone())
+            }
+            Node::Empty => Visit::Empty,
+        }
+    }
+}
@@ -113,3 +113,59 @@ where
 {
     recur(store, root, 0, paths)
 }
+
+// Exports the whole subtree under `node`, leaving no `Stub`
+fn snapshot_recur<Key, Value>(
+    mut store: Store<Key, Value>,
+    node: Label,
+) -> (Store<Key, Value>, MapNode<Key, Value>)
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    let hash = node.hash();
+
+    match get(&mut store, node) {
+        Node::Internal(left, right) => {
+            let (store, left, right) = match store.split() {
+                Split::Split(left_store, right_store) => {
+                    let ((left_store, left), (right_store, right)) = rayon::join(
+                        move || snapshot_recur(left_store, left),
+                        move || snapshot_recur(right_store, right),
+                    );
+
+                    (Store::merge(left_store, right_store), left, right)
+                }
+                Split::Unsplittable(store) => {
+                    let (store, left) = snapshot_recur(store, left);
+                    let (store, right) = snapshot_recur(store, right);
+
+                    (store, left, right)
+                }
+            };
+
+            (
+                store,
+                MapNode::Internal(MapInternal::raw(hash, left, right)),
+            )
+        }
+        Node::Leaf(key, value) => {
+            let key = MapWrap::raw(key.digest(), (**key.inner()).clone());
+            let value = MapWrap::raw(value.digest(), (**value.inner()).clone());
+
+            (store, MapNode::Leaf(MapLeaf::raw(hash, key, value)))
+        }
+        Node::Empty => (store, MapNode::Empty),
+    }
+}
+
+pub(crate) fn snapshot<Key, Value>(
+    store: Store<Key, Value>,
+    root: Label,
+) -> (Store<Key, Value>, MapNode<Key, Value>)
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    snapshot_recur(store, root)
+}
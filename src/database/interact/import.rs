@@ -0,0 +1,67 @@
+use crate::{
+    common::{store::Field, tree::Prefix},
+    database::store::{Label, MapId, Node, Store, Wrap},
+    map::store::Node as MapNode,
+};
+
+use std::collections::hash_map::Entry::Occupied;
+
+fn recur<Key, Value>(
+    store: &mut Store<Key, Value>,
+    node: &MapNode<Key, Value>,
+    location: Prefix,
+) -> Label
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    let label = match node {
+        MapNode::Empty => return Label::Empty,
+        MapNode::Internal(internal) => {
+            let label = Label::Internal(MapId::internal(location), internal.hash());
+
+            // Nodes already in `store` are shared, along with their subtrees
+            if !matches!(store.entry(label), Occupied(..)) {
+                let left = recur(store, internal.left(), location.left());
+                let right = recur(store, internal.right(), location.right());
+
+                store.populate(label, Node::Internal(left, right));
+            }
+
+            label
+        }
+        MapNode::Leaf(leaf) => {
+            let key = leaf.key();
+            let value = leaf.value();
+
+            let label = Label::Leaf(MapId::leaf(&key.digest()), leaf.hash());
+
+            if !matches!(store.entry(label), Occupied(..)) {
+                let key = Wrap::raw(key.digest(), key.inner().clone());
+                let value = Wrap::raw(value.digest(), value.inner().clone());
+
+                store.populate(label, Node::Leaf(key, value));
+            }
+
+            label
+        }
+        MapNode::Stub(_) => panic!("called `import` on a tree with `Stub`s"),
+    };
+
+    store.incref(label);
+    label
+}
+
+/// Stores the nodes of the tree rooted at `root`, which must not contain
+/// `Stub`s, and returns the label of `root`. Hashes are not recomputed.
+pub(crate) fn import<Key, Value>(
+    mut store: Store<Key, Value>,
+    root: &MapNode<Key, Value>,
+) -> (Store<Key, Value>, Label)
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    let root = recur(&mut store, root, Prefix::root());
+    (store, root)
+}
@@ -11,6 +11,7 @@ pub(crate) mod apply;
 pub(crate) mod diff;
 pub(crate) mod drop;
 pub(crate) mod export;
+pub(crate) mod import;
 
 pub(crate) use action::Action;
 pub(crate) use batch::Batch;
@@ -3,21 +3,27 @@ mod macros;
 
 mod interact;
 mod store;
-mod sync;
 
+mod certificate;
 mod collection;
 mod collection_answer;
 mod collection_receiver;
+mod collection_reconciler;
 mod collection_response;
 mod collection_sender;
 mod collection_status;
 mod collection_transaction;
 mod database_impl;
+mod diff_iter;
 mod family;
+mod persistent_vector;
 mod query;
+mod range_proof;
 mod question;
+mod reconciliation;
 mod table;
 mod table_answer;
+mod table_differ;
 mod table_receiver;
 mod table_response;
 mod table_sender;
@@ -27,20 +33,28 @@ mod table_transaction;
 use table_transaction::Tid;
 
 pub mod errors;
+pub mod sync;
 
+pub use certificate::Certificate;
 pub use collection::Collection;
 pub use collection_answer::CollectionAnswer;
 pub use collection_receiver::CollectionReceiver;
+pub use collection_reconciler::CollectionReconciler;
 pub use collection_response::CollectionResponse;
 pub use collection_sender::CollectionSender;
 pub use collection_status::CollectionStatus;
 pub use collection_transaction::CollectionTransaction;
 pub use database_impl::Database;
+pub use diff_iter::DiffIter;
 pub use family::Family;
+pub use persistent_vector::PersistentVector;
 pub use query::Query;
 pub use question::Question;
+pub use range_proof::RangeProof;
+pub use reconciliation::Reconciliation;
 pub use table::Table;
 pub use table_answer::TableAnswer;
+pub use table_differ::TableDiffer;
 pub use table_receiver::TableReceiver;
 pub use table_response::TableResponse;
 pub use table_sender::TableSender;
@@ -0,0 +1,375 @@
+use crate::{
+    common::store::Field,
+    database::store::VECTORS,
+    vector::{errors::VectorError, Proof, Vector},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use rocksdb::{WriteBatchWithTransaction, DB};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use std::sync::Arc;
+
+use talk::crypto::primitives::hash::Hash;
+
+/// A [`Vector`] whose items and hash layers are stored in the RocksDB
+/// instance of a [`Database`].
+///
+/// A `PersistentVector` is created by [`Database::new_vector`] and reopened
+/// (e.g., after a restart) by [`Database::get_vector`]. Every call to
+/// [`set`] writes the new item and the hashes it changes in a single
+/// atomic batch, so the stored vector is never partially updated.
+///
+/// [`Vector`]: crate::vector::Vector
+/// [`Database`]: crate::database::Database
+/// [`Database::new_vector`]: crate::database::Database::new_vector
+/// [`Database::get_vector`]: crate::database::Database::get_vector
+/// [`set`]: PersistentVector::set
+///
+/// # Examples
+///
+/// ```
+/// use tenaciouszebra::database::{Database, PersistentVector};
+///
+/// let database: Database<String, i32> = Database::new("test");
+///
+/// let mut log: PersistentVector<u32> = database.new_vector("log", vec![0, 1, 2]).unwrap();
+/// log.set(1, 42).unwrap();
+///
+/// let reloaded: PersistentVector<u32> = database.get_vector("log").unwrap().unwrap();
+/// assert_eq!(reloaded.items(), &[0, 42, 2]);
+/// assert_eq!(reloaded.root(), log.root());
+/// ```
+pub struct PersistentVector<Item: Field, const PACKING: usize = 1> {
+    db: Arc<DB>,
+    name: String,
+    vector: Vector<Item, PACKING>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Shape {
+    packing: usize,
+    items: usize,
+    layers: Vec<usize>,
+}
+
+#[derive(Serialize)]
+enum Slot {
+    Shape,
+    Item(usize),
+    Hash(usize, usize),
+}
+
+impl<Item, const PACKING: usize> PersistentVector<Item, PACKING>
+where
+    Item: Field,
+{
+    pub(crate) fn new(db: Arc<DB>, name: &str, items: Vec<Item>) -> Result<Self, Top<VectorError>> {
+        let vector = Vector::new(items)?;
+
+        let family = db.cf_handle(VECTORS).unwrap();
+        let mut batch = WriteBatchWithTransaction::<false>::default();
+
+        // Drop whatever was previously stored under `name`
+        if let Some(shape) = Self::shape(&db, name)? {
+            for index in 0..shape.items {
+                batch.delete_cf(&family, Self::key(name, Slot::Item(index)));
+            }
+
+            for (layer, len) in shape.layers.iter().enumerate() {
+                for position in 0..*len {
+                    batch.delete_cf(&family, Self::key(name, Slot::Hash(layer, position)));
+                }
+            }
+        }
+
+        let shape = Shape {
+            packing: PACKING,
+            items: vector.len(),
+            layers: vector.layers().iter().map(Vec::len).collect(),
+        };
+
+        batch.put_cf(&family, Self::key(name, Slot::Shape), bincode::serialize(&shape).unwrap());
+
+        for (index, item) in vector.items().iter().enumerate() {
+            batch.put_cf(&family, Self::key(name, Slot::Item(index)), bincode::serialize(item).unwrap());
+        }
+
+        for (layer, hashes) in vector.layers().iter().enumerate() {
+            for (position, hash) in hashes.iter().enumerate() {
+                batch.put_cf(
+                    &family,
+                    Self::key(name, Slot::Hash(layer, position)),
+                    bincode::serialize(hash).unwrap(),
+                );
+            }
+        }
+
+        db.write(batch).pot(VectorError::StorageError, here!())?;
+
+        Ok(PersistentVector {
+            db: db.clone(),
+            name: name.to_string(),
+            vector,
+        })
+    }
+
+    pub(crate) fn load(db: Arc<DB>, name: &str) -> Result<Option<Self>, Top<VectorError>> {
+        let shape = match Self::shape(&db, name)? {
+            Some(shape) => shape,
+            None => return Ok(None),
+        };
+
+        if shape.packing != PACKING {
+            return VectorError::PackingMismatch.fail().spot(here!());
+        }
+
+        let items = (0..shape.items)
+            .map(|index| Self::read::<Item>(&db, name, Slot::Item(index))?.ok_or_else(Self::corrupt))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let layers = shape
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(layer, len)| {
+                (0..*len)
+                    .map(|position| {
+                        Self::read::<Hash>(&db, name, Slot::Hash(layer, position))?
+                            .ok_or_else(Self::corrupt)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(PersistentVector {
+            db: db.clone(),
+            name: name.to_string(),
+            vector: Vector::from_raw(layers, items),
+        }))
+    }
+
+    /// Returns the name the vector is stored under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Stores the item at `index` and the hashes it changes in a single
+    /// atomic batch, then sets the item at `index` to `item`.
+    ///
+    /// # Errors
+    ///
+    /// If `item` cannot be hashed, [`HashError`] is returned; if the batch
+    /// cannot be written, [`StorageError`] is returned. In both cases, the
+    /// vector is left unchanged, in memory and in storage.
+    ///
+    /// [`HashError`]: crate::vector::errors::VectorError::HashError
+    /// [`StorageError`]: crate::vector::errors::VectorError::StorageError
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, item: Item) -> Result<(), Top<VectorError>> {
+        let hashes = self.vector.trail_hashes(index, &item)?;
+
+        let family = self.db.cf_handle(VECTORS).unwrap();
+        let mut batch = WriteBatchWithTransaction::<false>::default();
+
+        batch.put_cf(
+            &family,
+            Self::key(&self.name, Slot::Item(index)),
+            bincode::serialize(&item).unwrap(),
+        );
+
+        for ((layer, position), hash) in self.vector.trail(index).into_iter().zip(hashes.iter()) {
+            batch.put_cf(
+                &family,
+                Self::key(&self.name, Slot::Hash(layer, position)),
+                bincode::serialize(hash).unwrap(),
+            );
+        }
+
+        self.db.write(batch).pot(VectorError::StorageError, here!())?;
+        self.vector.replace(index, item, hashes);
+
+        Ok(())
+    }
+
+    /// Returns the number of items in the vector.
+    pub fn len(&self) -> usize {
+        self.vector.len()
+    }
+
+    /// Returns `true` if the vector holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.vector.is_empty()
+    }
+
+    /// Returns the root of the vector (see [`Vector::root`]).
+    ///
+    /// [`Vector::root`]: crate::vector::Vector::root
+    pub fn root(&self) -> Hash {
+        self.vector.root()
+    }
+
+    /// Returns the items of the vector.
+    pub fn items(&self) -> &[Item] {
+        self.vector.items()
+    }
+
+    /// Returns a [`Proof`] that the item at `index` belongs to the vector
+    /// (see [`Vector::prove`]).
+    ///
+    /// [`Proof`]: crate::vector::Proof
+    /// [`Vector::prove`]: crate::vector::Vector::prove
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn prove(&self, index: usize) -> Proof<Item, PACKING>
+    where
+        Item: Clone,
+    {
+        self.vector.prove(index)
+    }
+
+    fn shape(db: &DB, name: &str) -> Result<Option<Shape>, Top<VectorError>> {
+        Self::read::<Shape>(db, name, Slot::Shape)
+    }
+
+    // Returns `None` if nothing is stored under `slot`
+    fn read<T>(db: &DB, name: &str, slot: Slot) -> Result<Option<T>, Top<VectorError>>
+    where
+        T: DeserializeOwned,
+    {
+        let family = db.cf_handle(VECTORS).unwrap();
+
+        let raw = db
+            .get_cf(&family, Self::key(name, slot))
+            .pot(VectorError::StorageError, here!())?;
+
+        raw.map(|raw| bincode::deserialize::<T>(&raw).pot(VectorError::CorruptStorage, here!()))
+            .transpose()
+    }
+
+    fn corrupt() -> Top<VectorError> {
+        VectorError::CorruptStorage.into_top().spot(here!())
+    }
+
+    fn key(name: &str, slot: Slot) -> Vec<u8> {
+        bincode::serialize(&(name, slot)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+
+    use super::*;
+
+    #[test]
+    fn prove_and_set() {
+        Database::<u32, u32>::test_database(|database| {
+            let mut vector: PersistentVector<u32> =
+                database.new_vector("log", (0..33).collect()).unwrap();
+
+            for index in 0..33 {
+                vector.prove(index).verify(vector.root(), 33, index, &(index as u32)).unwrap();
+            }
+
+            vector.set(7, 70).unwrap();
+
+            let control = Vector::<u32>::new((0..33).map(|i| if i == 7 { 70 } else { i }).collect()).unwrap();
+            assert_eq!(vector.root(), control.root());
+            vector.prove(7).verify(vector.root(), 33, 7, &70).unwrap();
+        });
+    }
+
+    #[test]
+    fn missing() {
+        Database::<u32, u32>::test_database(|database| {
+            assert!(database.get_vector::<u32, 1>("log").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn overwrite() {
+        Database::<u32, u32>::test_database(|database| {
+            database.new_vector::<u32, 1>("log", (0..64).collect()).unwrap();
+            let vector = database.new_vector::<u32, 1>("log", (0..3).collect()).unwrap();
+
+            let reloaded = database.get_vector::<u32, 1>("log").unwrap().unwrap();
+            assert_eq!(reloaded.items(), &[0, 1, 2]);
+            assert_eq!(reloaded.root(), vector.root());
+        });
+    }
+
+    #[test]
+    fn restore_after_reopen() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let root = {
+            let database: Database<u32, u32> = Database::new(&path);
+
+            let table = database.empty_table("test");
+            let mut transaction = crate::database::TableTransaction::default();
+            transaction.set(0, 1).unwrap();
+            table.execute(transaction);
+
+            let mut vector: PersistentVector<u32, 4> =
+                database.new_vector("log", (0..50).collect()).unwrap();
+
+            for index in (0..50).step_by(3) {
+                vector.set(index, index as u32 * 10).unwrap();
+            }
+
+            vector.root()
+        };
+
+        {
+            let database: Database<u32, u32> = Database::new(&path);
+
+            database.get_table("test").unwrap().assert_records([(0, 1)]);
+
+            let mut vector: PersistentVector<u32, 4> = database.get_vector("log").unwrap().unwrap();
+            assert_eq!(vector.root(), root);
+
+            let control = Vector::<u32, 4>::new(
+                (0..50).map(|i| if i % 3 == 0 { i * 10 } else { i }).collect(),
+            )
+            .unwrap();
+            assert_eq!(vector.root(), control.root());
+
+            vector.set(49, 0).unwrap();
+            vector.prove(49).verify(vector.root(), 50, 49, &0).unwrap();
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn packing_mismatch() {
+        Database::<u32, u32>::test_database(|database| {
+            database.new_vector::<u32, 4>("log", (0..16).collect()).unwrap();
+            assert!(database.get_vector::<u32, 1>("log").is_err());
+        });
+    }
+
+    #[test]
+    fn corrupt() {
+        Database::<u32, u32>::test_database(|database| {
+            let vector = database.new_vector::<u32, 1>("log", (0..16).collect()).unwrap();
+
+            let family = vector.db.cf_handle(VECTORS).unwrap();
+            let key = PersistentVector::<u32>::key("log", Slot::Item(3));
+
+            vector.db.put_cf(&family, &key, [0xff]).unwrap();
+            assert!(database.get_vector::<u32, 1>("log").is_err());
+
+            vector.db.delete_cf(&family, &key).unwrap();
+            assert!(database.get_vector::<u32, 1>("log").is_err());
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use crate::database::store::Label;
+use crate::{common::data::Bytes, database::store::Label};
 
 use serde::{Deserialize, Serialize};
 
@@ -17,4 +17,35 @@ use crate::database::{TableAnswer, TableReceiver, TableSender};
 /// [`Answer`]: crate::database::Question
 
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct Question(pub(crate) Vec<Label>);
+pub struct Question(pub(crate) Ask);
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Ask {
+    // The nodes labelled by each `Label`
+    Labels(Vec<Label>),
+    // The root of the table, if its commitment is the one given (see
+    // `Database::receive_expecting`)
+    Root(Bytes),
+}
+
+impl Question {
+    pub(crate) fn labels(labels: Vec<Label>) -> Self {
+        Question(Ask::Labels(labels))
+    }
+
+    pub(crate) fn root(commit: Bytes) -> Self {
+        Question(Ask::Root(commit))
+    }
+
+    // Number of nodes asked
+    pub(crate) fn len(&self) -> usize {
+        match &self.0 {
+            Ask::Labels(labels) => labels.len(),
+            Ask::Root(..) => 1,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    common::{data::Bytes, store::hash},
+    database::{errors::RangeProofError, store::Step},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use talk::crypto::primitives::{hash as crypto, hash::Hash};
+
+/// A proof that a list of records is exactly the records of a [`Table`]
+/// whose key lies in a range (see [`Table::prove_range`]).
+///
+/// A `RangeProof` is verified against the commitment to the ordered index of
+/// the [`Table`] (see [`Table::index_commit`]), which is distinct from
+/// [`Table::commit`]. Its size is logarithmic in the size of the [`Table`],
+/// plus linear in the number of records in range.
+///
+/// [`Table`]: crate::database::Table
+/// [`Table::prove_range`]: crate::database::Table::prove_range
+/// [`Table::index_commit`]: crate::database::Table::index_commit
+/// [`Table::commit`]: crate::database::Table::commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangeProof<Key, Value>(Vec<Step<Key, Value>>);
+
+// A node whose children are being verified
+struct Frame<Key, Value> {
+    key: Arc<Key>,
+    value: Arc<Value>,
+    record: Bytes,
+    lower: Option<Arc<Key>>,
+    upper: Option<Arc<Key>>,
+    left: Option<Bytes>,
+}
+
+impl<Key, Value> RangeProof<Key, Value> {
+    pub(crate) fn new(steps: Vec<Step<Key, Value>>) -> Self {
+        RangeProof(steps)
+    }
+}
+
+impl<Key, Value> RangeProof<Key, Value>
+where
+    Key: Serialize + Ord + Clone,
+    Value: Serialize + Clone,
+{
+    /// Verifies that the proof was issued for `range` by a [`Table`] whose
+    /// ordered index has commitment `commit`, returning the records of the
+    /// [`Table`] whose key lies in `range`, in ascending order of key.
+    ///
+    /// Verification never panics, and its stack usage does not depend on the
+    /// proof: a malformed proof (e.g., one received from the network) results
+    /// in a [`RangeProofError`].
+    ///
+    /// [`Table`]: crate::database::Table
+    /// [`RangeProofError`]: crate::database::errors::RangeProofError
+    pub fn verify<R>(
+        &self,
+        commit: Hash,
+        range: R,
+    ) -> Result<Vec<(Key, Value)>, Top<RangeProofError>>
+    where
+        R: RangeBounds<Key>,
+    {
+        let (start, end) = (range.start_bound(), range.end_bound());
+
+        let mut stack: Vec<Frame<Key, Value>> = Vec::new();
+        let mut root = None;
+        let mut records = Vec::new();
+
+        // Steps follow a pre-order walk: each node comes before its children
+        for step in self.0.iter() {
+            if root.is_some() {
+                return RangeProofError::Malformed.fail().spot(here!());
+            }
+
+            // Bounds (excluded) on the keys of the subtree that `step` stands for
+            let (lower, upper) = match stack.last() {
+                None => (None, None),
+                Some(parent) if parent.left.is_none() => {
+                    (parent.lower.clone(), Some(parent.key.clone()))
+                }
+                Some(parent) => (Some(parent.key.clone()), parent.upper.clone()),
+            };
+
+            let mut subtree = match step {
+                Step::Stub(stub) => {
+                    // Only subtrees with no key in range can be left out
+                    if *stub != hash::empty() && !outside(&lower, &upper, start, end) {
+                        return RangeProofError::Incomplete.fail().spot(here!());
+                    }
+
+                    *stub
+                }
+                Step::Node(key, value) => {
+                    let misplaced = matches!(&lower, Some(lower) if key <= lower)
+                        || matches!(&upper, Some(upper) if key >= upper);
+
+                    if misplaced {
+                        return RangeProofError::Malformed.fail().spot(here!());
+                    }
+
+                    let record = hash::leaf(digest(key.as_ref())?, digest(value.as_ref())?);
+
+                    stack.push(Frame {
+                        key: key.clone(),
+                        value: value.clone(),
+                        record,
+                        lower,
+                        upper,
+                        left: None,
+                    });
+
+                    continue;
+                }
+            };
+
+            // `subtree` is complete: fold every node it completes in turn
+            loop {
+                match stack.last_mut() {
+                    None => {
+                        root = Some(subtree);
+                        break;
+                    }
+                    Some(parent) if parent.left.is_none() => {
+                        parent.left = Some(subtree);
+
+                        // Left subtrees come first: records are found in order
+                        if range.contains(parent.key.as_ref()) {
+                            records
+                                .push((parent.key.as_ref().clone(), parent.value.as_ref().clone()));
+                        }
+
+                        break;
+                    }
+                    Some(_) => {
+                        let parent = stack.pop().unwrap();
+
+                        subtree = hash::index(parent.record, parent.left.unwrap(), subtree);
+                    }
+                }
+            }
+        }
+
+        match root {
+            Some(root) if root == commit.into() => Ok(records),
+            Some(_) => RangeProofError::RootMismatch.fail().spot(here!()),
+            None => RangeProofError::Malformed.fail().spot(here!()),
+        }
+    }
+}
+
+fn digest<T>(field: &T) -> Result<Bytes, Top<RangeProofError>>
+where
+    T: Serialize,
+{
+    crypto::hash(field)
+        .map(Into::into)
+        .pot(RangeProofError::HashError, here!())
+}
+
+// Whether no key strictly between `lower` and `upper` lies between `start`
+// and `end`
+fn outside<Key>(
+    lower: &Option<Arc<Key>>,
+    upper: &Option<Arc<Key>>,
+    start: Bound<&Key>,
+    end: Bound<&Key>,
+) -> bool
+where
+    Key: Ord,
+{
+    let below = match (upper, start) {
+        (Some(upper), Bound::Included(start)) | (Some(upper), Bound::Excluded(start)) => {
+            upper.as_ref() <= start
+        }
+        _ => false,
+    };
+
+    let above = match (lower, end) {
+        (Some(lower), Bound::Included(end)) | (Some(lower), Bound::Excluded(end)) => {
+            lower.as_ref() >= end
+        }
+        _ => false,
+    };
+
+    below || above
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::Database;
+
+    #[test]
+    fn logarithmic() {
+        let database: Database<u32, u32> = Database::new("test");
+        let table = database.table_with_records((0..4096).map(|key| (key, key)));
+
+        let proof = table.prove_range(1000..1002);
+
+        assert_eq!(
+            proof
+                .verify(table.index_commit(), 1000..1002)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(proof.0.len() < 128);
+    }
+
+    #[test]
+    fn tampered() {
+        let database: Database<u32, u32> = Database::new("test");
+        let table = database.table_with_records((0..256).map(|key| (key, key)));
+
+        let commit = table.index_commit();
+        let proof = table.prove_range(64..128);
+
+        let position = proof
+            .0
+            .iter()
+            .position(|step| matches!(step, Step::Node(key, _) if **key == 100))
+            .unwrap();
+
+        // Leaving out a record in range
+        let mut tampered = proof.clone();
+        tampered.0[position] = Step::Stub(hash::empty());
+        assert!(tampered.verify(commit, 64..128).is_err());
+
+        // Altering a record in range
+        let mut tampered = proof.clone();
+        tampered.0[position] = Step::Node(Arc::new(100), Arc::new(101));
+
+        assert!(
+            *tampered.verify(commit, 64..128).err().unwrap().top() == RangeProofError::RootMismatch
+        );
+
+        // Truncating or extending the proof
+        let mut tampered = proof.clone();
+        tampered.0.pop();
+
+        assert!(
+            *tampered.verify(commit, 64..128).err().unwrap().top() == RangeProofError::Malformed
+        );
+
+        let mut tampered = proof;
+        tampered.0.push(Step::Stub(hash::empty()));
+
+        assert!(
+            *tampered.verify(commit, 64..128).err().unwrap().top() == RangeProofError::Malformed
+        );
+    }
+
+    #[test]
+    fn deep() {
+        // A proof nested far deeper than any table is verified without recursion
+        let mut steps = (0..1_000_000u32)
+            .map(|key| Step::Node(Arc::new(key), Arc::new(key)))
+            .collect::<Vec<_>>();
+
+        for _ in 0..1_000_001 {
+            steps.push(Step::Stub(hash::empty()));
+        }
+
+        let proof = RangeProof(steps);
+        assert!(proof.verify(Bytes([0; 32]).into(), ..).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{common::store::Field, database::Collection};
+
+use std::collections::HashSet;
+
+/// The outcome of a [`CollectionReconciler`]: the two-sided difference
+/// between the local [`Collection`] and the peer's.
+///
+/// [`CollectionReconciler`]: crate::database::CollectionReconciler
+pub struct Reconciliation<Item: Field> {
+    /// Items of the peer's [`Collection`] that the local one lacks.
+    pub missing: HashSet<Item>,
+    /// Items of the local [`Collection`] that the peer's lacks.
+    pub extra: HashSet<Item>,
+    /// A copy of the peer's [`Collection`], in the local [`Family`].
+    ///
+    /// [`Family`]: crate::database::Family
+    pub remote: Collection<Item>,
+}
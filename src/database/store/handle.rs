@@ -1,12 +1,18 @@
 use crate::{
-    common::{store::Field, tree::Path},
+    common::{
+        store::Field,
+        tree::{self, Diff, Path},
+    },
     database::{
-        interact::{apply, diff, drop, export, Batch},
-        store::{Cell, Label},
+        errors::TableError,
+        interact::{apply, diff, drop, export, import, Batch},
+        store::{Cell, KeyIndex, Label, OrderedIndex},
     },
-    map::store::Node as MapNode,
+    map::{interact::Walk, store::Node as MapNode, Iter},
 };
 
+use doomstack::{here, ResultExt, Top};
+
 use oh_snap::Snap;
 
 use std::{
@@ -20,6 +26,7 @@ use talk::crypto::primitives::hash::Hash;
 pub(crate) struct Handle<Key: Field, Value: Field> {
     pub cell: Cell<Key, Value>,
     pub root: RwLock<Label>,
+    pub index: RwLock<Option<Box<dyn KeyIndex<Key, Value>>>>,
 }
 
 impl<Key, Value> Handle<Key, Value>
@@ -28,14 +35,57 @@ where
     Value: Field,
 {
     pub fn empty(cell: Cell<Key, Value>) -> Self {
+        Handle::new(cell, Label::Empty)
+    }
+
+    pub fn new(cell: Cell<Key, Value>, root: Label) -> Self {
         Handle {
             cell,
-            root: RwLock::new(Label::Empty),
+            root: RwLock::new(root),
+            index: RwLock::new(None),
         }
     }
 
-    pub fn new(cell: Cell<Key, Value>, root: Label) -> Self {
-        Handle { cell, root: RwLock::new(root) }
+    pub fn from_map(
+        cell: Cell<Key, Value>,
+        table_name: String,
+        root: &MapNode<Key, Value>,
+    ) -> Result<Self, Top<TableError>>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let store = cell.take();
+
+        if let Err(error) = store.backup_records(Iter::new(root), table_name) {
+            cell.restore(store);
+            return Err(error);
+        }
+
+        let (store, root) = import::import(store, root);
+        cell.restore(store);
+
+        Ok(Handle::new(cell, root))
+    }
+
+    /// Returns a `Handle` sharing the tree of `self`, backing up the records
+    /// backed up for `source` as the contents of `table_name`.
+    pub fn fork(&self, source: &str, table_name: &str) -> Result<Self, Top<TableError>> {
+        let mut store = self.cell.take();
+
+        // `root` is read while `store` is taken, so that no `apply` can
+        // change it between the copy of the backup and `incref`
+        let root = *self.root.read().unwrap();
+
+        if let Err(error) = store.copy_backup(source, table_name) {
+            self.cell.restore(store);
+            return Err(error).pot(TableError::BackupFailed, here!());
+        }
+
+        store.incref(root);
+        self.cell.restore(store);
+
+        Ok(Handle::new(self.cell.clone(), root))
     }
 
     pub fn commit(&self) -> Hash {
@@ -46,10 +96,18 @@ where
 
         let store = self.cell.take();
 
-        if store.backup(&batch, table_name).is_err() {
+        // Tables not assigned to a `Database` (e.g., received or merged) have
+        // no name, and are not backed up
+        if !table_name.is_empty() && store.backup(&batch, table_name).is_err() {
             panic!("Backup failed");
         }
 
+        // The index is updated while `store` is taken, so that it cannot
+        // interleave with another `apply`
+        if let Some(index) = self.index.write().unwrap().as_mut() {
+            index.update(&batch);
+        }
+
         let (store, root, batch) = apply::apply(store, *self.root.read().unwrap(), batch);
 
         self.cell.restore(store);
@@ -70,6 +128,43 @@ where
         root
     }
 
+    pub fn snapshot(&self) -> MapNode<Key, Value>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        let store = self.cell.take();
+        let (store, root) = export::snapshot(store, *self.root.read().unwrap());
+        self.cell.restore(store);
+
+        root
+    }
+
+    /// Runs `read` on the `OrderedIndex` of the records under `root`, building
+    /// it first if `self` has none yet.
+    pub fn read_index<T, F>(&self, read: F) -> T
+    where
+        Key: Ord,
+        F: FnOnce(&dyn KeyIndex<Key, Value>) -> T,
+    {
+        if let Some(index) = self.index.read().unwrap().as_ref() {
+            return read(index.as_ref());
+        }
+
+        // `store` is taken before `index` is locked, as in `apply`
+        let mut store = self.cell.take();
+        let mut index = self.index.write().unwrap();
+
+        if index.is_none() {
+            let root = *self.root.read().unwrap();
+            *index = Some(Box::new(OrderedIndex::build(&mut store, root)));
+        }
+
+        self.cell.restore(store);
+
+        read(index.as_ref().unwrap().as_ref())
+    }
+
     pub fn diff(
         lho: &Handle<Key, Value>,
         rho: &Handle<Key, Value>,
@@ -79,7 +174,7 @@ where
         Value: Clone + Eq,
     {
         if !ptr::eq(lho.cell.as_ref(), rho.cell.as_ref()) {
-            panic!("called `Handle::diff` on two `Handle`s for different `Store`s (most likely, `Table::diff` / `Collection::diff` was called on two objects belonging to different `Database`s / `Family`-es)");
+            return Handle::diff_across(lho, rho);
         }
 
         let store = lho.cell.take();
@@ -117,6 +212,56 @@ where
 
         diff
     }
+
+    // Compares `lho` and `rho` by label hash, visiting both `Store`s
+    fn diff_across(
+        lho: &Handle<Key, Value>,
+        rho: &Handle<Key, Value>,
+    ) -> HashMap<Key, (Option<Value>, Option<Value>)>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        // Cells are always taken in the same order, so that two concurrent
+        // diffs between the same `Store`s cannot deadlock
+        let lho_first = (lho.cell.as_ref() as *const _ as usize) < (rho.cell.as_ref() as *const _ as usize);
+
+        let (mut lho_store, mut rho_store) = if lho_first {
+            let lho_store = lho.cell.take();
+            (lho_store, rho.cell.take())
+        } else {
+            let rho_store = rho.cell.take();
+            (lho.cell.take(), rho_store)
+        };
+
+        let diff = tree::diff(
+            &mut lho_store,
+            *lho.root.read().unwrap(),
+            &mut rho_store,
+            *rho.root.read().unwrap(),
+        );
+
+        lho.cell.restore(lho_store);
+        rho.cell.restore(rho_store);
+
+        diff.unwrap() // A `Store` contains no stubs
+    }
+
+    /// Returns `None` if `map` is missing some branch that differs from `self`.
+    pub fn diff_map(
+        &self,
+        map: &MapNode<Key, Value>,
+    ) -> Option<Diff<Key, Value>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        let mut store = self.cell.take();
+        let diff = tree::diff(&mut store, *self.root.read().unwrap(), &mut Walk::default(), map);
+        self.cell.restore(store);
+
+        diff
+    }
 }
 
 impl<Key, Value> Clone for Handle<Key, Value>
@@ -129,9 +274,17 @@ where
         store.incref(*self.root.read().unwrap());
         self.cell.restore(store);
 
+        let index = self
+            .index
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|index| index.boxed_clone());
+
         Handle {
             cell: self.cell.clone(),
             root: RwLock::new(*self.root.read().unwrap()),
+            index: RwLock::new(index),
         }
     }
 }
@@ -30,6 +30,10 @@ impl MapId {
         MapId(key_hash.0[0])
     }
 
+    pub fn leaves() -> impl Iterator<Item = MapId> {
+        (0..=u8::MAX).map(MapId)
+    }
+
     pub fn id(&self) -> usize {
         if DEPTH > 0 {
             (self.0 >> (8 - DEPTH)) as usize
@@ -4,6 +4,7 @@ mod handle;
 mod label;
 mod map_id;
 mod node;
+mod ordered_index;
 mod split;
 mod store_impl;
 mod wrap;
@@ -16,6 +17,7 @@ pub(crate) use handle::Handle;
 pub(crate) use label::Label;
 pub(crate) use map_id::MapId;
 pub(crate) use node::Node;
+pub(crate) use ordered_index::{KeyIndex, OrderedIndex, Step};
 pub(crate) use split::Split;
-pub(crate) use store_impl::Store;
+pub(crate) use store_impl::{Extractor, Store, RECEIVES, VECTORS};
 pub(crate) use wrap::Wrap;
@@ -0,0 +1,338 @@
+use crate::{
+    common::{
+        data::Bytes,
+        store::{hash, Field},
+    },
+    database::{
+        interact::{diff, Action, Batch},
+        store::{Label, Node, Store, Wrap},
+        RangeProof,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use std::{cmp::Ordering, ops::Bound, sync::Arc};
+
+/// An index over the records of a table, maintained by `Handle::apply`.
+///
+/// Object-safe, so that a `Handle` can hold an index regardless of the
+/// bounds (e.g., `Key: Ord`) the index requires.
+pub(crate) trait KeyIndex<Key: Field, Value: Field>: Send + Sync {
+    fn update(&mut self, batch: &Batch<Key, Value>);
+    fn commit(&self) -> Bytes;
+    fn range(&self, start: Bound<&Key>, end: Bound<&Key>) -> Vec<(Arc<Key>, Arc<Value>)>;
+    fn prove(&self, start: Bound<&Key>, end: Bound<&Key>) -> RangeProof<Key, Value>;
+    fn boxed_clone(&self) -> Box<dyn KeyIndex<Key, Value>>;
+}
+
+/// Records of a table, ordered by `Key`, in a Merkle treap: a binary search
+/// tree by `Key`, heap-ordered by the digest of each `Key`.
+///
+/// The shape of a treap depends only on its records, so replicas holding the
+/// same records agree on `commit`. Nodes are immutable and shared, as are keys
+/// and values with the tree: clones of the index are O(1), and an update only
+/// copies the nodes along the paths it changes.
+pub(crate) struct OrderedIndex<Key: Field, Value: Field> {
+    root: Link<Key, Value>,
+}
+
+type Link<Key, Value> = Option<Arc<IndexNode<Key, Value>>>;
+
+struct IndexNode<Key: Field, Value: Field> {
+    key: Wrap<Key>,
+    value: Wrap<Value>,
+    left: Link<Key, Value>,
+    right: Link<Key, Value>,
+    hash: Bytes,
+}
+
+/// A step of the pre-order walk encoded by a `RangeProof`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum Step<Key, Value> {
+    Stub(Bytes),
+    Node(Arc<Key>, Arc<Value>),
+}
+
+impl<Key, Value> OrderedIndex<Key, Value>
+where
+    Key: Field + Ord,
+    Value: Field,
+{
+    /// Indexes the records of the tree rooted at `root` in `store`.
+    pub fn build(store: &mut Store<Key, Value>, root: Label) -> Self {
+        let mut index = OrderedIndex { root: None };
+        index.collect(store, root);
+        index
+    }
+
+    fn collect(&mut self, store: &mut Store<Key, Value>, label: Label) {
+        match diff::get(store, label) {
+            Node::Internal(left, right) => {
+                self.collect(store, left);
+                self.collect(store, right);
+            }
+            Node::Leaf(key, value) => {
+                self.root = insert(&self.root, key, value);
+            }
+            Node::Empty => {}
+        }
+    }
+}
+
+impl<Key, Value> IndexNode<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn new(
+        key: Wrap<Key>,
+        value: Wrap<Value>,
+        left: Link<Key, Value>,
+        right: Link<Key, Value>,
+    ) -> Arc<Self> {
+        let hash = hash::index(
+            hash::leaf(key.digest(), value.digest()),
+            hash_of(&left),
+            hash_of(&right),
+        );
+
+        Arc::new(IndexNode {
+            key,
+            value,
+            left,
+            right,
+            hash,
+        })
+    }
+
+    // Copies `self` (but not its children) with `left` and `right` as children
+    fn with(&self, left: Link<Key, Value>, right: Link<Key, Value>) -> Arc<Self> {
+        IndexNode::new(self.key.clone(), self.value.clone(), left, right)
+    }
+}
+
+fn hash_of<Key, Value>(link: &Link<Key, Value>) -> Bytes
+where
+    Key: Field,
+    Value: Field,
+{
+    link.as_ref().map_or(hash::empty(), |node| node.hash)
+}
+
+// Splits `link` into the records before and after `key` (`key` excluded)
+fn split<Key, Value>(link: &Link<Key, Value>, key: &Key) -> (Link<Key, Value>, Link<Key, Value>)
+where
+    Key: Field + Ord,
+    Value: Field,
+{
+    match link {
+        None => (None, None),
+        Some(node) => match key.cmp(&**node.key.inner()) {
+            Ordering::Less => {
+                let (left, right) = split(&node.left, key);
+                (left, Some(node.with(right, node.right.clone())))
+            }
+            Ordering::Greater => {
+                let (left, right) = split(&node.right, key);
+                (Some(node.with(node.left.clone(), left)), right)
+            }
+            Ordering::Equal => (node.left.clone(), node.right.clone()),
+        },
+    }
+}
+
+// Joins `left` and `right`, all of whose keys are smaller than those in `right`
+fn merge<Key, Value>(left: &Link<Key, Value>, right: &Link<Key, Value>) -> Link<Key, Value>
+where
+    Key: Field + Ord,
+    Value: Field,
+{
+    match (left, right) {
+        (None, link) | (link, None) => link.clone(),
+        (Some(left_node), Some(right_node)) => {
+            if priority(&left_node.key) > priority(&right_node.key) {
+                Some(left_node.with(left_node.left.clone(), merge(&left_node.right, right)))
+            } else {
+                Some(right_node.with(merge(left, &right_node.left), right_node.right.clone()))
+            }
+        }
+    }
+}
+
+// Nodes of higher priority lie above those of lower priority
+fn priority<Key>(key: &Wrap<Key>) -> (Bytes, &Arc<Key>)
+where
+    Key: Field,
+{
+    (key.digest(), key.inner())
+}
+
+fn insert<Key, Value>(
+    link: &Link<Key, Value>,
+    key: Wrap<Key>,
+    value: Wrap<Value>,
+) -> Link<Key, Value>
+where
+    Key: Field + Ord,
+    Value: Field,
+{
+    let node = match link {
+        None => return Some(IndexNode::new(key, value, None, None)),
+        Some(node) => node,
+    };
+
+    match key.inner().cmp(node.key.inner()) {
+        Ordering::Equal => Some(IndexNode::new(
+            key,
+            value,
+            node.left.clone(),
+            node.right.clone(),
+        )),
+        _ if priority(&key) > priority(&node.key) => {
+            let (left, right) = split(link, key.inner());
+            Some(IndexNode::new(key, value, left, right))
+        }
+        Ordering::Less => Some(node.with(insert(&node.left, key, value), node.right.clone())),
+        Ordering::Greater => Some(node.with(node.left.clone(), insert(&node.right, key, value))),
+    }
+}
+
+// Returns `None` if `key` is not in `link`, leaving `link` unchanged
+fn remove<Key, Value>(link: &Link<Key, Value>, key: &Key) -> Option<Link<Key, Value>>
+where
+    Key: Field + Ord,
+    Value: Field,
+{
+    let node = link.as_ref()?;
+
+    match key.cmp(&**node.key.inner()) {
+        Ordering::Less => {
+            let left = remove(&node.left, key)?;
+            Some(Some(node.with(left, node.right.clone())))
+        }
+        Ordering::Greater => {
+            let right = remove(&node.right, key)?;
+            Some(Some(node.with(node.left.clone(), right)))
+        }
+        Ordering::Equal => Some(merge(&node.left, &node.right)),
+    }
+}
+
+fn before<Key: Ord>(key: &Key, start: Bound<&Key>) -> bool {
+    match start {
+        Bound::Included(start) => key < start,
+        Bound::Excluded(start) => key <= start,
+        Bound::Unbounded => false,
+    }
+}
+
+fn after<Key: Ord>(key: &Key, end: Bound<&Key>) -> bool {
+    match end {
+        Bound::Included(end) => key > end,
+        Bound::Excluded(end) => key >= end,
+        Bound::Unbounded => false,
+    }
+}
+
+fn collect_range<Key, Value>(
+    link: &Link<Key, Value>,
+    start: Bound<&Key>,
+    end: Bound<&Key>,
+    records: &mut Vec<(Arc<Key>, Arc<Value>)>,
+) where
+    Key: Field + Ord,
+    Value: Field,
+{
+    if let Some(node) = link {
+        let key = node.key.inner();
+
+        if !before(key.as_ref(), start) {
+            collect_range(&node.left, start, end, records);
+        }
+
+        if !before(key.as_ref(), start) && !after(key.as_ref(), end) {
+            records.push((key.clone(), node.value.inner().clone()));
+        }
+
+        if !after(key.as_ref(), end) {
+            collect_range(&node.right, start, end, records);
+        }
+    }
+}
+
+// Walks `link` in pre-order, revealing the nodes that could lie in range
+// and the path to them, and stubbing every other subtree
+fn collect_proof<Key, Value>(
+    link: &Link<Key, Value>,
+    start: Bound<&Key>,
+    end: Bound<&Key>,
+    steps: &mut Vec<Step<Key, Value>>,
+) where
+    Key: Field + Ord,
+    Value: Field,
+{
+    match link {
+        None => steps.push(Step::Stub(hash::empty())),
+        Some(node) => {
+            let key = node.key.inner();
+            steps.push(Step::Node(key.clone(), node.value.inner().clone()));
+
+            if before(key.as_ref(), start) {
+                steps.push(Step::Stub(hash_of(&node.left)));
+            } else {
+                collect_proof(&node.left, start, end, steps);
+            }
+
+            if after(key.as_ref(), end) {
+                steps.push(Step::Stub(hash_of(&node.right)));
+            } else {
+                collect_proof(&node.right, start, end, steps);
+            }
+        }
+    }
+}
+
+impl<Key, Value> KeyIndex<Key, Value> for OrderedIndex<Key, Value>
+where
+    Key: Field + Ord,
+    Value: Field,
+{
+    fn update(&mut self, batch: &Batch<Key, Value>) {
+        for operation in batch.operations() {
+            match &operation.action {
+                Action::Set(key, value) => {
+                    self.root = insert(&self.root, key.clone(), value.clone());
+                }
+                Action::Remove(key) => {
+                    if let Some(root) = remove(&self.root, key.inner()) {
+                        self.root = root;
+                    }
+                }
+                Action::Get(..) => {}
+            }
+        }
+    }
+
+    fn commit(&self) -> Bytes {
+        hash_of(&self.root)
+    }
+
+    fn range(&self, start: Bound<&Key>, end: Bound<&Key>) -> Vec<(Arc<Key>, Arc<Value>)> {
+        let mut records = Vec::new();
+        collect_range(&self.root, start, end, &mut records);
+        records
+    }
+
+    fn prove(&self, start: Bound<&Key>, end: Bound<&Key>) -> RangeProof<Key, Value> {
+        let mut steps = Vec::new();
+        collect_proof(&self.root, start, end, &mut steps);
+        RangeProof::new(steps)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn KeyIndex<Key, Value>> {
+        Box::new(OrderedIndex {
+            root: self.root.clone(),
+        })
+    }
+}
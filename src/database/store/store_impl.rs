@@ -1,13 +1,16 @@
 use crate::{
     common::{data::Bytes, store::Field, tree::Prefix},
     database::{
+        errors::{CertificateError, QueryError, TableError},
         interact::{Action, Batch},
         store::{Entry, Label, MapId, Node, Split},
         Table, TableTransaction,
     },
 };
 
-use rocksdb::{Error, WriteBatchWithTransaction, DB};
+use doomstack::{here, Doom, ResultExt, Top};
+
+use rocksdb::{Error, Options, WriteBatchWithTransaction, DB, DEFAULT_COLUMN_FAMILY_NAME};
 
 use oh_snap::Snap;
 
@@ -19,20 +22,53 @@ use std::{
         },
         HashMap,
     },
-    iter,
+    iter, mem,
     sync::Arc,
 };
 
 pub(crate) type EntryMap<Key, Value> = HashMap<Bytes, Entry<Key, Value>>;
 pub(crate) type EntryMapEntry<'a, Key, Value> = HashMapEntry<'a, Bytes, Entry<Key, Value>>;
-pub(crate) type BackupRestore<'a, Key, Value> = (Store<Key, Value>, HashMap<std::string::String, (&'a Arc<Table<Key, Value>>, TableTransaction<Key, Value>)>);
+pub(crate) type BackupRestore<'a, Key, Value> = HashMap<std::string::String, (&'a Arc<Table<Key, Value>>, TableTransaction<Key, Value>)>;
 
 pub(crate) const DEPTH: u8 = 8;
 
+/// Number of records copied by each write of `Store::copy_backup`.
+const COPY_CHUNK: usize = 1 << 12;
+
+/// Column family holding [`PersistentVector`]s. Kept apart from the default
+/// column family, which `restore_backup` expects to contain only table records.
+///
+/// [`PersistentVector`]: crate::database::PersistentVector
+pub(crate) const VECTORS: &str = "vectors";
+
+/// Column family holding, for each table name, the last version certified
+/// by `Table::certify`.
+pub(crate) const CERTIFICATES: &str = "certificates";
+
+/// Column family holding the checkpoints of `TableReceiver`s.
+pub(crate) const RECEIVES: &str = "receives";
+
+/// Column family holding the entries of value indexes (see
+/// `Database::register_index`): one `(table name, index name, index key, key)`
+/// entry, with an empty value, per indexed record.
+///
+/// Extractors are not persisted: entries left by a previous run go stale as
+/// soon as a record changes before its index is registered again. They are
+/// never read in the meantime, as `lookup_index` requires the index to be
+/// registered, and `register_index` rebuilds them.
+pub(crate) const INDEXES: &str = "indexes";
+
+/// Extracts, from a record, the serialized key under which it is indexed.
+pub(crate) type Extractor<Key, Value> = Arc<dyn Fn(&Key, &Value) -> Vec<u8> + Send + Sync>;
+
+/// The `Extractor`s of each index, by table name and index name.
+type Indexes<Key, Value> = HashMap<String, HashMap<String, Extractor<Key, Value>>>;
+
 pub(crate) struct Store<Key: Field, Value: Field> {
    pub(crate) db: Arc<DB>,
     maps: Snap<EntryMap<Key, Value>>,
     scope: Prefix,
+    indexes: Arc<Indexes<Key, Value>>,
 }
 
 impl<Key, Value> Store<Key, Value>
@@ -40,36 +76,56 @@ where
     Key: Field,
     Value: Field,
 {
-    pub fn new(backup_folder_path: &str) -> Self {
-        Store {
-            db: Arc::new(DB::open_default(backup_folder_path).unwrap()),
+    pub fn new(backup_folder_path: &str) -> Result<Self, Top<TableError>> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let db = DB::open_cf(
+            &options,
+            backup_folder_path,
+            [DEFAULT_COLUMN_FAMILY_NAME, VECTORS, CERTIFICATES, RECEIVES, INDEXES],
+        )
+        .pot(TableError::StorageError, here!())?;
+
+        Ok(Store {
+            db: Arc::new(db),
             maps: Snap::new(iter::repeat_with(EntryMap::new).take(1 << DEPTH).collect()),
             scope: Prefix::root(),
-        }
+            indexes: Arc::new(HashMap::new()),
+        })
     }
 
-    pub(crate) fn restore_backup(self, tables: &HashMap<String, Arc<Table<Key, Value>>>) -> BackupRestore<Key, Value> {
+    /// Reads the records backed up for `tables`, returning, for each table,
+    /// the transaction that restores them. Records backed up for other names
+    /// (e.g., a table whose creation was interrupted) are skipped.
+    pub(crate) fn restore_backup<'a>(
+        &self,
+        tables: &'a HashMap<String, Arc<Table<Key, Value>>>,
+    ) -> Result<BackupRestore<'a, Key, Value>, Top<TableError>> {
         let mut table_transactions = tables
             .iter()
             .map(|f| (f.0.clone(), (f.1, TableTransaction::default())))
-            .collect::<HashMap<String, (&Arc<Table<Key, Value>>, TableTransaction<Key, Value>)>>();
+            .collect::<BackupRestore<Key, Value>>();
 
         let mut iter = self.db.raw_iterator();
 
         iter.seek_to_first();
         while iter.valid() {
-            let (table_name, key) = bincode::deserialize::<(String, Key)>(iter.key().unwrap()).unwrap();
-            let value =
-                bincode::deserialize::<Value>(iter.value().unwrap()).unwrap();
-            let _ = table_transactions
-                .get_mut(&table_name)
-                .unwrap()
-                .1
-                .set(key, value);
+            let (table_name, key) = bincode::deserialize::<(String, Key)>(iter.key().unwrap())
+                .pot(TableError::BackupCorrupt, here!())?;
+
+            if let Some((_, transaction)) = table_transactions.get_mut(&table_name) {
+                let value = bincode::deserialize::<Value>(iter.value().unwrap())
+                    .pot(TableError::BackupCorrupt, here!())?;
+
+                let _ = transaction.set(key, value);
+            }
+
             iter.next();
         }
-       
-        (self.clone(), table_transactions)
+
+        Ok(table_transactions)
     }
 
     pub fn merge(left: Self, right: Self) -> Self {
@@ -77,6 +133,7 @@ where
             db: left.db.clone(),
             maps: Snap::merge(right.maps, left.maps),
             scope: left.scope.ancestor(1),
+            indexes: left.indexes,
         }
     }
 
@@ -90,12 +147,14 @@ where
                 db: self.db.clone(),
                 maps: left_maps,
                 scope: self.scope.left(),
+                indexes: self.indexes.clone(),
             };
 
             let right = Store {
                 db: self.db.clone(),
                 maps: right_maps,
                 scope: self.scope.right(),
+                indexes: self.indexes,
             };
 
             Split::Split(left, right)
@@ -104,25 +163,278 @@ where
         }
     }
 
-    pub fn backup(&self, batch: &Batch<Key, Value>, table_name: String) -> Result<(), Error> {
+    pub fn backup(
+        &self,
+        batch: &Batch<Key, Value>,
+        table_name: String,
+    ) -> Result<(), Top<TableError>> {
         let mut rocks_batch = WriteBatchWithTransaction::<false>::default();
         for operation in batch.operations() {
             match operation.action {
                 Action::Set(ref key, ref value) => {
+                    self.index(&mut rocks_batch, &table_name, key.inner(), Some(value.inner()))?;
                     rocks_batch.put(
                         bincode::serialize(&(&table_name, key.inner())).unwrap(),
                         bincode::serialize(&value.inner()).unwrap(),
                     );
                 }
                 Action::Remove(ref key) => {
+                    self.index(&mut rocks_batch, &table_name, key.inner(), None)?;
                     rocks_batch.delete(bincode::serialize(&(&table_name, key.inner())).unwrap());
                 }
                 Action::Get(..) => {}
             }
         }
+        self.db.write(rocks_batch).pot(TableError::BackupFailed, here!())
+    }
+
+    /// Backs up `records` as the contents of `table_name`, without going
+    /// through a `Batch` (see `Database::table_from_map`).
+    pub fn backup_records<'a, I>(
+        &self,
+        records: I,
+        table_name: String,
+    ) -> Result<(), Top<TableError>>
+    where
+        I: IntoIterator<Item = (&'a Key, &'a Value)>,
+    {
+        let mut rocks_batch = WriteBatchWithTransaction::<false>::default();
+        for (key, value) in records {
+            self.index(&mut rocks_batch, &table_name, key, Some(value))?;
+            rocks_batch.put(
+                bincode::serialize(&(&table_name, key)).unwrap(),
+                bincode::serialize(value).unwrap(),
+            );
+        }
+        self.db.write(rocks_batch).pot(TableError::BackupFailed, here!())
+    }
+
+    /// Backs up the records backed up for `source` as the contents of
+    /// `table_name`, copying them in chunks of `COPY_CHUNK` records (see
+    /// `Database::derive_table`). On failure, the records copied so far are
+    /// deleted.
+    pub fn copy_backup(&self, source: &str, table_name: &str) -> Result<(), Error> {
+        let result = self.copy_records(source, table_name);
+
+        if result.is_err() {
+            // Otherwise, the records copied would be restored as `table_name`'s
+            let _ = self.delete_records(table_name);
+        }
+
+        result
+    }
+
+    fn copy_records(&self, source: &str, table_name: &str) -> Result<(), Error> {
+        let source = bincode::serialize(source).unwrap();
+        let prefix = bincode::serialize(table_name).unwrap();
+
+        let mut rocks_batch = WriteBatchWithTransaction::<false>::default();
+        let mut iter = self.db.raw_iterator();
+
+        // Record keys are serialized `(table name, key)` pairs: only the
+        // table name is replaced
+        iter.seek(&source);
+        while iter.valid() && iter.key().unwrap().starts_with(&source) {
+            let mut key = prefix.clone();
+            key.extend_from_slice(&iter.key().unwrap()[source.len()..]);
+
+            rocks_batch.put(key, iter.value().unwrap());
+
+            if rocks_batch.len() == COPY_CHUNK {
+                self.db.write(mem::take(&mut rocks_batch))?;
+            }
+
+            iter.next();
+        }
+
         self.db.write(rocks_batch)
     }
 
+    fn delete_records(&self, table_name: &str) -> Result<(), Error> {
+        let prefix = bincode::serialize(table_name).unwrap();
+
+        let mut rocks_batch = WriteBatchWithTransaction::<false>::default();
+        let mut iter = self.db.raw_iterator();
+
+        iter.seek(&prefix);
+        while iter.valid() && iter.key().unwrap().starts_with(&prefix) {
+            rocks_batch.delete(iter.key().unwrap());
+            iter.next();
+        }
+
+        self.db.write(rocks_batch)
+    }
+
+    // Adds to `rocks_batch` the changes to the indexes of `table_name` caused by
+    // setting (or removing, if `value` is `None`) the record of `key`. Entries
+    // of the record's previous value are read from its backup.
+    fn index(
+        &self,
+        rocks_batch: &mut WriteBatchWithTransaction<false>,
+        table_name: &str,
+        key: &Key,
+        value: Option<&Value>,
+    ) -> Result<(), Top<TableError>> {
+        let indexes = match self.indexes.get(table_name) {
+            Some(indexes) => indexes,
+            None => return Ok(()),
+        };
+
+        let family = self.db.cf_handle(INDEXES).unwrap();
+
+        let previous = self
+            .db
+            .get(bincode::serialize(&(table_name, key)).unwrap())
+            .pot(TableError::BackupFailed, here!())?
+            .map(|raw| bincode::deserialize::<Value>(&raw))
+            .transpose()
+            .pot(TableError::BackupCorrupt, here!())?;
+
+        for (index_name, extractor) in indexes {
+            // Deletions and insertions are applied in order: an unchanged entry is kept
+            if let Some(previous) = previous.as_ref() {
+                rocks_batch.delete_cf(
+                    &family,
+                    index_entry(table_name, index_name, &extractor(key, previous), key),
+                );
+            }
+
+            if let Some(value) = value {
+                rocks_batch.put_cf(
+                    &family,
+                    index_entry(table_name, index_name, &extractor(key, value), key),
+                    [],
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Registers `extractor` as the index `index_name` of `table_name`, then
+    /// builds its entries from the records backed up for `table_name`,
+    /// replacing any entry left by a previous registration.
+    pub fn register_index(
+        &mut self,
+        table_name: &str,
+        index_name: &str,
+        extractor: Extractor<Key, Value>,
+    ) -> Result<(), Top<TableError>> {
+        let family = self.db.cf_handle(INDEXES).unwrap();
+        let mut rocks_batch = WriteBatchWithTransaction::<false>::default();
+
+        let prefix = bincode::serialize(&(table_name, index_name)).unwrap();
+        let mut iter = self.db.raw_iterator_cf(&family);
+
+        iter.seek(&prefix);
+        while iter.valid() && iter.key().unwrap().starts_with(&prefix) {
+            rocks_batch.delete_cf(&family, iter.key().unwrap());
+            iter.next();
+        }
+
+        let prefix = bincode::serialize(table_name).unwrap();
+        let mut iter = self.db.raw_iterator();
+
+        iter.seek(&prefix);
+        while iter.valid() && iter.key().unwrap().starts_with(&prefix) {
+            let (_, key) = bincode::deserialize::<(String, Key)>(iter.key().unwrap())
+                .pot(TableError::BackupCorrupt, here!())?;
+            let value = bincode::deserialize::<Value>(iter.value().unwrap())
+                .pot(TableError::BackupCorrupt, here!())?;
+
+            rocks_batch.put_cf(
+                &family,
+                index_entry(table_name, index_name, &extractor(&key, &value), &key),
+                [],
+            );
+
+            iter.next();
+        }
+
+        self.db.write(rocks_batch).pot(TableError::BackupFailed, here!())?;
+
+        Arc::make_mut(&mut self.indexes)
+            .entry(table_name.to_string())
+            .or_default()
+            .insert(index_name.to_string(), extractor);
+
+        Ok(())
+    }
+
+    /// Returns the extractor of the index `index_name` of `table_name`, along
+    /// with the keys whose entries hold `index_key`.
+    #[allow(clippy::type_complexity)]
+    pub fn lookup_index(
+        &self,
+        table_name: &str,
+        index_name: &str,
+        index_key: &[u8],
+    ) -> Result<(Extractor<Key, Value>, Vec<Key>), Top<QueryError>> {
+        let extractor = match self
+            .indexes
+            .get(table_name)
+            .and_then(|indexes| indexes.get(index_name))
+        {
+            Some(extractor) => extractor.clone(),
+            None => return QueryError::IndexMissing.fail().spot(here!()),
+        };
+
+        let family = self.db.cf_handle(INDEXES).unwrap();
+        let prefix = bincode::serialize(&(table_name, index_name, index_key)).unwrap();
+
+        let mut keys = Vec::new();
+        let mut iter = self.db.raw_iterator_cf(&family);
+
+        iter.seek(&prefix);
+        while iter.valid() && iter.key().unwrap().starts_with(&prefix) {
+            let (_, _, _, key) = bincode::deserialize::<(String, String, Vec<u8>, Key)>(
+                iter.key().unwrap(),
+            )
+            .pot(QueryError::IndexCorrupt, here!())?;
+
+            keys.push(key);
+            iter.next();
+        }
+
+        Ok((extractor, keys))
+    }
+
+    /// Returns the version following the last certified version of
+    /// `table_name`, without recording it (see `commit_version`).
+    pub fn next_version(&self, table_name: &str) -> Result<u64, Top<CertificateError>> {
+        let family = self.db.cf_handle(CERTIFICATES).unwrap();
+        let key = bincode::serialize(table_name).unwrap();
+
+        let raw = self
+            .db
+            .get_cf(&family, &key)
+            .pot(CertificateError::VersionStorage, here!())?;
+
+        match raw {
+            Some(raw) => {
+                let version = bincode::deserialize::<u64>(&raw)
+                    .pot(CertificateError::VersionStorage, here!())?;
+
+                Ok(version + 1)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Records `version` as the last certified version of `table_name`.
+    pub fn commit_version(
+        &mut self,
+        table_name: &str,
+        version: u64,
+    ) -> Result<(), Top<CertificateError>> {
+        let family = self.db.cf_handle(CERTIFICATES).unwrap();
+        let key = bincode::serialize(table_name).unwrap();
+
+        self.db
+            .put_cf(&family, &key, bincode::serialize(&version).unwrap())
+            .pot(CertificateError::VersionStorage, here!())
+    }
+
     pub fn entry(&mut self, label: Label) -> EntryMapEntry<Key, Value> {
         let map = label.map().id() - self.maps.range().start;
         let hash = label.hash();
@@ -214,6 +526,15 @@ where
     }
 }
 
+// Entries are serialized as a tuple whose fields are all length-prefixed (or
+// last), so that the entries of an index, or of an index key, share a prefix
+fn index_entry<Key>(table_name: &str, index_name: &str, index_key: &[u8], key: &Key) -> Vec<u8>
+where
+    Key: Field,
+{
+    bincode::serialize(&(table_name, index_name, index_key, key)).unwrap()
+}
+
 impl<Key, Value> Clone for Store<Key, Value>
 where
     Key: Field,
@@ -224,6 +545,7 @@ where
             db: self.db.clone(),
             maps: self.maps.clone(),
             scope: self.scope,
+            indexes: self.indexes.clone(),
         }
     }
 }
@@ -248,7 +570,7 @@ mod tests {
         where
             I: IntoIterator<Item = (Key, Value)>,
         {
-            let mut store = Store::new(backup_folder_path);
+            let mut store = Store::new(backup_folder_path).unwrap();
 
             let labels = leaves
                 .into_iter()
@@ -601,9 +923,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn indexes_kept_on_open() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let index_entries = |store: &Store<u32, u32>| {
+            let family = store.db.cf_handle(INDEXES).unwrap();
+            let mut iter = store.db.raw_iterator_cf(&family);
+            let mut entries = 0;
+
+            iter.seek_to_first();
+            while iter.valid() {
+                entries += 1;
+                iter.next();
+            }
+
+            entries
+        };
+
+        let extractor: Extractor<u32, u32> = Arc::new(|_, value| vec![*value as u8]);
+
+        {
+            let mut store = Store::<u32, u32>::new(&path).unwrap();
+            store.backup_records([(&0, &0), (&1, &1)], "test".to_string()).unwrap();
+
+            store.register_index("test", "index", extractor.clone()).unwrap();
+            assert_eq!(index_entries(&store), 2);
+        }
+
+        {
+            let mut store = Store::<u32, u32>::new(&path).unwrap();
+            assert_eq!(index_entries(&store), 2);
+
+            // Unregistered indexes are not updated, nor read
+            store.backup_records([(&1, &2)], "test".to_string()).unwrap();
+            assert!(store.lookup_index("test", "index", &[2]).is_err());
+
+            // Registering again replaces the stale entries
+            store.register_index("test", "index", extractor).unwrap();
+            assert_eq!(index_entries(&store), 2);
+
+            assert!(store.lookup_index("test", "index", &[1]).unwrap().1.is_empty());
+            assert_eq!(store.lookup_index("test", "index", &[2]).unwrap().1, vec![1]);
+        }
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
     #[test]
     fn test_if_size_of_store_entries_is_correct() {
-        let store = Store::<u32, u32>::new("test");
+        let store = Store::<u32, u32>::new("test").unwrap();
         assert_eq!(store.size(), 0);
 
         let leaves = (0..=8).map(|i| (i, i));
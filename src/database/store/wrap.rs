@@ -26,6 +26,13 @@ where
         })
     }
 
+    pub fn raw(digest: Bytes, inner: Inner) -> Self {
+        Wrap {
+            digest,
+            inner: Arc::new(inner),
+        }
+    }
+
     pub fn digest(&self) -> Bytes {
         self.digest
     }
@@ -0,0 +1,854 @@
+use crate::{
+    common::store::Field,
+    database::{
+        errors::SyncError, sync::SyncSettings, Question, Table, TableAnswer, TableReceiver,
+        TableSender, TableStatus,
+    },
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    time,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Settings for [`serve`] and [`fetch`].
+///
+/// [`serve`]: crate::database::sync::serve
+/// [`fetch`]: crate::database::sync::fetch
+#[derive(Debug, Clone)]
+pub struct DriverSettings {
+    /// Maximum time to wait for the peer's next message.
+    pub timeout: Duration,
+    /// Maximum size (in bytes) of a message, larger messages are refused.
+    pub max_frame_size: usize,
+}
+
+// The outcome of asking a `Question` to one of the peers of `fetch_many`
+struct Exchange<'c, Key: Field, Value: Field, C> {
+    peer: usize,
+    connection: &'c mut C,
+    sent: Option<Question>,
+    answer: Result<TableAnswer<Key, Value>, Top<SyncError>>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Request {
+    Hello,
+    Question(Question),
+    Done,
+    Negotiate(SyncSettings),
+}
+
+impl Default for DriverSettings {
+    fn default() -> Self {
+        DriverSettings {
+            timeout: DEFAULT_TIMEOUT,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+}
+
+/// Serves `sender` over `connection` until the peer's [`fetch`] completes.
+///
+/// `connection` can be any [`AsyncRead`] + [`AsyncWrite`] stream (e.g., a
+/// `TcpStream`, or the in-process half of a `tokio::io::duplex`). Each message
+/// is framed as a 4-byte big-endian length followed by its `bincode`
+/// serialization.
+///
+/// Before the transfer, the peer proposes its [`SyncSettings`], and each
+/// limit is lowered to `sender`'s if smaller. `sender` answers the peer
+/// within the settings agreed (its own settings are left unchanged).
+///
+/// Returns an error if the peer asks a malformed [`Question`], sends a
+/// malformed or oversized message, stays silent for longer than the
+/// timeout, or drops the connection before the transfer completes.
+/// Dropping the returned future cancels the transfer.
+///
+/// [`fetch`]: crate::database::sync::fetch
+/// [`Question`]: crate::database::Question
+/// [`SyncSettings`]: crate::database::sync::SyncSettings
+pub async fn serve<Key, Value, C>(
+    sender: &TableSender<Key, Value>,
+    connection: &mut C,
+) -> Result<(), Top<SyncError>>
+where
+    Key: Field,
+    Value: Field,
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    serve_with(sender, connection, &DriverSettings::default()).await
+}
+
+/// Like [`serve`], with custom [`DriverSettings`].
+///
+/// [`serve`]: crate::database::sync::serve
+pub async fn serve_with<Key, Value, C>(
+    sender: &TableSender<Key, Value>,
+    connection: &mut C,
+    settings: &DriverSettings,
+) -> Result<(), Top<SyncError>>
+where
+    Key: Field,
+    Value: Field,
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut agreed = sender.settings.clone();
+
+    loop {
+        let answer = match receive::<Request, _>(connection, settings).await? {
+            Request::Hello => sender.hello_with(&agreed),
+            Request::Question(question) => sender.answer_with(&question, &agreed)?,
+            Request::Done => return Ok(()),
+            Request::Negotiate(proposal) => {
+                agreed = sender.settings.negotiate(&proposal);
+                send(connection, &agreed, settings).await?;
+                continue;
+            }
+        };
+
+        send(connection, &answer, settings).await?;
+    }
+}
+
+/// Drives `receiver` over `connection` until the [`Table`] served by the
+/// peer's [`serve`] is received.
+///
+/// See [`serve`] for the requirements on `connection` and the wire format.
+///
+/// The settings of the transfer are negotiated first (see [`serve`]):
+/// `receiver` adopts the settings agreed by the peer, after checking that
+/// they do not exceed its own.
+///
+/// Returns an error if the peer agrees to settings that exceed `receiver`'s,
+/// sends a malformed or oversized message, a malicious [`TableAnswer`] (see
+/// [`TableReceiver::learn`]), stays silent for longer than the timeout, or
+/// drops the connection. Dropping the returned future cancels the transfer,
+/// releasing the nodes held by `receiver`.
+///
+/// # Examples
+///
+/// ```
+/// use tenaciouszebra::database::{sync, Database, TableTransaction};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let alice: Database<u32, u32> = Database::new("test");
+/// let bob: Database<u32, u32> = Database::new("test2");
+///
+/// let mut transaction = TableTransaction::default();
+/// transaction.set(0, 1).unwrap();
+///
+/// let original = alice.empty_table("test");
+/// original.execute(transaction);
+///
+/// let sender = original.send();
+/// let (mut server, mut client) = tokio::io::duplex(1024);
+///
+/// let (served, fetched) = tokio::join!(
+///     sync::serve(&sender, &mut server),
+///     sync::fetch(bob.receive(), &mut client)
+/// );
+///
+/// served.unwrap();
+/// assert_eq!(fetched.unwrap().commit(), original.commit());
+/// # }
+/// ```
+///
+/// [`Table`]: crate::database::Table
+/// [`serve`]: crate::database::sync::serve
+/// [`TableAnswer`]: crate::database::TableAnswer
+/// [`TableReceiver::learn`]: crate::database::TableReceiver::learn
+pub async fn fetch<Key, Value, C>(
+    receiver: TableReceiver<Key, Value>,
+    connection: &mut C,
+) -> Result<Table<Key, Value>, Top<SyncError>>
+where
+    Key: Field,
+    Value: Field,
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    fetch_with(receiver, connection, &DriverSettings::default()).await
+}
+
+/// Like [`fetch`], with custom [`DriverSettings`].
+///
+/// [`fetch`]: crate::database::sync::fetch
+pub async fn fetch_with<Key, Value, C>(
+    mut receiver: TableReceiver<Key, Value>,
+    connection: &mut C,
+    settings: &DriverSettings,
+) -> Result<Table<Key, Value>, Top<SyncError>>
+where
+    Key: Field,
+    Value: Field,
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    receiver.settings = negotiate(connection, &receiver.settings, settings).await?;
+
+    // A receiver expecting a known root can ask for it right away
+    let question = receiver.ask();
+
+    let mut request = if question.is_empty() {
+        Request::Hello
+    } else {
+        Request::Question(question)
+    };
+
+    loop {
+        send(connection, &request, settings).await?;
+
+        if let Request::Question(question) = &request {
+            receiver.count_sent(question);
+        }
+
+        let answer = receive::<TableAnswer<Key, Value>, _>(connection, settings).await?;
+
+        match receiver.learn(answer)? {
+            TableStatus::Complete(table) => {
+                send(connection, &Request::Done, settings).await?;
+                return Ok(table);
+            }
+            TableStatus::Incomplete(next, question) => {
+                receiver = next;
+                request = Request::Question(question);
+            }
+        }
+    }
+}
+
+/// Drives `receiver` over several `connections` in parallel, each served by
+/// a peer's [`serve`] of the same table, until the table is received.
+///
+/// `receiver` must have been created by [`Database::receive_expecting`] (see
+/// [`TableReceiver::ask_peer`]). The settings of the transfer are negotiated
+/// with every peer first, and `receiver` adopts the lowest settings agreed
+/// (see [`fetch`]). Every peer is asked a disjoint subset of
+/// the missing nodes, and answers are learned in the order they arrive: a
+/// peer is asked again as soon as its answer is learned. A peer that fails
+/// (malicious answer, malformed message, timeout, disconnection) is dropped,
+/// and the nodes it was asked are asked to the remaining peers.
+///
+/// Returns an error if every peer failed before the table was received, or
+/// if `receiver` has no expected root.
+///
+/// [`serve`]: crate::database::sync::serve
+/// [`fetch`]: crate::database::sync::fetch
+/// [`Database::receive_expecting`]: crate::database::Database::receive_expecting
+/// [`TableReceiver::ask_peer`]: crate::database::TableReceiver::ask_peer
+pub async fn fetch_many<Key, Value, C>(
+    mut receiver: TableReceiver<Key, Value>,
+    connections: &mut [C],
+    settings: &DriverSettings,
+) -> Result<Table<Key, Value>, Top<SyncError>>
+where
+    Key: Field,
+    Value: Field,
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut agreed = Vec::new();
+
+    for (peer, connection) in connections.iter_mut().enumerate() {
+        if let Ok(limits) = negotiate(connection, &receiver.settings, settings).await {
+            agreed.push((peer, limits));
+        }
+    }
+
+    // Every peer is held to the lowest settings agreed
+    let lowest = agreed
+        .iter()
+        .fold(receiver.settings.clone(), |lowest, (_, limits)| lowest.negotiate(limits));
+
+    let mut idle = Vec::new();
+
+    for (peer, connection) in connections.iter_mut().enumerate() {
+        let limits = match agreed.iter().find(|(agreed, _)| *agreed == peer) {
+            Some((_, limits)) => limits,
+            None => continue,
+        };
+
+        // Being within `peer`'s limits, `lowest` is agreed unchanged
+        let held = *limits == lowest
+            || matches!(
+                negotiate(connection, &lowest, settings).await,
+                Ok(limits) if limits == lowest
+            );
+
+        if held {
+            idle.push((peer, connection));
+        }
+    }
+
+    receiver.settings = lowest;
+
+    let mut pending = FuturesUnordered::new();
+
+    while !receiver.is_complete() {
+        let mut waiting = Vec::new();
+
+        // Every peer that is not working is asked what is left, so that
+        // the labels of a failed peer are asked again straight away
+        for (peer, connection) in idle {
+            let question = receiver.ask_peer(peer)?;
+
+            if question.is_empty() {
+                waiting.push((peer, connection));
+            } else {
+                pending.push(exchange(peer, connection, question, settings));
+            }
+        }
+
+        idle = waiting;
+
+        // Answers are learned in the order they arrive
+        let exchange = match pending.next().await {
+            Some(exchange) => exchange,
+            None => return SyncError::PeersExhausted.fail().spot(here!()),
+        };
+
+        if let Some(question) = &exchange.sent {
+            receiver.count_sent(question);
+        }
+
+        let learned = match exchange.answer {
+            Ok(answer) => receiver.learn_peer(exchange.peer, answer),
+            Err(e) => Err(e),
+        };
+
+        match learned {
+            Ok(()) => idle.push((exchange.peer, exchange.connection)),
+            Err(_) => receiver.release_peer(exchange.peer),
+        }
+    }
+
+    // Every label was answered, so no peer is still working
+    while let Some(exchange) = pending.next().await {
+        if exchange.answer.is_ok() {
+            idle.push((exchange.peer, exchange.connection));
+        }
+    }
+
+    for (_, connection) in idle {
+        // The table is complete: a peer failing to hang up is irrelevant
+        let _ = send(connection, &Request::Done, settings).await;
+    }
+
+    receiver.finish()
+}
+
+// Asks `question` to `peer` over `connection`, returning `connection` along
+// with `question` (if it was sent) and `peer`'s answer
+async fn exchange<'c, Key, Value, C>(
+    peer: usize,
+    connection: &'c mut C,
+    question: Question,
+    settings: &DriverSettings,
+) -> Exchange<'c, Key, Value, C>
+where
+    Key: Field,
+    Value: Field,
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let request = Request::Question(question);
+
+    let sent = send(connection, &request, settings).await;
+
+    let question = match request {
+        Request::Question(question) => question,
+        _ => unreachable!(),
+    };
+
+    let (sent, answer) = match sent {
+        Ok(()) => (Some(question), receive(connection, settings).await),
+        Err(e) => (None, Err(e)),
+    };
+
+    Exchange {
+        peer,
+        connection,
+        sent,
+        answer,
+    }
+}
+
+// Proposes `proposal` to the peer, returning the settings it agreed to
+async fn negotiate<C>(
+    connection: &mut C,
+    proposal: &SyncSettings,
+    settings: &DriverSettings,
+) -> Result<SyncSettings, Top<SyncError>>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    send(connection, &Request::Negotiate(proposal.clone()), settings).await?;
+
+    let agreed = receive::<SyncSettings, _>(connection, settings).await?;
+    proposal.validate(&agreed)?;
+
+    Ok(agreed)
+}
+
+async fn send<M, C>(
+    connection: &mut C,
+    message: &M,
+    settings: &DriverSettings,
+) -> Result<(), Top<SyncError>>
+where
+    M: Serialize,
+    C: AsyncWrite + Unpin,
+{
+    let payload = bincode::serialize(message).pot(SyncError::MalformedMessage, here!())?;
+
+    if payload.len() > settings.max_frame_size {
+        return SyncError::FrameTooLarge.fail().spot(here!());
+    }
+
+    let write = async {
+        connection.write_u32(payload.len() as u32).await?;
+        connection.write_all(&payload).await?;
+        connection.flush().await
+    };
+
+    time::timeout(settings.timeout, write)
+        .await
+        .pot(SyncError::Timeout, here!())?
+        .pot(SyncError::ConnectionError, here!())
+}
+
+async fn receive<M, C>(connection: &mut C, settings: &DriverSettings) -> Result<M, Top<SyncError>>
+where
+    M: DeserializeOwned,
+    C: AsyncRead + Unpin,
+{
+    let read = async {
+        let size = connection
+            .read_u32()
+            .await
+            .pot(SyncError::ConnectionError, here!())? as usize;
+
+        if size > settings.max_frame_size {
+            return SyncError::FrameTooLarge.fail().spot(here!());
+        }
+
+        let mut payload = vec![0u8; size];
+
+        connection
+            .read_exact(&mut payload)
+            .await
+            .pot(SyncError::ConnectionError, here!())?;
+
+        Ok(payload)
+    };
+
+    let payload = time::timeout(settings.timeout, read)
+        .await
+        .pot(SyncError::Timeout, here!())??;
+
+    bincode::deserialize(&payload).pot(SyncError::MalformedMessage, here!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{question::Ask, Database};
+
+    use tokio::io::duplex;
+
+    fn settings() -> DriverSettings {
+        DriverSettings {
+            timeout: Duration::from_millis(200),
+            max_frame_size: 1024 * 1024,
+        }
+    }
+
+    #[tokio::test]
+    async fn transfer() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let sender = original.send();
+
+        let (mut server, mut client) = duplex(4096);
+
+        let (served, fetched) = tokio::join!(
+            serve(&sender, &mut server),
+            fetch(bob.receive(), &mut client)
+        );
+
+        served.unwrap();
+        let received = fetched.unwrap();
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+    }
+
+    #[tokio::test]
+    async fn transfer_expecting() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let sender = original.send();
+
+        let (mut server, mut client) = duplex(4096);
+
+        let (served, fetched) = tokio::join!(
+            serve(&sender, &mut server),
+            fetch(bob.receive_expecting(original.commit()), &mut client)
+        );
+
+        served.unwrap();
+        assert_eq!(fetched.unwrap().commit(), original.commit());
+    }
+
+    #[tokio::test]
+    async fn unknown_commitment() {
+        let settings = settings();
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+        let carol: Database<u32, u32> = Database::new("test3");
+
+        let original = alice.table_with_records((0..8).map(|i| (i, i)));
+        let sender = original.send();
+
+        let expected = carol.table_with_records((0..8).map(|i| (i, i + 1)));
+
+        let (mut server, mut client) = duplex(4096);
+
+        let serving = async {
+            let served = serve_with(&sender, &mut server, &settings).await;
+            drop(server);
+            served
+        };
+
+        let (served, fetched) = tokio::join!(
+            serving,
+            fetch_with(bob.receive_expecting(expected.commit()), &mut client, &settings)
+        );
+
+        assert!(*served.err().unwrap().top() == SyncError::MalformedQuestion);
+        assert!(*fetched.err().unwrap().top() == SyncError::ConnectionError);
+
+        bob.check_correctness([], []);
+    }
+
+    #[tokio::test]
+    async fn negotiated() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+
+        let mut sender = original.send();
+        sender.settings.answer_depth = 8;
+        sender.settings.max_answer_nodes = usize::MAX;
+
+        // Answers within `sender`'s limits would be refused by `receiver`
+        let mut receiver = bob.receive();
+        receiver.settings.answer_depth = 1;
+        receiver.settings.max_answer_nodes = 16;
+        receiver.settings.window = 4;
+
+        let (mut server, mut client) = duplex(4096);
+
+        let (served, fetched) = tokio::join!(
+            serve(&sender, &mut server),
+            fetch(receiver, &mut client)
+        );
+
+        served.unwrap();
+        let received = fetched.unwrap();
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+
+        // `sender`'s own settings are left unchanged
+        assert_eq!(sender.settings.answer_depth, 8);
+    }
+
+    #[tokio::test]
+    async fn negotiation_failed() {
+        let settings = settings();
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let (mut server, mut client) = duplex(4096);
+
+        let malicious = async {
+            let proposal = match receive::<Request, _>(&mut server, &settings).await.unwrap() {
+                Request::Negotiate(proposal) => proposal,
+                _ => unreachable!(),
+            };
+
+            let mut agreed = proposal;
+            agreed.window += 1;
+
+            send(&mut server, &agreed, &settings).await.unwrap();
+        };
+
+        let (_, fetched) = tokio::join!(malicious, fetch_with(bob.receive(), &mut client, &settings));
+
+        assert!(*fetched.err().unwrap().top() == SyncError::NegotiationFailed);
+        bob.check_correctness([], []);
+    }
+
+    #[tokio::test]
+    async fn transfer_many() {
+        let settings = settings();
+
+        let databases = ["test", "test3", "test4"]
+            .iter()
+            .map(|path| Database::<u32, u32>::new(path))
+            .collect::<Vec<_>>();
+
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let originals = databases
+            .iter()
+            .map(|database| database.table_with_records((0..1024).map(|i| (i, i))))
+            .collect::<Vec<_>>();
+
+        let mut senders = originals.iter().map(|original| original.send()).collect::<Vec<_>>();
+
+        // All peers are held to the lowest limits
+        senders[1].settings.window = 32;
+
+        let (servers, mut clients): (Vec<_>, Vec<_>) = (0..3).map(|_| duplex(4096)).unzip();
+
+        let serving = async {
+            let mut servers = servers;
+            let (first, rest) = servers.split_at_mut(1);
+            let (second, third) = rest.split_at_mut(1);
+
+            tokio::join!(
+                serve_with(&senders[0], &mut first[0], &settings),
+                serve_with(&senders[1], &mut second[0], &settings),
+                serve_with(&senders[2], &mut third[0], &settings)
+            )
+        };
+
+        let ((first, second, third), fetched) = tokio::join!(
+            serving,
+            fetch_many(
+                bob.receive_expecting(originals[0].commit()),
+                &mut clients,
+                &settings
+            )
+        );
+
+        first.unwrap();
+        second.unwrap();
+        third.unwrap();
+
+        let received = fetched.unwrap();
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+    }
+
+    #[tokio::test]
+    async fn transfer_many_with_failures() {
+        let settings = settings();
+
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let sender = original.send();
+
+        let (servers, mut clients): (Vec<_>, Vec<_>) = (0..3).map(|_| duplex(4096)).unzip();
+        let mut servers = servers.into_iter();
+
+        let mut honest = servers.next().unwrap();
+        let _silent = servers.next().unwrap();
+        drop(servers.next().unwrap()); // Disconnected
+
+        let (served, fetched) = tokio::join!(
+            serve_with(&sender, &mut honest, &settings),
+            fetch_many(
+                bob.receive_expecting(original.commit()),
+                &mut clients,
+                &settings
+            )
+        );
+
+        served.unwrap();
+
+        let received = fetched.unwrap();
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+    }
+
+    #[tokio::test]
+    async fn transfer_many_with_stall() {
+        let settings = settings();
+
+        // The honest peer outlasts the stalled one
+        let patient = DriverSettings {
+            timeout: Duration::from_secs(5),
+            ..settings.clone()
+        };
+
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let sender = original.send();
+
+        let (mut stalled, stalled_client) = duplex(4096);
+        let (mut honest, honest_client) = duplex(4096);
+
+        let mut clients = vec![stalled_client, honest_client];
+
+        let stalling = async {
+            match receive::<Request, _>(&mut stalled, &settings).await.unwrap() {
+                Request::Negotiate(proposal) => {
+                    send(&mut stalled, &proposal, &settings).await.unwrap()
+                }
+                _ => unreachable!(),
+            }
+
+            // The root is asked first, and never answered
+            let question = receive::<Request, _>(&mut stalled, &patient).await.unwrap();
+            assert!(matches!(question, Request::Question(Question(Ask::Root(..)))));
+
+            stalled
+        };
+
+        let (_stalled, served, fetched) = tokio::join!(
+            stalling,
+            serve_with(&sender, &mut honest, &patient),
+            fetch_many(
+                bob.receive_expecting(original.commit()),
+                &mut clients,
+                &settings
+            )
+        );
+
+        served.unwrap();
+
+        let received = fetched.unwrap();
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+    }
+
+    #[tokio::test]
+    async fn peers_exhausted() {
+        let settings = settings();
+
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..8).map(|i| (i, i)));
+
+        let (servers, mut clients): (Vec<_>, Vec<_>) = (0..2).map(|_| duplex(4096)).unzip();
+        drop(servers);
+
+        let fetched = fetch_many(
+            bob.receive_expecting(original.commit()),
+            &mut clients,
+            &settings,
+        )
+        .await;
+
+        assert!(*fetched.err().unwrap().top() == SyncError::PeersExhausted);
+        bob.check_correctness([], []);
+    }
+
+    #[tokio::test]
+    async fn garbage() {
+        let settings = settings();
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let (mut server, mut client) = duplex(4096);
+
+        let malicious = async {
+            receive::<Request, _>(&mut server, &settings).await.unwrap();
+            server.write_u32(4).await.unwrap();
+            server.write_all(&[0xff; 4]).await.unwrap();
+        };
+
+        let (_, fetched) = tokio::join!(malicious, fetch_with(bob.receive(), &mut client, &settings));
+
+        assert!(*fetched.err().unwrap().top() == SyncError::MalformedMessage);
+    }
+
+    #[tokio::test]
+    async fn oversized() {
+        let settings = settings();
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let (mut server, mut client) = duplex(4096);
+
+        let malicious = async {
+            receive::<Request, _>(&mut server, &settings).await.unwrap();
+            server.write_u32(u32::MAX).await.unwrap();
+        };
+
+        let (_, fetched) = tokio::join!(malicious, fetch_with(bob.receive(), &mut client, &settings));
+
+        assert!(*fetched.err().unwrap().top() == SyncError::FrameTooLarge);
+    }
+
+    #[tokio::test]
+    async fn silent() {
+        let settings = settings();
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let (_server, mut client) = duplex(4096);
+
+        let fetched = fetch_with(bob.receive(), &mut client, &settings).await;
+        assert!(*fetched.err().unwrap().top() == SyncError::Timeout);
+    }
+
+    #[tokio::test]
+    async fn disconnected() {
+        let settings = settings();
+        let alice: Database<u32, u32> = Database::new("test");
+
+        let original = alice.table_with_records((0..8).map(|i| (i, i)));
+        let sender = original.send();
+
+        let (mut server, client) = duplex(4096);
+        drop(client);
+
+        let served = serve_with(&sender, &mut server, &settings).await;
+        assert!(*served.err().unwrap().top() == SyncError::ConnectionError);
+    }
+
+    #[tokio::test]
+    async fn malformed_question() {
+        let settings = settings();
+        let alice: Database<u32, u32> = Database::new("test");
+        let carol: Database<u32, u32> = Database::new("test3");
+
+        let original = alice.table_with_records((0..8).map(|i| (i, i)));
+        let sender = original.send();
+
+        let other = carol.table_with_records((0..8).map(|i| (i, i + 1)));
+
+        let (mut server, mut client) = duplex(4096);
+
+        let malicious = async {
+            let question = Request::Question(Question::labels(vec![other.root()]));
+            send(&mut client, &question, &settings).await.unwrap();
+        };
+
+        let (served, _) = tokio::join!(serve_with(&sender, &mut server, &settings), malicious);
+
+        assert!(*served.err().unwrap().top() == SyncError::MalformedQuestion);
+    }
+}
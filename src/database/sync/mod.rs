@@ -1,7 +1,15 @@
+mod driver;
+mod settings;
 mod severity;
+mod stats;
 
 pub(crate) const ANSWER_DEPTH: u8 = 2;
 
 pub(crate) mod locate;
 
 pub(crate) use severity::Severity;
+pub(crate) use stats::Counters;
+
+pub use driver::{fetch, fetch_many, fetch_with, serve, serve_with, DriverSettings};
+pub use settings::SyncSettings;
+pub use stats::SyncStats;
@@ -0,0 +1,158 @@
+use crate::{
+    common::store::Field,
+    database::{errors::SyncError, sync::ANSWER_DEPTH, TableAnswer},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_WINDOW: usize = 128;
+const DEFAULT_MAX_ANSWER_BYTES: usize = 64 * 1024 * 1024;
+
+/// Limits of a sync between a [`TableSender`] and a [`TableReceiver`].
+///
+/// A [`TableSender`] never exceeds its limits when answering, and a
+/// [`TableReceiver`] refuses answers that exceed its limits with
+/// [`SyncError::AnswerTooLarge`]. The drivers in [`sync`] negotiate the
+/// settings of each transfer, taking the lower of the two ends' values for
+/// every limit. Ends driven by hand are expected to agree on the same
+/// `SyncSettings`.
+///
+/// [`TableSender`]: crate::database::TableSender
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`SyncError::AnswerTooLarge`]: crate::database::errors::SyncError::AnswerTooLarge
+/// [`sync`]: crate::database::sync
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSettings {
+    /// Number of levels below each asked node included in an answer.
+    pub answer_depth: u8,
+    /// Maximum number of nodes in an answer.
+    pub max_answer_nodes: usize,
+    /// Maximum size (in bytes) of a serialized answer.
+    pub max_answer_bytes: usize,
+    /// Maximum number of nodes asked in a question.
+    pub window: usize,
+}
+
+impl SyncSettings {
+    // Maximum number of nodes a full answer to a single label can contain,
+    // saturating for depths past the width of `usize`
+    pub(crate) fn subtree_nodes(&self) -> usize {
+        1usize
+            .checked_shl(self.answer_depth as u32 + 1)
+            .map_or(usize::MAX, |nodes| nodes - 1)
+    }
+
+    // Returns the settings agreed with a peer proposing `proposal`
+    pub(crate) fn negotiate(&self, proposal: &SyncSettings) -> SyncSettings {
+        SyncSettings {
+            answer_depth: self.answer_depth.min(proposal.answer_depth),
+            max_answer_nodes: self.max_answer_nodes.min(proposal.max_answer_nodes),
+            max_answer_bytes: self.max_answer_bytes.min(proposal.max_answer_bytes),
+            window: self.window.min(proposal.window),
+        }
+    }
+
+    // Checks that the settings `agreed` by a peer are within `self`, and
+    // allow the transfer to make progress
+    pub(crate) fn validate(&self, agreed: &SyncSettings) -> Result<(), Top<SyncError>> {
+        if agreed.answer_depth > self.answer_depth
+            || agreed.max_answer_nodes > self.max_answer_nodes
+            || agreed.max_answer_bytes > self.max_answer_bytes
+            || agreed.window > self.window
+            || agreed.max_answer_nodes == 0
+            || agreed.window == 0
+        {
+            return SyncError::NegotiationFailed.fail().spot(here!());
+        }
+
+        Ok(())
+    }
+
+    // Returns the serialized size of `answer`
+    pub(crate) fn check<Key, Value>(
+        &self,
+        answer: &TableAnswer<Key, Value>,
+    ) -> Result<u64, Top<SyncError>>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        if answer.0.len() > self.max_answer_nodes {
+            return SyncError::AnswerTooLarge.fail().spot(here!());
+        }
+
+        let bytes = bincode::serialized_size(answer).pot(SyncError::MalformedAnswer, here!())?;
+
+        if bytes > self.max_answer_bytes as u64 {
+            return SyncError::AnswerTooLarge.fail().spot(here!());
+        }
+
+        Ok(bytes)
+    }
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        SyncSettings {
+            answer_depth: ANSWER_DEPTH,
+            // Enough to fully answer a full window
+            max_answer_nodes: DEFAULT_WINDOW * ((1 << (ANSWER_DEPTH + 1)) - 1),
+            max_answer_bytes: DEFAULT_MAX_ANSWER_BYTES,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subtree_nodes() {
+        let subtree_nodes = |answer_depth| {
+            SyncSettings {
+                answer_depth,
+                ..SyncSettings::default()
+            }
+            .subtree_nodes()
+        };
+
+        assert_eq!(subtree_nodes(0), 1);
+        assert_eq!(subtree_nodes(2), 7);
+        assert_eq!(subtree_nodes((usize::BITS - 2) as u8), usize::MAX >> 1);
+
+        for answer_depth in (usize::BITS - 1) as u8..=u8::MAX {
+            assert_eq!(subtree_nodes(answer_depth), usize::MAX);
+        }
+    }
+
+    #[test]
+    fn negotiate() {
+        let local = SyncSettings::default();
+
+        let proposal = SyncSettings {
+            answer_depth: u8::MAX,
+            max_answer_nodes: 16,
+            max_answer_bytes: usize::MAX,
+            window: 4,
+        };
+
+        let agreed = local.negotiate(&proposal);
+
+        assert_eq!(agreed.answer_depth, local.answer_depth);
+        assert_eq!(agreed.max_answer_nodes, 16);
+        assert_eq!(agreed.max_answer_bytes, local.max_answer_bytes);
+        assert_eq!(agreed.window, 4);
+
+        local.validate(&agreed).unwrap();
+        proposal.validate(&agreed).unwrap();
+
+        assert!(local.validate(&proposal).is_err());
+
+        let mut stalled = agreed.clone();
+        stalled.window = 0;
+        assert!(local.validate(&stalled).is_err());
+    }
+}
@@ -1,6 +1,4 @@
-use crate::database::sync::ANSWER_DEPTH;
-
-use std::ops::Add;
+use crate::database::sync::SyncSettings;
 
 #[derive(Debug)]
 pub(crate) enum Severity {
@@ -34,19 +32,17 @@ impl Severity {
             Severity::Malicious => true,
         }
     }
-}
-
-impl Add for Severity {
-    type Output = Self;
 
-    fn add(self, rho: Self) -> Self {
-        match (self, rho) {
+    /// Adds `offence` to `self`. Benign offences that exceed what a full
+    /// answer to a single label (under `settings`) can explain are malicious.
+    pub(crate) fn aggravate(self, offence: Severity, settings: &SyncSettings) -> Self {
+        match (self, offence) {
             (Severity::Benign(left), Severity::Benign(right)) => {
                 let recidivity = left + right;
-                if recidivity > (1 << (ANSWER_DEPTH + 1)) - 2 {
+                if recidivity > settings.subtree_nodes() - 1 {
                     Severity::Malicious
                 } else {
-                    Severity::Benign(left + right)
+                    Severity::Benign(recidivity)
                 }
             }
             _ => Severity::Malicious,
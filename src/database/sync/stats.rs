@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A snapshot of the progress of a sync, as seen by a [`TableReceiver`] or a
+/// [`TableSender`] (see [`TableReceiver::stats`] and [`TableSender::stats`]).
+///
+/// Counters start at zero when the receiver or sender is created (or, for a
+/// receiver, resumed by [`Database::resume_receive`]).
+///
+/// [`TableReceiver`]: crate::database::TableReceiver
+/// [`TableSender`]: crate::database::TableSender
+/// [`TableReceiver::stats`]: crate::database::TableReceiver::stats
+/// [`TableSender::stats`]: crate::database::TableSender::stats
+/// [`Database::resume_receive`]: crate::database::Database::resume_receive
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    /// Nodes downloaded from the sender (receiver only).
+    pub nodes_received: u64,
+    /// Nodes found in the local store, and not downloaded (receiver only).
+    pub nodes_reused: u64,
+    /// Nodes sent to the receiver (sender only).
+    pub nodes_sent: u64,
+    /// Serialized size of the messages received.
+    pub bytes_in: u64,
+    /// Serialized size of the messages sent. A receiver only accounts for the
+    /// questions sent by the drivers in [`sync`] (e.g., [`fetch`]).
+    ///
+    /// [`sync`]: crate::database::sync
+    /// [`fetch`]: crate::database::sync::fetch
+    pub bytes_out: u64,
+    /// Number of answers learned by the receiver, or given by the sender.
+    pub round_trips: u64,
+    /// Number of nodes the receiver still has to ask for (receiver only).
+    pub frontier: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct Counters {
+    pub nodes_received: AtomicU64,
+    pub nodes_reused: AtomicU64,
+    pub nodes_sent: AtomicU64,
+    pub bytes_in: AtomicU64,
+    pub bytes_out: AtomicU64,
+    pub round_trips: AtomicU64,
+}
+
+impl Counters {
+    pub fn add(counter: &AtomicU64, amount: u64) {
+        counter.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, frontier: usize) -> SyncStats {
+        SyncStats {
+            nodes_received: self.nodes_received.load(Ordering::Relaxed),
+            nodes_reused: self.nodes_reused.load(Ordering::Relaxed),
+            nodes_sent: self.nodes_sent.load(Ordering::Relaxed),
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            round_trips: self.round_trips.load(Ordering::Relaxed),
+            frontier,
+        }
+    }
+}
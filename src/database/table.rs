@@ -1,18 +1,21 @@
 use crate::{
     common::{data::Bytes, store::Field, tree::Path},
     database::{
-        errors::QueryError,
+        errors::{CertificateError, QueryError, TableError},
         store::{Cell, Handle, Label},
-        TableResponse, TableSender, TableTransaction,
+        Certificate, DiffIter, RangeProof, TableResponse, TableSender, TableTransaction,
     },
-    map::Map,
+    map::{errors::MapError, Map},
 };
-use doomstack::{here, ResultExt, Top};
+use doomstack::{here, Doom, ResultExt, Top};
 
 use oh_snap::Snap;
-use std::{borrow::Borrow, collections::HashMap, hash::Hash as StdHash};
+use std::{borrow::Borrow, collections::HashMap, hash::Hash as StdHash, ops::RangeBounds};
 
-use talk::crypto::primitives::{hash, hash::Hash};
+use talk::crypto::{
+    primitives::{hash, hash::Hash},
+    KeyChain,
+};
 
 // Documentation links
 #[allow(unused_imports)]
@@ -57,10 +60,59 @@ where
         self.0.commit()
     }
 
+    /// Signs the `Table`'s commitment, together with its name and a fresh
+    /// version, producing a [`Certificate`].
+    ///
+    /// Each call issues a version strictly greater than any version
+    /// previously issued for the same name by the `Database`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::Database;
+    /// use talk::crypto::KeyChain;
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let table = database.empty_table("test");
+    ///
+    /// let keychain = KeyChain::random();
+    /// let certificate = table.certify(&keychain).unwrap();
+    ///
+    /// certificate.verify(&keychain.keycard()).unwrap();
+    /// assert_eq!(certificate.commitment(), table.commit());
+    /// ```
+    pub fn certify(&self, keychain: &KeyChain) -> Result<Certificate, Top<CertificateError>> {
+        // `store` is held until the version is recorded, so that no two
+        // certificates for the same name share a version
+        let mut store = self.0.cell.take();
+
+        let certificate = store.next_version(&self.1).and_then(|version| {
+            let certificate = Certificate::new(keychain, self.get_name(), version, self.commit())?;
+
+            // A version is only consumed once its certificate is signed
+            store.commit_version(&self.1, version)?;
+            Ok(certificate)
+        });
+
+        self.0.cell.restore(store);
+        certificate
+    }
+
     pub(crate) fn get_name(&self) -> String {
         self.1.clone()
     }
 
+    /// Returns a `Table` named `name`, sharing the nodes and a copy of the
+    /// backup of `self` (see `Database::derive_table`).
+    pub(crate) fn fork(&self, name: &str) -> Result<Self, Top<TableError>> {
+        let handle = self.0.fork(&self.get_name(), name)?;
+        Ok(Table(handle, name.to_string()))
+    }
+
+    pub(crate) fn belongs_to(&self, cell: &Cell<Key, Value>) -> bool {
+        std::ptr::eq(self.0.cell.as_ref(), cell.as_ref())
+    }
+
     /// Executes a [`TableTransaction`] returning a [`TableResponse`]
     /// (see their respective documentations for more details).
     ///
@@ -126,6 +178,140 @@ where
         Ok(Map::raw(root))
     }
 
+    /// Returns a [`Map`] holding all the records of the table. Unlike
+    /// [`Table::export`], no branch is replaced by a `Stub`.
+    ///
+    /// See [`Database::table_from_map`] for the converse.
+    ///
+    /// [`Map`]: crate::map::Map
+    /// [`Database::table_from_map`]: crate::database::Database::table_from_map
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let table = database.empty_table("test");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 1).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// let map = table.to_map();
+    ///
+    /// assert!(map.is_complete());
+    /// assert_eq!(map.commit(), table.commit());
+    /// assert_eq!(map.get(&0).unwrap(), Some(&1));
+    /// ```
+    pub fn to_map(&self) -> Map<Key, Value>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        Map::raw(self.0.snapshot())
+    }
+
+    /// Returns the records of the table whose key lies in `range`, in
+    /// ascending order of key (unlike [`Table::diff_iter`], which follows
+    /// the hash order of the tree).
+    ///
+    /// Records are read from the ordered index of the table. The index is
+    /// built from the tree the first time it is needed (e.g., after the table
+    /// is restored, received or built from a [`Map`]), then maintained by
+    /// [`Table::execute`]. Clones of the table share the nodes of its index,
+    /// as they share those of its tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let table = database.empty_table("test");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// for key in 0..16 {
+    ///     transaction.set(key, key + 1).unwrap();
+    /// }
+    /// table.execute(transaction);
+    ///
+    /// let records = table.range(4..7);
+    /// assert_eq!(records, vec![(4, 5), (5, 6), (6, 7)]);
+    /// ```
+    pub fn range<R>(&self, range: R) -> Vec<(Key, Value)>
+    where
+        Key: Ord + Clone,
+        Value: Clone,
+        R: RangeBounds<Key>,
+    {
+        self.0
+            .read_index(|index| index.range(range.start_bound(), range.end_bound()))
+            .into_iter()
+            .map(|(key, value)| ((*key).clone(), (*value).clone()))
+            .collect()
+    }
+
+    /// Returns a cryptographic commitment to the ordered index of the table
+    /// (see [`Table::range`]), against which [`RangeProof`]s are verified.
+    ///
+    /// The commitment depends only on the records of the table: tables holding
+    /// the same records have the same `index_commit`.
+    ///
+    /// `index_commit` is not anchored to [`Table::commit`]: neither commitment
+    /// can be derived from the other, so a verifier that only trusts
+    /// [`Table::commit`] (e.g., through a [`Certificate`]) must obtain
+    /// `index_commit` from a trusted source as well. Moreover, the index is
+    /// not persisted: after a restart (or a receive), it is rebuilt by reading
+    /// every record of the table, the first time it is needed.
+    ///
+    /// [`Certificate`]: crate::database::Certificate
+    pub fn index_commit(&self) -> Hash
+    where
+        Key: Ord,
+    {
+        self.0.read_index(|index| index.commit()).into()
+    }
+
+    /// Returns a [`RangeProof`] that the records whose key lies in `range` are
+    /// exactly those returned by [`Table::range`].
+    ///
+    /// The proof is verified against [`Table::index_commit`], not against
+    /// [`Table::commit`] (see [`Table::index_commit`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let table = database.empty_table("test");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// for key in 0..16 {
+    ///     transaction.set(key, key + 1).unwrap();
+    /// }
+    /// table.execute(transaction);
+    ///
+    /// let proof = table.prove_range(4..7);
+    /// let records = proof.verify(table.index_commit(), 4..7).unwrap();
+    ///
+    /// assert_eq!(records, vec![(4, 5), (5, 6), (6, 7)]);
+    /// ```
+    pub fn prove_range<R>(&self, range: R) -> RangeProof<Key, Value>
+    where
+        Key: Ord,
+        R: RangeBounds<Key>,
+    {
+        self.0
+            .read_index(|index| index.prove(range.start_bound(), range.end_bound()))
+    }
+
+    /// Returns, for each key whose value differs between `lho` and `rho`, its
+    /// values in `lho` and `rho` (`None` if absent).
+    ///
+    /// `lho` and `rho` can belong to different [`Database`]s: subtrees are
+    /// compared by hash, so only the branches that differ are visited.
     pub fn diff(
         lho: &Table<Key, Value>,
         rho: &Table<Key, Value>,
@@ -134,7 +320,279 @@ where
         Key: Clone + Eq + StdHash,
         Value: Clone + Eq,
     {
-        Handle::diff(&lho.0, & rho.0)
+        Handle::diff(&lho.0, &rho.0)
+    }
+
+    /// Returns a lazy iterator over the keys whose value differs between `lho`
+    /// and `rho`, with their values in `lho` and `rho` (`None` if absent).
+    ///
+    /// Unlike [`Table::diff`], which collects the whole difference, records are
+    /// retrieved one at a time, in path order, as the iterator is advanced.
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, Table, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let lho = database.empty_table("test");
+    /// let rho = database.empty_table("test2");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 0).unwrap();
+    /// lho.execute(transaction);
+    ///
+    /// let mut iter = Table::diff_iter(&lho, &rho);
+    /// assert_eq!(iter.next(), Some((0, Some(0), None)));
+    /// assert_eq!(iter.next(), None);
+    /// ```
+    pub fn diff_iter(lho: &Table<Key, Value>, rho: &Table<Key, Value>) -> DiffIter<Key, Value>
+    where
+        Key: Clone,
+        Value: Clone,
+    {
+        DiffIter::new(lho.0.clone(), rho.0.clone())
+    }
+
+    /// Merges `ours` and `theirs`, two tables forked from `base`, into a new
+    /// table.
+    ///
+    /// Keys changed on only one side take that side's value (a `None` value
+    /// meaning that the key is absent). Keys changed on both sides to the same
+    /// value take that value; for all others, `resolver` is called with the
+    /// key and its values in `base`, `ours` and `theirs`, and returns the
+    /// merged value. Changes are found by [`Table::diff_iter`]: subtrees with
+    /// matching labels are not visited.
+    ///
+    /// The merged table shares the nodes of `ours`, to which the changes are
+    /// applied by [`Table::execute`]. `ours` is left unchanged. Like a table
+    /// received by a [`TableReceiver`], the merged table is not assigned to
+    /// any [`Database`], and is not backed up (see [`Database::merge_tables`]
+    /// to merge into a table assigned to a [`Database`]).
+    ///
+    /// # Errors
+    ///
+    /// If a `Key` cannot be hashed, [`HashError`] is returned.
+    ///
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    /// [`Database`]: crate::database::Database
+    /// [`Database::merge_tables`]: crate::database::Database::merge_tables
+    /// [`HashError`]: crate::database::errors::QueryError::HashError
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, Table, TableTransaction};
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let base = database.empty_table("test");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 0).unwrap();
+    /// transaction.set(1, 1).unwrap();
+    /// base.execute(transaction);
+    ///
+    /// let ours = (*base).clone();
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 10).unwrap();
+    /// transaction.set(1, 11).unwrap();
+    /// ours.execute(transaction);
+    ///
+    /// let theirs = (*base).clone();
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(1, 21).unwrap();
+    /// transaction.set(2, 22).unwrap();
+    /// theirs.execute(transaction);
+    ///
+    /// // Conflicts are resolved by taking the greatest value
+    /// let merged = Table::merge(&base, &ours, &theirs, |_, _, ours, theirs| {
+    ///     ours.max(theirs).cloned()
+    /// })
+    /// .unwrap();
+    ///
+    /// let mut expected = TableTransaction::default();
+    /// expected.set(0, 10).unwrap();
+    /// expected.set(1, 21).unwrap();
+    /// expected.set(2, 22).unwrap();
+    ///
+    /// let reference = database.empty_table("test2");
+    /// reference.execute(expected);
+    ///
+    /// assert_eq!(merged.commit(), reference.commit());
+    /// ```
+    pub fn merge<F>(
+        base: &Table<Key, Value>,
+        ours: &Table<Key, Value>,
+        theirs: &Table<Key, Value>,
+        resolver: F,
+    ) -> Result<Table<Key, Value>, Top<QueryError>>
+    where
+        Key: Clone + Eq,
+        Value: Clone + Eq,
+        F: FnMut(&Key, Option<&Value>, Option<&Value>, Option<&Value>) -> Option<Value>,
+    {
+        let transaction = Table::merge_transaction(base, ours, theirs, resolver)?;
+
+        let merged = Table(ours.0.clone(), String::new());
+        merged.execute(transaction);
+
+        Ok(merged)
+    }
+
+    /// Returns the transaction that, executed on `ours`, merges `theirs` into
+    /// it (see [`Table::merge`]).
+    pub(crate) fn merge_transaction<F>(
+        base: &Table<Key, Value>,
+        ours: &Table<Key, Value>,
+        theirs: &Table<Key, Value>,
+        mut resolver: F,
+    ) -> Result<TableTransaction<Key, Value>, Top<QueryError>>
+    where
+        Key: Clone + Eq,
+        Value: Clone + Eq,
+        F: FnMut(&Key, Option<&Value>, Option<&Value>, Option<&Value>) -> Option<Value>,
+    {
+        // Both sides are visited in path order (greater paths first), so that
+        // a key changed on both sides is met on both at the same time
+        let mut our_changes = Table::diff_iter(base, ours).map(with_path).peekable();
+        let mut transaction = TableTransaction::default();
+
+        for their_change in Table::diff_iter(base, theirs).map(with_path) {
+            let (path, key, base_value, their_value) = their_change?;
+            let mut our_value = None;
+
+            while let Some(our_change) = our_changes.peek() {
+                match our_change {
+                    Ok((our_path, ..)) if *our_path < path => break,
+                    Ok((our_path, ..)) if *our_path > path => {
+                        our_changes.next();
+                    }
+                    _ => {
+                        // Equal paths, or an error to return
+                        let (_, _, _, value) = our_changes.next().unwrap()?;
+                        our_value = Some(value);
+                        break;
+                    }
+                }
+            }
+
+            let value = match our_value {
+                None => their_value,
+                Some(our_value) if our_value == their_value => continue,
+                Some(our_value) => {
+                    let value = resolver(
+                        &key,
+                        base_value.as_ref(),
+                        our_value.as_ref(),
+                        their_value.as_ref(),
+                    );
+
+                    if value == our_value {
+                        continue;
+                    }
+
+                    value
+                }
+            };
+
+            match value {
+                Some(value) => transaction.set(key, value)?,
+                None => transaction.remove(key)?,
+            }
+        }
+
+        Ok(transaction)
+    }
+
+    /// Returns, for each key whose value differs between the table and `map`,
+    /// its values in the table and in `map` (`None` if absent).
+    ///
+    /// Fails with [`MapError::BranchUnknown`] if `map` is missing (i.e., only
+    /// stores the hash of) a branch that differs from the table.
+    ///
+    /// [`MapError::BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// ```
+    /// use tenaciouszebra::{
+    ///     database::{Database, TableTransaction},
+    ///     map::Map,
+    /// };
+    ///
+    /// let database: Database<u32, u32> = Database::new("test");
+    /// let table = database.empty_table("test");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(0, 0).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// let mut map = Map::new();
+    /// map.insert(0, 1).unwrap();
+    ///
+    /// let diff = table.diff_map(&map).unwrap();
+    /// assert_eq!(diff.get(&0), Some(&(Some(0), Some(1))));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn diff_map(
+        &self,
+        map: &Map<Key, Value>,
+    ) -> Result<HashMap<Key, (Option<Value>, Option<Value>)>, Top<MapError>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        match self.0.diff_map(map.root()) {
+            Some(diff) => Ok(diff),
+            None => MapError::BranchUnknown.fail().spot(here!()),
+        }
+    }
+
+    /// Returns the records of the table indexed under `key` by the index named
+    /// `index` (see [`Database::register_index`]), in no particular order.
+    ///
+    /// Index entries follow the records last executed under the table's name:
+    /// each candidate record is read from the table, and returned only if it
+    /// is still indexed under `key`. Hence, for a clone of a table (e.g., a
+    /// snapshot), only records that have not changed since are returned.
+    ///
+    /// # Errors
+    ///
+    /// If no index named `index` is registered for the table (in particular,
+    /// if it was not registered again since the `Database` was restored),
+    /// [`IndexMissing`] is returned. If an entry of the index cannot be
+    /// decoded, [`IndexCorrupt`] is returned.
+    ///
+    /// [`Database::register_index`]: crate::database::Database::register_index
+    /// [`IndexMissing`]: crate::database::errors::QueryError::IndexMissing
+    /// [`IndexCorrupt`]: crate::database::errors::QueryError::IndexCorrupt
+    pub fn lookup_by<IndexKey>(
+        &self,
+        index: &str,
+        key: &IndexKey,
+    ) -> Result<Vec<(Key, Value)>, Top<QueryError>>
+    where
+        Key: Clone,
+        Value: Clone,
+        IndexKey: Field,
+    {
+        let index_key = bincode::serialize(key).unwrap();
+
+        let store = self.0.cell.take();
+        let lookup = store.lookup_index(&self.1, index, &index_key);
+        self.0.cell.restore(store);
+
+        let (extractor, keys) = lookup?;
+
+        let map = self.export(keys.iter())?;
+
+        // `map` holds the branches of all `keys`: `get` cannot fail
+        let records = keys
+            .into_iter()
+            .filter_map(|key| {
+                let value = map.get(&key).unwrap()?.clone();
+                Some((key, value))
+            })
+            .filter(|(key, value)| extractor(key, value) == index_key)
+            .collect();
+
+        Ok(records)
     }
 
     /// Transforms the table into a [`TableSender`], preparing it for sending to
@@ -167,6 +625,18 @@ where
     }
 }
 
+// Prepends to a change yielded by a `DiffIter` the path of its key
+#[allow(clippy::type_complexity)]
+fn with_path<Key, Value>(
+    (key, lho_value, rho_value): (Key, Option<Value>, Option<Value>),
+) -> Result<(Path, Key, Option<Value>, Option<Value>), Top<QueryError>>
+where
+    Key: Field,
+{
+    let digest = hash::hash(&key).pot(QueryError::HashError, here!())?;
+    Ok((Path::from(Bytes::from(digest)), key, lho_value, rho_value))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -174,6 +644,8 @@ mod tests {
 
     use super::*;
 
+    use crate::database::errors::RangeProofError;
+
     use rand::seq::IteratorRandom;
 
     use std::{fmt::Debug, hash::Hash, collections::HashMap};
@@ -699,4 +1171,381 @@ mod tests {
             assert_eq!(Table::diff(&lho, &rho), diff_reference);
         }
     }
+
+    #[test]
+    fn diff_across_stores() {
+        let paths = [
+            format!("test/{}", rand::random::<u64>()),
+            format!("test/{}", rand::random::<u64>()),
+        ];
+
+        {
+            let alice: Database<u32, u32> = Database::new(&paths[0]);
+            let bob: Database<u32, u32> = Database::new(&paths[1]);
+
+            let lho = alice.empty_table("test");
+            let rho = bob.empty_table("test");
+
+            let mut lho_transaction = TableTransaction::default();
+            let mut rho_transaction = TableTransaction::default();
+
+            for key in 0..1024 {
+                lho_transaction.set(key, key).unwrap();
+            }
+
+            for key in 512..1536 {
+                let value = if key < 768 { key + 1 } else { key };
+                rho_transaction.set(key, value).unwrap();
+            }
+
+            lho.execute(lho_transaction);
+            rho.execute(rho_transaction);
+
+            let diff = Table::diff(&lho, &rho);
+            assert_eq!(diff.len(), 1280);
+
+            for key in 0..1536 {
+                if key < 512 {
+                    assert_eq!(diff[&key], (Some(key), None));
+                } else if key < 768 {
+                    assert_eq!(diff[&key], (Some(key), Some(key + 1)));
+                } else if key < 1024 {
+                    assert_eq!(diff.get(&key), None);
+                } else {
+                    assert_eq!(diff[&key], (None, Some(key)));
+                }
+            }
+
+            assert_eq!(Table::diff(&rho, &lho).len(), 1280);
+            assert!(Table::diff(&lho, &lho).is_empty());
+        }
+
+        for path in paths {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn diff_map() {
+        let database: Database<u32, u32> = Database::new("test");
+        let table = database.empty_table("test");
+
+        let mut transaction = TableTransaction::default();
+        let mut map = Map::new();
+
+        for key in 0..1024 {
+            transaction.set(key, key).unwrap();
+        }
+
+        for key in 512..1536 {
+            map.insert(key, key).unwrap();
+        }
+
+        table.execute(transaction);
+
+        let diff = table.diff_map(&map).unwrap();
+        assert_eq!(diff.len(), 1024);
+
+        for key in 0..1536 {
+            if key < 512 {
+                assert_eq!(diff[&key], (Some(key), None));
+            } else if key < 1024 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (None, Some(key)));
+            }
+        }
+
+        for key in 0..1536 {
+            if key < 1024 {
+                map.insert(key, key).unwrap();
+            } else {
+                map.remove(&key).unwrap();
+            }
+        }
+
+        assert!(table.diff_map(&map).unwrap().is_empty());
+        assert!(table.diff_map(&Map::root_stub(map.commit())).unwrap().is_empty());
+
+        // Only the branch of key 0 differs, and it is known
+        map.insert(0, 1).unwrap();
+        let export = map.export([&0]).unwrap();
+
+        let diff = table.diff_map(&export).unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[&0], (Some(0), Some(1)));
+
+        // The differing branches are stubs
+        let stub = Map::root_stub(map.commit());
+        assert!(table.diff_map(&stub).is_err());
+    }
+
+    #[test]
+    fn to_map() {
+        let database: Database<u32, u32> = Database::new("test");
+        let table = database.empty_table("test");
+
+        let mut transaction = TableTransaction::default();
+        for key in 0..1024 {
+            transaction.set(key, key + 1).unwrap();
+        }
+        table.execute(transaction);
+
+        let map = table.to_map();
+
+        assert!(map.is_complete());
+        assert_eq!(map.commit(), table.commit());
+        assert_eq!(
+            map.iter().map(|(key, value)| (*key, *value)).collect::<HashMap<_, _>>(),
+            table.collect_records()
+        );
+
+        // The snapshot is not affected by later changes to the table
+        let mut transaction = TableTransaction::default();
+        transaction.remove(0).unwrap();
+        table.execute(transaction);
+
+        assert_eq!(map.get(&0).unwrap(), Some(&1));
+        assert!(database.empty_table("test2").to_map().is_empty());
+    }
+
+    #[test]
+    fn range() {
+        let database: Database<u32, u32> = Database::new("test");
+        let table = database.empty_table("test");
+
+        assert!(table.range(..).is_empty());
+
+        let mut transaction = TableTransaction::default();
+        for key in 0..512 {
+            transaction.set(key, key).unwrap();
+        }
+        table.execute(transaction);
+
+        assert_eq!(table.range(..), (0..512).map(|key| (key, key)).collect::<Vec<_>>());
+
+        let snapshot = (*table).clone();
+
+        let mut transaction = TableTransaction::default();
+        for key in 0..256 {
+            transaction.remove(key).unwrap();
+        }
+        for key in 256..768 {
+            transaction.set(key, key + 1).unwrap();
+        }
+        table.execute(transaction);
+
+        assert_eq!(
+            table.range(128..=300),
+            (256..=300).map(|key| (key, key + 1)).collect::<Vec<_>>()
+        );
+
+        assert_eq!(table.range(700..).len(), 68);
+        assert!(table.range(768..).is_empty());
+
+        // Clones keep the index of the table at the time of cloning
+        assert_eq!(
+            snapshot.range(128..300),
+            (128..300).map(|key| (key, key)).collect::<Vec<_>>()
+        );
+
+        let mut transaction = TableTransaction::default();
+        transaction.set(0, 1).unwrap();
+        snapshot.execute(transaction);
+
+        assert_eq!(snapshot.range(..1), vec![(0, 1)]);
+        assert!(table.range(..1).is_empty());
+    }
+
+    #[test]
+    fn index_commit() {
+        let database: Database<u32, u32> = Database::new("test");
+
+        let forward = database.table_with_records((0..256).map(|key| (key, key)));
+        let backward = database.table_with_records((0..256).rev().map(|key| (key, key)));
+
+        // The shape of the index does not depend on the order of execution
+        assert_eq!(forward.index_commit(), backward.index_commit());
+
+        let mut transaction = TableTransaction::default();
+        transaction.set(300, 300).unwrap();
+        transaction.remove(7).unwrap();
+        forward.execute(transaction);
+
+        assert_ne!(forward.index_commit(), backward.index_commit());
+
+        let mut transaction = TableTransaction::default();
+        transaction.remove(7).unwrap();
+        transaction.set(300, 300).unwrap();
+        backward.execute(transaction);
+
+        assert_eq!(forward.index_commit(), backward.index_commit());
+    }
+
+    #[test]
+    fn prove_range() {
+        let database: Database<u32, u32> = Database::new("test");
+        let table = database.table_with_records((0..1024).map(|key| (key * 2, key)));
+
+        let commit = table.index_commit();
+
+        let proof = table.prove_range(100..=200);
+        assert_eq!(
+            proof.verify(commit, 100..=200).unwrap(),
+            (50..=100).map(|key| (key * 2, key)).collect::<Vec<_>>()
+        );
+
+        let proof = table.prove_range(..31);
+        assert_eq!(proof.verify(commit, ..31).unwrap().len(), 16);
+
+        let proof = table.prove_range(..);
+        assert_eq!(proof.verify(commit, ..).unwrap().len(), 1024);
+
+        // A proof does not cover a wider range, nor another index
+        let proof = table.prove_range(1001..1002);
+        assert!(proof.verify(commit, 1001..1002).unwrap().is_empty());
+
+        assert!(
+            *proof.verify(commit, 900..1002).err().unwrap().top()
+                == RangeProofError::Incomplete
+        );
+
+        let other = database.table_with_records((0..1024).map(|key| (key * 2, key + 1)));
+        let proof = other.prove_range(100..=200);
+
+        assert!(
+            *proof.verify(commit, 100..=200).err().unwrap().top() == RangeProofError::RootMismatch
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let database: Database<u32, u32> = Database::new("test");
+        let base = database.empty_table("test");
+
+        let mut transaction = TableTransaction::default();
+        for key in 0..1024 {
+            transaction.set(key, key).unwrap();
+        }
+        base.execute(transaction);
+
+        // Ours: changes 0..256, removes 256..384, adds 1024..1152
+        let ours = (*base).clone();
+        let mut transaction = TableTransaction::default();
+        for key in 0..256 {
+            transaction.set(key, key + 1).unwrap();
+        }
+        for key in 256..384 {
+            transaction.remove(key).unwrap();
+        }
+        for key in 1024..1152 {
+            transaction.set(key, key).unwrap();
+        }
+        ours.execute(transaction);
+
+        // Theirs: changes 128..512 (128..256 to the same values as ours),
+        // adds 1088..1216 (1088..1152 with different values from ours)
+        let theirs = (*base).clone();
+        let mut transaction = TableTransaction::default();
+        for key in 128..512 {
+            let value = if key < 256 { key + 1 } else { key + 2 };
+            transaction.set(key, value).unwrap();
+        }
+        for key in 1088..1216 {
+            transaction.set(key, key + 2).unwrap();
+        }
+        theirs.execute(transaction);
+
+        let mut conflicts = HashMap::new();
+
+        let merged = Table::merge(&base, &ours, &theirs, |key, base, ours, theirs| {
+            conflicts.insert(*key, (base.cloned(), ours.cloned(), theirs.cloned()));
+            ours.cloned()
+        })
+        .unwrap();
+
+        let mut reference = HashMap::new();
+
+        for key in 256..384 {
+            assert_eq!(conflicts[&key], (Some(key), None, Some(key + 2)));
+        }
+
+        for key in 1088..1152 {
+            assert_eq!(conflicts[&key], (None, Some(key), Some(key + 2)));
+        }
+
+        assert_eq!(conflicts.len(), 192);
+
+        for key in 0..256 {
+            reference.insert(key, key + 1);
+        }
+        for key in 384..512 {
+            reference.insert(key, key + 2);
+        }
+        for key in 512..1024 {
+            reference.insert(key, key);
+        }
+        for key in 1024..1152 {
+            reference.insert(key, key);
+        }
+        for key in 1152..1216 {
+            reference.insert(key, key + 2);
+        }
+
+        merged.assert_records(reference);
+
+        // `ours` is left unchanged
+        ours.assert_records((0..1152).filter(|key| !(256..384).contains(key)).map(|key| {
+            (key, if key < 256 { key + 1 } else { key })
+        }));
+    }
+
+    #[test]
+    fn merge_resolver() {
+        let database: Database<u32, u32> = Database::new("test");
+        let base = database.empty_table("test");
+
+        let mut transaction = TableTransaction::default();
+        for key in 0..128 {
+            transaction.set(key, key).unwrap();
+        }
+        base.execute(transaction);
+
+        let ours = (*base).clone();
+        let mut transaction = TableTransaction::default();
+        for key in 0..64 {
+            transaction.set(key, key + 1).unwrap();
+        }
+        ours.execute(transaction);
+
+        let theirs = (*base).clone();
+        let mut transaction = TableTransaction::default();
+        for key in 0..64 {
+            transaction.remove(key).unwrap();
+        }
+        theirs.execute(transaction);
+
+        // Even keys are removed, odd keys are summed
+        let merged = Table::merge(&base, &ours, &theirs, |key, base, ours, theirs| {
+            assert_eq!(base, Some(key));
+            assert_eq!(theirs, None);
+
+            if key % 2 == 0 {
+                None
+            } else {
+                Some(base.unwrap() + ours.unwrap())
+            }
+        })
+        .unwrap();
+
+        let reference = (0..128)
+            .filter(|key| *key >= 64 || key % 2 == 1)
+            .map(|key| (key, if key < 64 { 2 * key + 1 } else { key }));
+
+        merged.assert_records(reference);
+
+        // Merging is a no-op if `theirs` did not change
+        let merged = Table::merge(&base, &ours, &base, |_, _, _, _| unreachable!()).unwrap();
+        assert_eq!(merged.commit(), ours.commit());
+    }
 }
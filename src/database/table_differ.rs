@@ -0,0 +1,197 @@
+use crate::{
+    common::store::Field,
+    database::{errors::SyncError, Question, Table, TableAnswer, TableReceiver},
+};
+
+use doomstack::Top;
+
+use std::{collections::HashMap, hash::Hash as StdHash};
+
+/// Computes the difference between a local [`Table`] and the [`Table`] of
+/// a remote [`Database`], as [`Table::diff`] would if both were local.
+///
+/// A `TableDiffer` (see [`Database::diff_remote`]) is fed the [`TableAnswer`]s
+/// of the remote [`TableSender`], and returns the [`Question`] to answer next.
+/// Subtrees already held by the local [`Database`] (in particular, those shared
+/// with the local [`Table`]) are never asked for, nor is anything below them:
+/// only the subtrees whose labels differ are transferred, and they are released
+/// as soon as the difference is computed.
+///
+/// The same differ reconciles [`Collection`]s (see [`CollectionReconciler`]).
+///
+/// [`Database`]: crate::database::Database
+/// [`Database::diff_remote`]: crate::database::Database::diff_remote
+/// [`Table::diff`]: crate::database::Table::diff
+/// [`TableSender`]: crate::database::TableSender
+/// [`Collection`]: crate::database::Collection
+/// [`CollectionReconciler`]: crate::database::CollectionReconciler
+pub struct TableDiffer<Key: Field, Value: Field> {
+    local: Table<Key, Value>,
+    pub(crate) receiver: TableReceiver<Key, Value>,
+}
+
+impl<Key, Value> TableDiffer<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub(crate) fn new(local: Table<Key, Value>, receiver: TableReceiver<Key, Value>) -> Self {
+        TableDiffer { local, receiver }
+    }
+
+    /// Learns the remote [`TableSender`]'s [`TableAnswer`] (its
+    /// [`TableSender::hello`] first, then its answers to the returned
+    /// [`Question`]s). Returns the [`Question`] to send next, or `None` once
+    /// the difference can be computed (see [`finish`]).
+    ///
+    /// Malformed answers are accounted for as by [`TableReceiver::learn`].
+    /// On error, the differ should be dropped.
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    /// [`TableSender::hello`]: crate::database::TableSender::hello
+    /// [`TableReceiver::learn`]: crate::database::TableReceiver::learn
+    /// [`finish`]: TableDiffer::finish
+    pub fn learn(
+        &mut self,
+        answer: TableAnswer<Key, Value>,
+    ) -> Result<Option<Question>, Top<SyncError>> {
+        self.receiver.absorb(answer)?;
+
+        if self.receiver.is_complete() {
+            Ok(None)
+        } else {
+            Ok(Some(self.receiver.ask()))
+        }
+    }
+
+    /// Returns, for each key whose value differs, its value in the local and
+    /// in the remote [`Table`] (`None` if absent).
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`learn`] has not returned `None` yet.
+    ///
+    /// # Errors
+    ///
+    /// Fails as [`TableReceiver::finish`] does.
+    ///
+    /// [`learn`]: TableDiffer::learn
+    /// [`TableReceiver::finish`]: crate::database::TableReceiver::finish
+    #[allow(clippy::type_complexity)]
+    pub fn finish(self) -> Result<HashMap<Key, (Option<Value>, Option<Value>)>, Top<SyncError>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        let (diff, _) = self.finish_remote()?;
+        Ok(diff)
+    }
+
+    // Like `finish`, also returning the remote table
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn finish_remote(
+        self,
+    ) -> Result<(HashMap<Key, (Option<Value>, Option<Value>)>, Table<Key, Value>), Top<SyncError>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        if !self.receiver.is_complete() {
+            panic!("called `TableDiffer::finish` on an incomplete diff");
+        }
+
+        // Remote nodes are flushed in the local store, so that the diff skips
+        // every subtree shared with `local`
+        let remote = self.receiver.finish()?;
+        let diff = Table::diff(&self.local, &remote);
+
+        Ok((diff, remote))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::database::{Database, TableSender, TableTransaction};
+
+    type Diff = HashMap<u32, (Option<u32>, Option<u32>)>;
+
+    fn diff_with(
+        mut differ: TableDiffer<u32, u32>,
+        sender: &TableSender<u32, u32>,
+    ) -> (Diff, usize) {
+        let mut answer = sender.hello();
+        let mut asked = 0;
+
+        while let Some(question) = differ.learn(answer).unwrap() {
+            asked += question.len();
+            answer = sender.answer(&question).unwrap();
+        }
+
+        (differ.finish().unwrap(), asked)
+    }
+
+    #[test]
+    fn remote() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new(&path);
+
+        let remote = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let local = bob.table_with_records((0..1024).map(|i| (i, i)));
+
+        let mut transaction = TableTransaction::default();
+        transaction.set(3, 30).unwrap();
+        transaction.remove(5).unwrap();
+        transaction.set(2048, 2048).unwrap();
+        local.execute(transaction);
+
+        let mut differ = bob.diff_remote(&local);
+        differ.receiver.settings.window = usize::MAX;
+
+        let (diff, asked) = diff_with(differ, &remote.send());
+
+        let expected: Diff = [
+            (3, (Some(30), Some(3))),
+            (5, (None, Some(5))),
+            (2048, (Some(2048), None)),
+        ]
+        .iter()
+        .copied()
+        .collect();
+
+        assert_eq!(diff, expected);
+
+        // Only the paths to the differences are explored
+        assert!(asked < 128);
+
+        // The remote nodes are released once the difference is computed
+        bob.check_correctness([&*local], []);
+
+        drop((local, bob));
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn identical() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new(&path);
+
+        let remote = alice.table_with_records((0..256).map(|i| (i, i)));
+        let local = bob.table_with_records((0..256).map(|i| (i, i)));
+
+        let (diff, asked) = diff_with(bob.diff_remote(&local), &remote.send());
+
+        assert!(diff.is_empty());
+        assert_eq!(asked, 0);
+
+        bob.check_correctness([&*local], []);
+
+        drop((local, bob));
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
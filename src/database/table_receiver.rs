@@ -1,36 +1,45 @@
 use crate::{
     common::{data::Bytes, store::Field, tree::Prefix},
     database::{
-        errors::SyncError,
+        errors::{CertificateError, SyncError},
         interact::drop,
-        store::{Cell, Label, MapId, Node, Store},
-        sync::{locate, Severity},
-        Question, Table, TableAnswer, TableStatus,
+        store::{Cell, Label, MapId, Node, Store, RECEIVES},
+        sync::{locate, Counters, Severity, SyncSettings, SyncStats},
+        Certificate, Question, Table, TableAnswer, TableStatus,
     },
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
+use rocksdb::{WriteBatchWithTransaction, DB};
+
+use serde::{Deserialize, Serialize};
+
+use talk::crypto::KeyCard;
+
 use std::collections::{
     hash_map::Entry::{Occupied, Vacant},
     HashMap, HashSet,
 };
 
-const DEFAULT_WINDOW: usize = 128;
+// Key under which the last checkpoint id is stored in `RECEIVES`
+const LAST_CHECKPOINT: &[u8] = b"last";
 
 
 pub struct TableReceiver<Key: Field, Value: Field> {
     cell: Cell<Key, Value>,
     root: Option<Label>,
+    expected: Option<Bytes>,
     name: String,
-    held: HashSet<Label>,
+    held: HashMap<Label, Prefix>,
     frontier: HashMap<Bytes, Context>,
     acquired: HashMap<Bytes, Node<Key, Value>>,
-    pub settings: Settings,
-}
-
-pub struct Settings {
-    pub window: usize,
+    claims: HashMap<Bytes, usize>,
+    peers: HashMap<usize, Severity>,
+    checkpoint: Option<u64>,
+    checkpointed: HashSet<Bytes>,
+    counters: Counters,
+    pub settings: SyncSettings,
 }
 
 struct Context {
@@ -38,6 +47,22 @@ struct Context {
     remote_label: Label,
 }
 
+#[derive(Serialize, Deserialize)]
+enum Slot {
+    State,
+    Node(u64),
+}
+
+#[derive(Serialize, Deserialize)]
+struct State {
+    name: String,
+    root: Option<Label>,
+    expected: Option<Bytes>,
+    frontier: Vec<(Prefix, Label)>,
+    held: Vec<(Label, Prefix)>,
+    nodes: u64,
+}
+
 impl<Key, Value> TableReceiver<Key, Value>
 where
     Key: Field,
@@ -47,28 +72,318 @@ where
         TableReceiver {
             cell,
             root: None,
+            expected: None,
             name: String::new(),
-            held: HashSet::new(),
+            held: HashMap::new(),
             frontier: HashMap::new(),
             acquired: HashMap::new(),
-            settings: Settings {
-                window: DEFAULT_WINDOW,
-            },
+            claims: HashMap::new(),
+            peers: HashMap::new(),
+            checkpoint: None,
+            checkpointed: HashSet::new(),
+            counters: Counters::default(),
+            settings: SyncSettings::default(),
+        }
+    }
+
+    pub(crate) fn resume(cell: Cell<Key, Value>, id: u64) -> Result<Option<Self>, Top<SyncError>> {
+        let mut store = cell.take();
+
+        let (state, nodes) = match Self::read(&store.db, id) {
+            Ok(Some(checkpoint)) => checkpoint,
+            Ok(None) => {
+                cell.restore(store);
+                return Ok(None);
+            }
+            Err(e) => {
+                cell.restore(store);
+                return Err(e);
+            }
+        };
+
+        let mut receiver = TableReceiver::new(cell.clone());
+
+        receiver.name = state.name;
+        receiver.root = state.root;
+        receiver.expected = state.expected;
+        receiver.checkpoint = Some(id);
+
+        for node in nodes {
+            let hash = node.hash();
+
+            receiver.acquired.insert(hash, node);
+            receiver.checkpointed.insert(hash);
+        }
+
+        for (location, label) in state.frontier {
+            receiver.sight(&label, location);
+        }
+
+        for (label, location) in state.held {
+            let present = match store.entry(label) {
+                Occupied(..) => true,
+                Vacant(..) => false,
+            };
+
+            if present {
+                store.incref(label);
+                receiver.held.insert(label, location);
+            } else {
+                // `label` was only held by something that did not survive
+                // the restart (e.g., a table that was never stored): ask again
+                receiver.sight(&label, location);
+            }
+        }
+
+        cell.restore(store);
+        Ok(Some(receiver))
+    }
+
+    pub(crate) fn checkpoints(cell: &Cell<Key, Value>) -> Vec<u64> {
+        let store = cell.take();
+        let family = store.db.cf_handle(RECEIVES).unwrap();
+
+        let mut ids = Vec::new();
+        let mut iter = store.db.raw_iterator_cf(&family);
+
+        iter.seek_to_first();
+        while iter.valid() {
+            if let Ok((id, Slot::State)) = bincode::deserialize::<(u64, Slot)>(iter.key().unwrap()) {
+                ids.push(id);
+            }
+
+            iter.next();
+        }
+
+        cell.restore(store);
+        ids
+    }
+
+    pub(crate) fn discard(cell: &Cell<Key, Value>, id: u64) -> Result<bool, Top<SyncError>> {
+        let store = cell.take();
+        let family = store.db.cf_handle(RECEIVES).unwrap();
+
+        // All keys of the checkpoint start with `id` (see `key`), including
+        // the nodes of a checkpoint whose `State` cannot be decoded
+        let prefix = bincode::serialize(&id).unwrap();
+
+        let mut batch = WriteBatchWithTransaction::<false>::default();
+        let mut iter = store.db.raw_iterator_cf(&family);
+
+        iter.seek(&prefix);
+        while iter.valid() && iter.key().unwrap().starts_with(&prefix) {
+            batch.delete_cf(&family, iter.key().unwrap());
+            iter.next();
+        }
+
+        let discarded = !batch.is_empty();
+        let result = store.db.write(batch);
+
+        cell.restore(store);
+
+        result.pot(SyncError::StorageError, here!())?;
+        Ok(discarded)
+    }
+
+    /// Saves the state of the receive to the [`Database`]'s storage and
+    /// returns its id, from which the receive can be resumed (e.g., after a
+    /// restart) by [`Database::resume_receive`].
+    ///
+    /// Checkpointing the same receiver again updates the same checkpoint,
+    /// only writing the nodes acquired since the last call. The checkpoint
+    /// is removed once the receive completes, or by
+    /// [`Database::discard_receive`] if the receive is abandoned.
+    ///
+    /// Fails with [`SyncError::StorageError`] if the checkpoint cannot be
+    /// written, in which case the receive can carry on (and be checkpointed
+    /// again) as if the call never happened.
+    ///
+    /// [`Database`]: crate::database::Database
+    /// [`Database::resume_receive`]: crate::database::Database::resume_receive
+    /// [`Database::discard_receive`]: crate::database::Database::discard_receive
+    pub fn checkpoint(&mut self) -> Result<u64, Top<SyncError>> {
+        let store = self.cell.take();
+        let db = store.db.clone();
+
+        let id = match self.checkpoint {
+            Some(id) => Ok(id),
+            // The store is taken: no other receiver can be assigned the same id
+            None => Self::assign(&db),
+        };
+
+        self.cell.restore(store);
+
+        let id = id?;
+        self.checkpoint = Some(id);
+
+        let family = db.cf_handle(RECEIVES).unwrap();
+
+        let mut batch = WriteBatchWithTransaction::<false>::default();
+        let mut written = Vec::new();
+
+        for (hash, node) in self.acquired.iter() {
+            if !self.checkpointed.contains(hash) {
+                let index = (self.checkpointed.len() + written.len()) as u64;
+                let raw = bincode::serialize(node).pot(SyncError::StorageError, here!())?;
+
+                batch.put_cf(&family, Self::key(id, Slot::Node(index)), raw);
+                written.push(*hash);
+            }
+        }
+
+        let state = State {
+            name: self.name.clone(),
+            root: self.root,
+            expected: self.expected,
+            frontier: self
+                .frontier
+                .values()
+                .map(|context| (context.location, context.remote_label))
+                .collect(),
+            held: self
+                .held
+                .iter()
+                .map(|(label, location)| (*label, *location))
+                .collect(),
+            nodes: (self.checkpointed.len() + written.len()) as u64,
+        };
+
+        batch.put_cf(
+            &family,
+            Self::key(id, Slot::State),
+            bincode::serialize(&state).unwrap(),
+        );
+
+        db.write(batch).pot(SyncError::StorageError, here!())?;
+
+        // Only nodes that reached the storage are skipped by the next checkpoint
+        self.checkpointed.extend(written);
+
+        Ok(id)
+    }
+
+    // Reserves a new checkpoint id
+    fn assign(db: &DB) -> Result<u64, Top<SyncError>> {
+        let family = db.cf_handle(RECEIVES).unwrap();
+
+        let id = match db
+            .get_cf(&family, LAST_CHECKPOINT)
+            .pot(SyncError::StorageError, here!())?
+        {
+            Some(raw) => {
+                bincode::deserialize::<u64>(&raw).pot(SyncError::CorruptCheckpoint, here!())? + 1
+            }
+            None => 0,
+        };
+
+        db.put_cf(&family, LAST_CHECKPOINT, bincode::serialize(&id).unwrap())
+            .pot(SyncError::StorageError, here!())?;
+
+        Ok(id)
+    }
+
+    /// Pins the root of the table to receive to the commitment certified by
+    /// `certificate`, after checking that `certificate` was produced by the
+    /// owner of `keycard`.
+    ///
+    /// Once pinned, [`learn`] fails with [`SyncError::RootMismatch`] if the
+    /// sender serves a table whose commitment differs.
+    ///
+    /// [`learn`]: TableReceiver::learn
+    /// [`SyncError::RootMismatch`]: crate::database::errors::SyncError::RootMismatch
+    pub fn expect_certified(
+        &mut self,
+        certificate: &Certificate,
+        keycard: &KeyCard,
+    ) -> Result<(), Top<CertificateError>> {
+        certificate.verify(keycard)?;
+        self.expect(certificate.commitment().into());
+        Ok(())
+    }
+
+    pub(crate) fn expect(&mut self, commit: Bytes) {
+        self.expected = Some(commit);
+    }
+
+    /// Returns the [`Question`] to send to the [`TableSender`] next.
+    ///
+    /// For a receiver created by [`Database::receive_expecting`], this allows
+    /// to start receiving without waiting for [`TableSender::hello`].
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    /// [`TableSender::hello`]: crate::database::TableSender::hello
+    /// [`Database::receive_expecting`]: crate::database::Database::receive_expecting
+    pub fn ask(&self) -> Question {
+        match self.awaited_root() {
+            Some(commit) => Question::root(commit),
+            None => Question::labels(
+                self.frontier
+                    .values()
+                    .map(|context| context.remote_label)
+                    .take(self.settings.window)
+                    .collect(),
+            ),
         }
     }
 
+    /// Returns a [`SyncStats`] snapshot of the receive so far.
+    ///
+    /// Since [`learn`] consumes the receiver on completion, the last snapshot
+    /// available through [`learn`] precedes the final answer. Receivers driven
+    /// by [`learn_peer`] remain available until [`finish`].
+    ///
+    /// [`SyncStats`]: crate::database::sync::SyncStats
+    /// [`learn`]: TableReceiver::learn
+    /// [`learn_peer`]: TableReceiver::learn_peer
+    /// [`finish`]: TableReceiver::finish
+    pub fn stats(&self) -> SyncStats {
+        let awaited = self.awaited_root().is_some() as usize;
+        self.counters.snapshot(self.frontier.len() + awaited)
+    }
+
     /// 
     pub fn learn(
         mut self,
         answer: TableAnswer<Key, Value>,
     ) -> Result<TableStatus<Key, Value>, Top<SyncError>> {
+        self.absorb(answer)?;
+
+        if self.is_complete() {
+            // Receive complete, flush if necessary
+            Ok(TableStatus::Complete(self.finish()?))
+        } else {
+            // Receive incomplete, carry on with new `Question`
+            let question = self.ask();
+            Ok(TableStatus::Incomplete(self, question))
+        }
+    }
+
+    // Feeds `answer` to the receive, leaving the receiver usable on success
+    pub(crate) fn absorb(&mut self, answer: TableAnswer<Key, Value>) -> Result<(), Top<SyncError>> {
+        if let (None, Some(expected)) = (self.root, self.expected) {
+            // The first node of the first answer is the root of the table served
+            // (no node at all means the table served is empty)
+            let root = answer.0.first().map_or(Label::Empty.hash(), Node::hash);
+
+            if root != expected {
+                return SyncError::RootMismatch.fail().spot(here!());
+            }
+        }
+
+        let bytes = self.settings.check(&answer)?;
+
+        Counters::add(&self.counters.bytes_in, bytes);
+        Counters::add(&self.counters.round_trips, 1);
+
         let mut store = self.cell.take();
         let mut severity = Severity::ok();
 
+        let mut redundant = HashSet::new();
+
         for node in answer.0 {
-            severity = match self.update(&mut store, node) {
+            severity = match self.update(&mut store, &mut redundant, node) {
                 Ok(()) => Severity::ok(),
-                Err(offence) => severity + offence,
+                Err(offence) => severity.aggravate(offence, &self.settings),
             };
 
             if severity.is_malicious() {
@@ -76,47 +391,228 @@ where
             }
         }
 
+        self.cell.restore(store);
+
         if severity.is_benign() {
-            if self.frontier.is_empty() {
-                // Receive complete, flush if necessary
-                match self.root {
-                    Some(root) => {
-                        // At least one node was received: flush
-                        self.flush(&mut store, root);
-                        self.cell.restore(store);
-
-                        Ok(TableStatus::Complete(Table::new(self.cell.clone(), root, self.name.clone())))
-                    }
-                    None => {
-                        // No node received: the new table's `root` should be `Empty`
-                        self.cell.restore(store);
-                        Ok(TableStatus::Complete(Table::new(
-                            self.cell.clone(),
-                            Label::Empty,
-                            self.name.clone(),
-                        )))
-                    }
+            Ok(())
+        } else {
+            SyncError::MalformedAnswer.fail().spot(here!())
+        }
+    }
+
+    /// Returns the [`Question`] to send to `peer` next, when receiving the
+    /// same table from several [`TableSender`]s in parallel.
+    ///
+    /// Each label is asked to at most one peer at a time: labels already asked
+    /// to a peer are only asked again once that peer's [`TableAnswer`] is
+    /// learned (see [`learn_peer`]) or the peer is released (see
+    /// [`release_peer`]). An empty `Question` means that there is currently
+    /// nothing to ask `peer`.
+    ///
+    /// # Errors
+    ///
+    /// If the receiver was not created by [`Database::receive_expecting`],
+    /// [`SyncError::RootUnknown`] is returned: several peers can only be
+    /// trusted to serve the same table if its commitment is known in advance.
+    ///
+    /// [`TableSender`]: crate::database::TableSender
+    /// [`learn_peer`]: TableReceiver::learn_peer
+    /// [`release_peer`]: TableReceiver::release_peer
+    /// [`Database::receive_expecting`]: crate::database::Database::receive_expecting
+    /// [`SyncError::RootUnknown`]: crate::database::errors::SyncError::RootUnknown
+    pub fn ask_peer(&mut self, peer: usize) -> Result<Question, Top<SyncError>> {
+        if self.expected.is_none() {
+            return SyncError::RootUnknown.fail().spot(here!());
+        }
+
+        if matches!(self.peers.get(&peer), Some(Severity::Malicious)) {
+            return Ok(Question::labels(Vec::new()));
+        }
+
+        if let Some(commit) = self.awaited_root() {
+            // The root is asked to one peer at a time, like any other node
+            return match self.claims.entry(commit) {
+                Vacant(entry) => {
+                    entry.insert(peer);
+                    Ok(Question::root(commit))
                 }
-            } else {
-                // Receive incomplete, carry on with new `Question`
-                self.cell.restore(store);
-                let question = self.ask();
+                Occupied(..) => Ok(Question::labels(Vec::new())),
+            };
+        }
+
+        let labels = self
+            .frontier
+            .iter()
+            .filter(|(hash, _)| !self.claims.contains_key(*hash))
+            .map(|(_, context)| context.remote_label)
+            .take(self.settings.window)
+            .collect::<Vec<_>>();
+
+        for label in labels.iter() {
+            self.claims.insert(label.hash(), peer);
+        }
+
+        Ok(Question::labels(labels))
+    }
+
+    /// Learns `peer`'s [`TableAnswer`] to the last [`Question`] returned by
+    /// [`ask_peer`], releasing the labels asked to `peer`.
+    ///
+    /// Offences are accounted for each peer separately, across answers. Once
+    /// `peer` is found to be malicious, its answers are refused with
+    /// [`SyncError::MalformedAnswer`] and nothing more is asked to it. The
+    /// receiver remains usable with the other peers.
+    ///
+    /// [`ask_peer`]: TableReceiver::ask_peer
+    /// [`SyncError::MalformedAnswer`]: crate::database::errors::SyncError::MalformedAnswer
+    pub fn learn_peer(
+        &mut self,
+        peer: usize,
+        answer: TableAnswer<Key, Value>,
+    ) -> Result<(), Top<SyncError>> {
+        self.release_peer(peer);
 
-                Ok(TableStatus::Incomplete(self, question))
+        let mut severity = self.peers.remove(&peer).unwrap_or_else(Severity::ok);
+
+        let bytes = match self.settings.check(&answer) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // Exceeding the limits is never accidental
+                self.peers.insert(peer, Severity::malicious());
+                return Err(e);
             }
-        } else {
+        };
+
+        Counters::add(&self.counters.bytes_in, bytes);
+        Counters::add(&self.counters.round_trips, 1);
+
+        if severity.is_benign() && self.root.is_none() {
+            // Only the peer asked for the root can answer with nodes at this point
+            if let Some(node) = answer.0.first() {
+                if Some(node.hash()) != self.expected {
+                    severity = Severity::malicious();
+                }
+            }
+        }
+
+        if severity.is_benign() {
+            let mut store = self.cell.take();
+
+            let mut redundant = HashSet::new();
+
+            for node in answer.0 {
+                // Offences accumulate across all of `peer`'s answers
+                if let Err(offence) = self.update(&mut store, &mut redundant, node) {
+                    severity = severity.aggravate(offence, &self.settings);
+                }
+
+                if severity.is_malicious() {
+                    break;
+                }
+            }
+
             self.cell.restore(store);
+        }
+
+        let malicious = severity.is_malicious();
+        self.peers.insert(peer, severity);
+
+        if malicious {
             SyncError::MalformedAnswer.fail().spot(here!())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Makes the labels asked to `peer` available to other peers, e.g., if
+    /// `peer` is too slow to answer or was disconnected.
+    pub fn release_peer(&mut self, peer: usize) {
+        self.claims.retain(|_, claimant| *claimant != peer);
+    }
+
+    /// Returns `true` if all the nodes of the table were received.
+    pub fn is_complete(&self) -> bool {
+        self.frontier.is_empty() && self.awaited_root().is_none()
+    }
+
+    /// Returns the table received from several peers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the receive is not complete (see [`is_complete`]).
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`SyncError::StorageError`] if the receiver was checkpointed
+    /// (see [`checkpoint`]) and its checkpoint cannot be removed.
+    ///
+    /// [`is_complete`]: TableReceiver::is_complete
+    /// [`checkpoint`]: TableReceiver::checkpoint
+    pub fn finish(mut self) -> Result<Table<Key, Value>, Top<SyncError>> {
+        if !self.is_complete() {
+            panic!("called `TableReceiver::finish` on an incomplete receive");
+        }
+
+        let mut store = self.cell.take();
+        let table = self.complete(&mut store);
+        self.cell.restore(store);
+
+        table
+    }
+
+    fn complete(
+        &mut self,
+        store: &mut Store<Key, Value>,
+    ) -> Result<Table<Key, Value>, Top<SyncError>> {
+        if let Some(id) = self.checkpoint {
+            let family = store.db.cf_handle(RECEIVES).unwrap();
+            let mut batch = WriteBatchWithTransaction::<false>::default();
+
+            batch.delete_cf(&family, Self::key(id, Slot::State));
+
+            for index in 0..self.checkpointed.len() as u64 {
+                batch.delete_cf(&family, Self::key(id, Slot::Node(index)));
+            }
+
+            // Nothing is flushed yet: on failure, the receiver is dropped as is
+            store.db.write(batch).pot(SyncError::StorageError, here!())?;
+            self.checkpoint = None;
         }
+
+        let table = match self.root {
+            Some(root) => {
+                // At least one node was received: flush
+                self.flush(store, root);
+                Table::new(self.cell.clone(), root, self.name.clone())
+            }
+            None => {
+                // No node received: the new table's `root` should be `Empty`
+                Table::new(self.cell.clone(), Label::Empty, self.name.clone())
+            }
+        };
+
+        Ok(table)
     }
 
     fn update(
         &mut self,
         store: &mut Store<Key, Value>,
+        redundant: &mut HashSet<Bytes>,
         node: Node<Key, Value>,
     ) -> Result<(), Severity> {
         let hash = node.hash();
 
+        // Nodes below a node already in `store` are expected in the same answer
+        // (e.g., answers deeper than one level, or `TableSender::delta_from`),
+        // and are skipped without offence.
+        if self.root.is_some() && !self.frontier.contains_key(&hash) && redundant.remove(&hash) {
+            if let Node::Internal(left, right) = node {
+                Self::skip(redundant, &left, &right);
+            }
+
+            return Ok(());
+        }
+
         let location = if self.root.is_some() {
             // Check if `hash` is in `frontier`. If so, retrieve `location`.
             Ok(self.frontier.get(&hash).ok_or(Severity::benign())?.location)
@@ -170,8 +666,13 @@ where
                 Ok(())
             }?;
 
+            if let Node::Internal(ref left, ref right) = node {
+                Self::skip(redundant, left, right);
+            }
+
             store.incref(label);
-            self.held.insert(label);
+            self.held.insert(label, location);
+            Counters::add(&self.counters.nodes_reused, 1);
         } else {
             if let Node::Internal(ref left, ref right) = node {
                 self.sight(left, location.left());
@@ -179,12 +680,29 @@ where
             }
 
             self.acquired.insert(label.hash(), node);
+            Counters::add(&self.counters.nodes_received, 1);
         }
 
         self.frontier.remove(&hash);
         Ok(())
     }
 
+    // The commitment of the root still to be received, if known in advance
+    fn awaited_root(&self) -> Option<Bytes> {
+        match (self.root, self.expected) {
+            (None, Some(commit)) if commit != Label::Empty.hash() => Some(commit),
+            _ => None,
+        }
+    }
+
+    fn skip(redundant: &mut HashSet<Bytes>, left: &Label, right: &Label) {
+        for label in [left, right] {
+            if !label.is_empty() {
+                redundant.insert(label.hash());
+            }
+        }
+    }
+
     fn sight(&mut self, label: &Label, location: Prefix) {
         if !label.is_empty() {
             self.frontier.insert(
@@ -197,14 +715,6 @@ where
         }
     }
 
-    fn ask(&self) -> Question {
-        Question(
-            self.frontier.values().map(|context| context.remote_label)
-                .take(self.settings.window)
-                .collect(),
-        )
-    }
-
     fn flush(&mut self, store: &mut Store<Key, Value>, label: Label) {
         if !label.is_empty() {
             let stored = match store.entry(label) {
@@ -224,9 +734,7 @@ where
                 }
             };
 
-            if self.held.contains(&label) {
-                self.held.remove(&label);
-            } else {
+            if self.held.remove(&label).is_none() {
                 store.incref(label);
             }
 
@@ -236,6 +744,52 @@ where
             }
         }
     }
+
+    /// Accounts for `question` in [`stats`], once sent to the [`TableSender`].
+    ///
+    /// [`stats`]: TableReceiver::stats
+    /// [`TableSender`]: crate::database::TableSender
+    pub(crate) fn count_sent(&self, question: &Question) {
+        if !question.is_empty() {
+            Counters::add(
+                &self.counters.bytes_out,
+                bincode::serialized_size(question).unwrap(),
+            );
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn read(db: &DB, id: u64) -> Result<Option<(State, Vec<Node<Key, Value>>)>, Top<SyncError>> {
+        let family = db.cf_handle(RECEIVES).unwrap();
+
+        let state = match db
+            .get_cf(&family, Self::key(id, Slot::State))
+            .pot(SyncError::StorageError, here!())?
+        {
+            Some(raw) => {
+                bincode::deserialize::<State>(&raw).pot(SyncError::CorruptCheckpoint, here!())?
+            }
+            None => return Ok(None),
+        };
+
+        let nodes = (0..state.nodes)
+            .map(|index| {
+                let raw = db
+                    .get_cf(&family, Self::key(id, Slot::Node(index)))
+                    .pot(SyncError::StorageError, here!())?
+                    .ok_or_else(|| SyncError::CorruptCheckpoint.into_top().spot(here!()))?;
+
+                bincode::deserialize::<Node<Key, Value>>(&raw)
+                    .pot(SyncError::CorruptCheckpoint, here!())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some((state, nodes)))
+    }
+
+    fn key(id: u64, slot: Slot) -> Vec<u8> {
+        bincode::serialize(&(id, slot)).unwrap()
+    }
 }
 
 impl<Key, Value> Drop for TableReceiver<Key, Value>
@@ -246,7 +800,7 @@ where
     fn drop(&mut self) {
         let mut store = self.cell.take();
 
-        for label in self.held.iter() {
+        for label in self.held.keys() {
             drop::drop(&mut store, *label);
         }
 
@@ -260,8 +814,11 @@ mod tests {
 
     use super::*;
 
-    use crate::database::{sync::ANSWER_DEPTH, Database, TableSender};
+    use crate::database::{question::Ask, sync::ANSWER_DEPTH, Database, TableSender};
 
+    use talk::crypto::KeyChain;
+
+    #[allow(clippy::large_enum_variant)]
     enum Transfer<'a, Key, Value>
     where
         Key: Field,
@@ -293,6 +850,7 @@ mod tests {
                     return Transfer::Complete(table);
                 }
                 TableStatus::Incomplete(receiver_t, question) => {
+                    receiver_t.count_sent(&question);
                     answer = sender.answer(&question).unwrap();
                     receiver = receiver_t;
                 }
@@ -302,13 +860,20 @@ mod tests {
         Transfer::Incomplete(sender, receiver, answer)
     }
 
+    fn labels(question: Question) -> Vec<Label> {
+        match question.0 {
+            Ask::Labels(labels) => labels,
+            Ask::Root(..) => panic!("Question asks for the root"),
+        }
+    }
+
     impl<Key, Value> TableReceiver<Key, Value>
     where
         Key: Field,
         Value: Field,
     {
         pub(crate) fn held(&self) -> Vec<Label> {
-            self.held.iter().copied().collect()
+            self.held.keys().copied().collect()
         }
     }
 
@@ -1096,7 +1661,7 @@ mod tests {
             Node::Internal(_, r) => r,
             _ => unreachable!(),
         };
-        let right = sender.answer(&Question(vec![right_label])).unwrap().0[0].clone();
+        let right = sender.answer(&Question::labels(vec![right_label])).unwrap().0[0].clone();
 
         let first = match run_for(receiver, &mut sender, answer, 100) {
             Transfer::Incomplete(..) => {
@@ -1141,11 +1706,11 @@ mod tests {
             Node::Internal(_, r) => r,
             _ => unreachable!(),
         };
-        let right_label = match sender.answer(&Question(vec![right_label])).unwrap().0[0].clone() {
+        let right_label = match sender.answer(&Question::labels(vec![right_label])).unwrap().0[0].clone() {
             Node::Internal(_, r) => r,
             _ => unreachable!(),
         };
-        let right = sender.answer(&Question(vec![right_label])).unwrap().0[0].clone();
+        let right = sender.answer(&Question::labels(vec![right_label])).unwrap().0[0].clone();
 
         let first = match run_for(receiver, &mut sender, answer, 100) {
             Transfer::Incomplete(..) => {
@@ -1180,4 +1745,696 @@ mod tests {
         bob.check_correctness([&first], []);
         first.assert_records((0..256).map(|i| (i, i)));
     }
+
+    #[test]
+    fn certified() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let keychain = KeyChain::random();
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let certificate = original.certify(&keychain).unwrap();
+        let mut sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver
+            .expect_certified(&certificate, &keychain.keycard())
+            .unwrap();
+
+        let ([received], _) = run(&bob, [], [(&mut sender, receiver)]);
+
+        assert_eq!(received.commit(), certificate.commitment());
+        received.assert_records((0..256).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn certified_mismatch() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let keychain = KeyChain::random();
+
+        let certified = alice.table_with_records((0..256).map(|i| (i, i)));
+        let certificate = certified.certify(&keychain).unwrap();
+
+        for original in [alice.table_with_records([(0, 1)]), alice.empty_table("empty")] {
+            let sender = original.send();
+
+            let mut receiver = bob.receive();
+            receiver
+                .expect_certified(&certificate, &keychain.keycard())
+                .unwrap();
+
+            match receiver.learn(sender.hello()) {
+                Err(e) if *e.top() == SyncError::RootMismatch => (),
+                Err(x) => {
+                    panic!("Expected `SyncError::RootMismatch` but got {:?}", x)
+                }
+                _ => panic!("Receiver accepts a table that does not match its certificate"),
+            }
+        }
+
+        bob.check_correctness([], []);
+    }
+
+    #[test]
+    fn certified_by_someone_else() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..8).map(|i| (i, i)));
+        let certificate = original.certify(&KeyChain::random()).unwrap();
+
+        let mut receiver = bob.receive();
+        assert!(receiver
+            .expect_certified(&certificate, &KeyChain::random().keycard())
+            .is_err());
+    }
+
+    #[test]
+    fn expecting_without_hello() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        for original in [
+            alice.empty_table("empty"),
+            alice.table_with_records([(0, 1)]),
+            alice.table_with_records((0..256).map(|i| (i, i))),
+        ] {
+            let mut sender = original.send();
+
+            let receiver = bob.receive_expecting(original.commit());
+            let question = receiver.ask();
+
+            // The root is asked by commitment, unless the table is empty
+            if original.root().is_empty() {
+                assert!(question.is_empty());
+                assert!(receiver.is_complete());
+            } else {
+                assert_eq!(question, Question::root(original.commit().into()));
+                assert_eq!(receiver.stats().frontier, 1);
+                assert!(!receiver.is_complete());
+            }
+
+            let answer = sender.answer(&question).unwrap();
+
+            let received = match run_for(receiver, &mut sender, answer, usize::MAX) {
+                Transfer::Complete(table) => table,
+                Transfer::Incomplete(..) => unreachable!(),
+            };
+
+            assert_eq!(received.commit(), original.commit());
+            bob.check_correctness([&received], []);
+        }
+    }
+
+    #[test]
+    fn expecting_mismatch() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let expected = alice.table_with_records((0..8).map(|i| (i, i)));
+        let original = alice.table_with_records((0..8).map(|i| (i, i + 1)));
+        let sender = original.send();
+
+        let receiver = bob.receive_expecting(expected.commit());
+
+        // `sender` does not serve the table expected
+        match sender.answer(&receiver.ask()) {
+            Err(e) if *e.top() == SyncError::MalformedQuestion => (),
+            _ => panic!("Sender answers for a root it does not serve"),
+        }
+
+        match receiver.learn(sender.hello()) {
+            Err(e) if *e.top() == SyncError::RootMismatch => (),
+            Err(x) => {
+                panic!("Expected `SyncError::RootMismatch` but got {:?}", x)
+            }
+            _ => panic!("Receiver accepts a table that does not match its commitment"),
+        }
+
+        bob.check_correctness([], []);
+    }
+
+    #[test]
+    fn peers() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let senders = [original.send(), original.send()];
+
+        let mut receiver = bob.receive_expecting(original.commit());
+        receiver.settings.window = 4;
+
+        let mut rounds = 0;
+
+        while !receiver.is_complete() {
+            rounds += 1;
+
+            let questions = [receiver.ask_peer(0).unwrap(), receiver.ask_peer(1).unwrap()];
+
+            // The root is asked to a single peer, then peers are asked disjoint labels
+            match &questions {
+                [Question(Ask::Root(commit)), second] if rounds == 1 => {
+                    assert_eq!(*commit, original.commit().into());
+                    assert!(second.is_empty());
+                }
+                [Question(Ask::Labels(first)), Question(Ask::Labels(second))] => {
+                    assert!(first.iter().all(|label| !second.contains(label)));
+                }
+                _ => panic!("Unexpected questions"),
+            }
+
+            for (peer, question) in questions.iter().enumerate() {
+                let answer = senders[peer].answer(question).unwrap();
+                receiver.learn_peer(peer, answer).unwrap();
+            }
+
+            bob.check_correctness([], [&receiver]);
+        }
+
+        let received = receiver.finish().unwrap();
+
+        assert!(rounds > 1);
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+    }
+
+    #[test]
+    fn peers_malicious() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let other = alice.table_with_records((0..256).map(|i| (i, i + 1)));
+
+        let honest = original.send();
+        let malicious = other.send();
+
+        let mut receiver = bob.receive_expecting(original.commit());
+
+        // The malicious peer is asked the root, and serves another table
+        let question = receiver.ask_peer(1).unwrap();
+        assert_eq!(question.len(), 1);
+
+        match receiver.learn_peer(1, malicious.hello()) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            _ => panic!("Receiver accepts a root that does not match its commitment"),
+        }
+
+        // The malicious peer is never asked again, its labels go to the honest peer
+        assert!(receiver.ask_peer(1).unwrap().is_empty());
+
+        while !receiver.is_complete() {
+            let question = receiver.ask_peer(0).unwrap();
+            let answer = honest.answer(&question).unwrap();
+            receiver.learn_peer(0, answer).unwrap();
+        }
+
+        let received = receiver.finish().unwrap();
+
+        received.assert_records((0..256).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+    }
+
+    #[test]
+    fn peers_benign_across_answers() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let sender = original.send();
+
+        let mut receiver = bob.receive_expecting(original.commit());
+        receiver.settings.window = 1;
+
+        let root = sender.hello().0.remove(0);
+
+        let question = receiver.ask_peer(0).unwrap();
+        receiver.learn_peer(0, sender.answer(&question).unwrap()).unwrap();
+
+        let max_benign = (1 << (ANSWER_DEPTH + 1)) - 2;
+
+        // Each answer carries a single benign offence, acceptable on its own
+        for _ in 0..max_benign {
+            let question = receiver.ask_peer(0).unwrap();
+            let mut answer = sender.answer(&question).unwrap();
+            answer.0.push(root.clone());
+            receiver.learn_peer(0, answer).unwrap();
+        }
+
+        let question = receiver.ask_peer(0).unwrap();
+        let mut answer = sender.answer(&question).unwrap();
+        answer.0.push(root);
+
+        match receiver.learn_peer(0, answer) {
+            Err(e) if *e.top() == SyncError::MalformedAnswer => (),
+            _ => panic!("Receiver forgets benign offences between answers"),
+        }
+
+        assert!(!receiver.is_complete());
+        assert!(receiver.ask_peer(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn peers_released() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let sender = original.send();
+
+        let mut receiver = bob.receive_expecting(original.commit());
+
+        let question = receiver.ask_peer(0).unwrap();
+        assert!(receiver.ask_peer(1).unwrap().is_empty());
+
+        // Peer 0 is too slow, its labels are asked to peer 1
+        receiver.release_peer(0);
+        assert_eq!(receiver.ask_peer(1).unwrap(), question);
+
+        while !receiver.is_complete() {
+            let question = receiver.ask_peer(1).unwrap();
+            let answer = sender.answer(&question).unwrap();
+            receiver.learn_peer(1, answer).unwrap();
+        }
+
+        assert_eq!(receiver.finish().unwrap().commit(), original.commit());
+    }
+
+    #[test]
+    fn peers_root_unknown() {
+        let bob: Database<u32, u32> = Database::new("test");
+        let mut receiver = bob.receive();
+
+        match receiver.ask_peer(0) {
+            Err(e) if *e.top() == SyncError::RootUnknown => (),
+            _ => panic!("Receiver asks several peers for a root it does not expect"),
+        }
+    }
+
+    #[test]
+    fn deeper_answers() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+
+        let mut sender = original.send();
+        let receiver = bob.receive();
+        let (_, shallow_steps) = run(&bob, [], [(&mut sender, receiver)]);
+
+        let mut sender = original.send();
+        sender.settings.answer_depth = 5;
+
+        let mut receiver = bob.receive();
+        receiver.settings.answer_depth = 5;
+        receiver.settings.max_answer_nodes = usize::MAX;
+
+        let ([received], deep_steps) = run(&bob, [], [(&mut sender, receiver)]);
+
+        received.assert_records((0..256).map(|i| (i, i)));
+        assert!(deep_steps < shallow_steps);
+    }
+
+    #[test]
+    fn answer_too_large() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let sender = original.send();
+
+        let mut receiver = bob.receive();
+        receiver.settings.max_answer_nodes = 2;
+
+        match receiver.learn(sender.hello()) {
+            Err(e) if *e.top() == SyncError::AnswerTooLarge => (),
+            _ => panic!("Receiver accepts an answer with too many nodes"),
+        }
+
+        let mut receiver = bob.receive();
+        receiver.settings.max_answer_bytes = 64;
+
+        match receiver.learn(sender.hello()) {
+            Err(e) if *e.top() == SyncError::AnswerTooLarge => (),
+            _ => panic!("Receiver accepts an answer with too many bytes"),
+        }
+
+        let mut receiver = bob.receive_expecting(original.commit());
+        receiver.settings.max_answer_nodes = 2;
+
+        receiver.ask_peer(0).unwrap();
+
+        match receiver.learn_peer(0, sender.hello()) {
+            Err(e) if *e.top() == SyncError::AnswerTooLarge => (),
+            _ => panic!("Receiver accepts an answer with too many nodes"),
+        }
+
+        assert!(receiver.ask_peer(0).unwrap().is_empty());
+
+        drop(receiver);
+        bob.check_correctness([], []);
+    }
+
+    #[test]
+    fn deeper_than_agreed() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+
+        // The sender answers deeper than the receiver's `answer_depth`, and
+        // ignores the receiver's limits
+        let mut sender = original.send();
+        sender.settings.answer_depth = 6;
+        sender.settings.max_answer_nodes = usize::MAX;
+
+        let receiver = bob.receive();
+
+        let receiver = match receiver.learn(sender.hello()).unwrap() {
+            TableStatus::Incomplete(receiver, _) => receiver,
+            TableStatus::Complete(..) => unreachable!(),
+        };
+
+        let answer = sender.answer(&receiver.ask()).unwrap();
+
+        match receiver.learn(answer) {
+            Err(e) if *e.top() == SyncError::AnswerTooLarge => (),
+            _ => panic!("Receiver accepts an answer larger than agreed"),
+        }
+
+        bob.check_correctness([], []);
+    }
+
+    #[test]
+    fn held_descendants() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let receiver = bob.receive();
+        let ([first], _) = run(&bob, [], [(&mut sender, receiver)]);
+
+        // All the nodes below the (held) root are skipped without offence
+        sender.settings.answer_depth = 6;
+
+        let mut receiver = bob.receive();
+        receiver.settings.answer_depth = 6;
+        receiver.settings.max_answer_nodes = usize::MAX;
+
+        let second = match receiver.learn(sender.hello()).unwrap() {
+            TableStatus::Complete(table) => table,
+            TableStatus::Incomplete(..) => panic!("Receiver asks for nodes it holds"),
+        };
+
+        second.assert_records((0..256).map(|i| (i, i)));
+        bob.check_correctness([&first, &second], []);
+    }
+
+    #[test]
+    fn stats() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let receiver = bob.receive();
+        let hello = sender.hello();
+
+        let (receiver, answer) = match run_for(receiver, &mut sender, hello, 2) {
+            Transfer::Incomplete(_, receiver, answer) => (receiver, answer),
+            Transfer::Complete(..) => unreachable!(),
+        };
+
+        let received = receiver.stats();
+        let sent = sender.stats();
+
+        assert_eq!(received.round_trips, 2);
+        assert_eq!(received.nodes_reused, 0);
+        assert_eq!(received.frontier, receiver.frontier.len());
+        assert!(received.frontier > 0);
+
+        // One answer is on its way
+        assert_eq!(sent.round_trips, 3);
+        assert_eq!(sent.bytes_in, received.bytes_out);
+        assert_eq!(
+            sent.bytes_out,
+            received.bytes_in + bincode::serialized_size(&answer).unwrap()
+        );
+        assert_eq!(
+            sent.nodes_sent,
+            received.nodes_received + answer.0.len() as u64
+        );
+
+        // Questions asked but never sent are not accounted for
+        receiver.ask();
+        receiver.ask();
+
+        assert_eq!(receiver.stats().bytes_out, received.bytes_out);
+    }
+
+    #[test]
+    fn stats_reuse() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let receiver = bob.receive();
+        let ([first], _) = run(&bob, [], [(&mut sender, receiver)]);
+
+        let mut receiver = bob.receive_expecting(original.commit());
+
+        let question = receiver.ask_peer(0).unwrap();
+        let answer = sender.answer(&question).unwrap();
+        receiver.learn_peer(0, answer).unwrap();
+
+        // The whole table is satisfied by the local store
+        let stats = receiver.stats();
+
+        assert!(receiver.is_complete());
+        assert_eq!(stats.nodes_received, 0);
+        assert_eq!(stats.nodes_reused, 1);
+        assert_eq!(stats.frontier, 0);
+
+        let second = receiver.finish().unwrap();
+        bob.check_correctness([&first, &second], []);
+    }
+
+    // A resumed receiver carries on with `ask`, without a new `hello`
+    fn resume_with<Key, Value>(
+        database: &Database<Key, Value>,
+        mut receiver: TableReceiver<Key, Value>,
+        sender: &TableSender<Key, Value>,
+    ) -> Table<Key, Value>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        loop {
+            let answer = sender.answer(&receiver.ask()).unwrap();
+
+            match receiver.learn(answer).unwrap() {
+                TableStatus::Complete(table) => return table,
+                TableStatus::Incomplete(next, _) => receiver = next,
+            }
+
+            database.check_correctness([], [&receiver]);
+        }
+    }
+
+    #[test]
+    fn resume() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let alice: Database<u32, u32> = Database::new("test");
+        let original = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let (id, frontier) = {
+            let bob: Database<u32, u32> = Database::new(&path);
+
+            let receiver = bob.receive();
+            let hello = sender.hello();
+
+            let mut receiver = match run_for(receiver, &mut sender, hello, 2) {
+                Transfer::Incomplete(_, receiver, _) => receiver,
+                Transfer::Complete(..) => unreachable!(),
+            };
+
+            let id = receiver.checkpoint().unwrap();
+
+            let answer = sender.answer(&receiver.ask()).unwrap();
+            let mut receiver = match receiver.learn(answer).unwrap() {
+                TableStatus::Incomplete(receiver, _) => receiver,
+                TableStatus::Complete(..) => unreachable!(),
+            };
+
+            // Checkpointing again updates the same checkpoint
+            assert_eq!(receiver.checkpoint().unwrap(), id);
+
+            receiver.settings.window = usize::MAX;
+            let mut frontier = labels(receiver.ask());
+            frontier.sort_by_key(Label::hash);
+
+            (id, frontier)
+        };
+
+        let bob: Database<u32, u32> = Database::new(&path);
+        assert!(bob.resume_receive(id + 1).unwrap().is_none());
+
+        let mut receiver = bob.resume_receive(id).unwrap().unwrap();
+
+        // Only the nodes that were not acquired before the checkpoint are asked
+        receiver.settings.window = usize::MAX;
+        let mut question = labels(receiver.ask());
+        question.sort_by_key(Label::hash);
+        assert_eq!(question, frontier);
+
+        let received = resume_with(&bob, receiver, &sender);
+
+        received.assert_records((0..1024).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+
+        // The checkpoint is removed once the receive completes
+        assert!(bob.resume_receive(id).unwrap().is_none());
+
+        drop(bob);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn resume_without_held() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let alice: Database<u32, u32> = Database::new("test");
+
+        let first = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut first_sender = first.send();
+
+        let second = alice.table_with_records((0..512).map(|i| (i, i)));
+        let mut second_sender = second.send();
+
+        let id = {
+            let bob: Database<u32, u32> = Database::new(&path);
+
+            let receiver = bob.receive();
+            let ([first], _) = run(&bob, [], [(&mut first_sender, receiver)]);
+
+            let receiver = bob.receive();
+            let hello = second_sender.hello();
+
+            let mut receiver = match run_for(receiver, &mut second_sender, hello, 3) {
+                Transfer::Incomplete(_, receiver, _) => receiver,
+                Transfer::Complete(..) => unreachable!(),
+            };
+
+            assert!(!receiver.held().is_empty());
+            let id = receiver.checkpoint().unwrap();
+
+            drop(receiver);
+            drop(first);
+
+            id
+        };
+
+        // `first` was never stored: the nodes held from it are asked again
+        let bob: Database<u32, u32> = Database::new(&path);
+        let receiver = bob.resume_receive(id).unwrap().unwrap();
+        let received = resume_with(&bob, receiver, &second_sender);
+
+        received.assert_records((0..512).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+
+        drop(bob);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn discard_corrupt() {
+        let path: String = format!("test/{}", rand::random::<u64>());
+
+        let alice: Database<u32, u32> = Database::new("test");
+
+        let original = alice.table_with_records((0..256).map(|i| (i, i)));
+        let mut sender = original.send();
+
+        let bob: Database<u32, u32> = Database::new(&path);
+
+        let receiver = bob.receive();
+        let hello = sender.hello();
+
+        let mut receiver = match run_for(receiver, &mut sender, hello, 2) {
+            Transfer::Incomplete(_, receiver, _) => receiver,
+            Transfer::Complete(..) => unreachable!(),
+        };
+
+        let id = receiver.checkpoint().unwrap();
+        drop(receiver);
+
+        let other = bob.receive_expecting(original.commit()).checkpoint().unwrap();
+
+        let mut ids = bob.receive_checkpoints();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![id, other]);
+
+        let keys = |database: &Database<u32, u32>| {
+            let store = database.store.take();
+            let family = store.db.cf_handle(RECEIVES).unwrap();
+
+            let mut keys = Vec::new();
+            let mut iter = store.db.raw_iterator_cf(&family);
+
+            iter.seek_to_first();
+            while iter.valid() {
+                keys.push(iter.key().unwrap().to_vec());
+                iter.next();
+            }
+
+            database.store.restore(store);
+            keys
+        };
+
+        let node = TableReceiver::<u32, u32>::key(id, Slot::Node(0));
+        assert!(keys(&bob).contains(&node));
+
+        {
+            let store = bob.store.take();
+            let family = store.db.cf_handle(RECEIVES).unwrap();
+
+            store
+                .db
+                .put_cf(&family, TableReceiver::<u32, u32>::key(id, Slot::State), [0xff; 3])
+                .unwrap();
+
+            bob.store.restore(store);
+        }
+
+        assert!(bob.resume_receive(id).is_err());
+
+        // Nodes are removed along with the state that cannot be decoded
+        assert!(bob.discard_receive(id).unwrap());
+        assert!(!keys(&bob).contains(&node));
+
+        assert_eq!(bob.receive_checkpoints(), vec![other]);
+        assert!(bob.resume_receive(id).unwrap().is_none());
+        assert!(!bob.discard_receive(id).unwrap());
+
+        // Other checkpoints are left untouched
+        let receiver = bob.resume_receive(other).unwrap().unwrap();
+        let received = resume_with(&bob, receiver, &sender);
+
+        received.assert_records((0..256).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+
+        drop(bob);
+        std::fs::remove_dir_all(path).unwrap();
+    }
 }
@@ -1,18 +1,67 @@
 use crate::{
-    common::store::Field,
+    common::{
+        data::Bytes,
+        store::{hash, Field},
+        tree::Prefix,
+    },
     database::{
         errors::SyncError,
-        store::{Handle, Label, Node, Store},
-        sync::ANSWER_DEPTH,
+        store::{Handle, Label, MapId, Node, Store},
+        sync::{Counters, SyncSettings, SyncStats},
+        question::Ask,
         Question, Table, TableAnswer,
     },
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::{
+    collections::hash_map::Entry::{Occupied, Vacant},
+    iter, slice,
+};
+
+use talk::crypto::primitives::hash::Hash;
+
+pub struct TableSender<Key: Field, Value: Field> {
+    handle: Handle<Key, Value>,
+    counters: Counters,
+    pub settings: SyncSettings,
+}
+
+// Budget left for the answer being collected
+struct Budget {
+    nodes: usize,
+    bytes: u64,
+}
+
+impl Budget {
+    fn new(settings: &SyncSettings) -> Self {
+        Budget {
+            nodes: settings.max_answer_nodes,
+            // The length of the answer is serialized as a `u64`
+            bytes: (settings.max_answer_bytes as u64).saturating_sub(8),
+        }
+    }
+
+    // Returns `false` (and stays exhausted) if `node` does not fit
+    fn spend<Key, Value>(&mut self, node: &Node<Key, Value>) -> Result<bool, Top<SyncError>>
+    where
+        Key: Field,
+        Value: Field,
+    {
+        let bytes = bincode::serialized_size(node).pot(SyncError::MalformedQuestion, here!())?;
 
-pub struct TableSender<Key: Field, Value: Field>(Handle<Key, Value>);
+        if self.nodes == 0 || bytes > self.bytes {
+            self.nodes = 0;
+            return Ok(false);
+        }
+
+        self.nodes -= 1;
+        self.bytes -= bytes;
+
+        Ok(true)
+    }
+}
 
 impl<Key, Value> TableSender<Key, Value>
 where
@@ -20,50 +69,270 @@ where
     Value: Field,
 {
     pub(crate) fn from_handle(handle: Handle<Key, Value>) -> Self {
-        TableSender(handle)
+        TableSender {
+            handle,
+            counters: Counters::default(),
+            settings: SyncSettings::default(),
+        }
     }
 
     pub fn hello(&self) -> TableAnswer<Key, Value> {
-        let root = *self.0.root.read().unwrap();
-        self.answer(&Question(vec![root])).unwrap()
+        self.hello_with(&self.settings)
     }
 
     pub fn answer(
         &self,
         question: &Question,
     ) -> Result<TableAnswer<Key, Value>, Top<SyncError>> {
+        self.answer_with(question, &self.settings)
+    }
+
+    // Like `hello`, within the `settings` negotiated with the receiver
+    pub(crate) fn hello_with(&self, settings: &SyncSettings) -> TableAnswer<Key, Value> {
+        let root = *self.handle.root.read().unwrap();
+        self.respond(&Question::labels(vec![root]), settings).unwrap()
+    }
+
+    // Like `answer`, within the `settings` negotiated with the receiver
+    pub(crate) fn answer_with(
+        &self,
+        question: &Question,
+        settings: &SyncSettings,
+    ) -> Result<TableAnswer<Key, Value>, Top<SyncError>> {
+        Counters::add(&self.counters.bytes_in, bincode::serialized_size(question).unwrap());
+        self.respond(question, settings)
+    }
+
+    fn respond(
+        &self,
+        question: &Question,
+        settings: &SyncSettings,
+    ) -> Result<TableAnswer<Key, Value>, Top<SyncError>> {
+        let root = *self.handle.root.read().unwrap();
+
+        let labels = match &question.0 {
+            Ask::Labels(labels) => labels.as_slice(),
+            // A receiver that knows the commitment in advance asks for the root by
+            // commitment, without knowing whether the root is `Internal` or `Leaf`
+            Ask::Root(commit) if *commit == root.hash() => slice::from_ref(&root),
+            Ask::Root(..) => return SyncError::MalformedQuestion.fail().spot(here!()),
+        };
+
         let mut collector: Vec<Node<Key, Value>> = Vec::new();
-        let mut store = self.0.cell.take();
+        let mut store = self.handle.cell.take();
 
-        for label in &question.0 {
-            if let Err(e) = TableSender::grab(&mut store, &mut collector, *label, ANSWER_DEPTH) {
-                self.0.cell.restore(store);
+        let mut budget = Budget::new(settings);
+
+        for label in labels {
+            if let Err(e) = TableSender::grab(
+                &mut store,
+                &mut collector,
+                &mut budget,
+                *label,
+                settings.answer_depth,
+            ) {
+                self.handle.cell.restore(store);
                 return Err(e);
             }
         }
 
-        self.0.cell.restore(store);
-        Ok(TableAnswer(collector))
+        self.handle.cell.restore(store);
+        Ok(self.sent(TableAnswer(collector)))
+    }
+
+    /// Returns the nodes of the table that differ from the table whose
+    /// commitment is `base_commit`, to be fed to a [`TableReceiver`] in place
+    /// of [`hello`].
+    ///
+    /// If the receiver holds the base table, the whole table is received in a
+    /// single round trip: below the root, only the nodes along changed paths
+    /// (and the roots of the unchanged subtrees next to them) are sent. If the
+    /// base table is empty or consists of a single record, no subtree is
+    /// shared and every node of the table is sent. Either way, the answer
+    /// respects the sender's limits (see [`SyncSettings`]), and the receiver
+    /// asks for whatever is left out.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`SyncError::BaseUnknown`] if the base table is not in the
+    /// sender's [`Database`] (the caller can fall back to [`hello`]), and
+    /// with [`SyncError::MalformedQuestion`] if a node cannot be found in
+    /// the store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::database::{Database, TableStatus, TableTransaction};
+    ///
+    /// let alice: Database<u32, u32> = Database::new("test");
+    /// let bob: Database<u32, u32> = Database::new("test2");
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// for i in 0..64 {
+    ///     transaction.set(i, i).unwrap();
+    /// }
+    ///
+    /// let table = alice.empty_table("test");
+    /// table.execute(transaction);
+    ///
+    /// // Bob receives a first version of the table
+    /// let sender = table.send();
+    /// let mut receiver = bob.receive_expecting(table.commit());
+    ///
+    /// let base = loop {
+    ///     let answer = sender.answer(&receiver.ask()).unwrap();
+    ///
+    ///     match receiver.learn(answer).unwrap() {
+    ///         TableStatus::Complete(table) => break table,
+    ///         TableStatus::Incomplete(next, _) => receiver = next,
+    ///     }
+    /// };
+    ///
+    /// // Alice keeps the first version around (cloning a table is cheap)
+    /// let snapshot = (*table).clone();
+    ///
+    /// let mut transaction = TableTransaction::default();
+    /// transaction.set(7, 70).unwrap();
+    /// table.execute(transaction);
+    ///
+    /// // Bob receives the second version in one round trip
+    /// let delta = table.send().delta_from(snapshot.commit()).unwrap();
+    ///
+    /// match bob.receive().learn(delta).unwrap() {
+    ///     TableStatus::Complete(received) => assert_eq!(received.commit(), table.commit()),
+    ///     TableStatus::Incomplete(..) => unreachable!(),
+    /// }
+    /// # drop(base);
+    /// ```
+    ///
+    /// [`TableReceiver`]: crate::database::TableReceiver
+    /// [`hello`]: TableSender::hello
+    /// [`Database`]: crate::database::Database
+    /// [`SyncSettings`]: crate::database::sync::SyncSettings
+    /// [`SyncError::BaseUnknown`]: crate::database::errors::SyncError::BaseUnknown
+    /// [`SyncError::MalformedQuestion`]: crate::database::errors::SyncError::MalformedQuestion
+    pub fn delta_from(&self, base_commit: Hash) -> Result<TableAnswer<Key, Value>, Top<SyncError>> {
+        let root = *self.handle.root.read().unwrap();
+        let mut store = self.handle.cell.take();
+
+        let base = match TableSender::base(&mut store, base_commit.into()) {
+            Some(base) => base,
+            None => {
+                self.handle.cell.restore(store);
+                return SyncError::BaseUnknown.fail().spot(here!());
+            }
+        };
+
+        let mut collector = Vec::new();
+        let mut budget = Budget::new(&self.settings);
+
+        let result = TableSender::delta(&mut store, &mut collector, &mut budget, root, base);
+
+        self.handle.cell.restore(store);
+        result?;
+
+        Ok(self.sent(TableAnswer(collector)))
+    }
+
+    /// Returns a [`SyncStats`] snapshot of the answers given so far.
+    ///
+    /// [`SyncStats`]: crate::database::sync::SyncStats
+    pub fn stats(&self) -> SyncStats {
+        self.counters.snapshot(0)
     }
 
     pub fn end(self, name: String) -> Table<Key, Value> {
-        Table::from_handle(self.0, name)
+        Table::from_handle(self.handle, name)
+    }
+
+    fn sent(&self, answer: TableAnswer<Key, Value>) -> TableAnswer<Key, Value> {
+        Counters::add(&self.counters.nodes_sent, answer.0.len() as u64);
+        Counters::add(&self.counters.bytes_out, bincode::serialized_size(&answer).unwrap());
+        Counters::add(&self.counters.round_trips, 1);
+
+        answer
+    }
+
+    /// Finds the label of the root whose hash is `commit` in `store`. A
+    /// single-record root is stored under a map derived from its key, which
+    /// is unknown here: every leaf map is probed.
+    fn base(store: &mut Store<Key, Value>, commit: Bytes) -> Option<Label> {
+        if commit == hash::empty() {
+            return Some(Label::Empty);
+        }
+
+        iter::once(Label::Internal(MapId::internal(Prefix::root()), commit))
+            .chain(MapId::leaves().map(|map| Label::Leaf(map, commit)))
+            .find(|label| matches!(store.entry(*label), Occupied(..)))
+    }
+
+    /// Recursively collect the nodes under `label` that differ from those
+    /// under `base`, along with the roots of the subtrees they share.
+    fn delta(
+        store: &mut Store<Key, Value>,
+        collector: &mut Vec<Node<Key, Value>>,
+        budget: &mut Budget,
+        label: Label,
+        base: Label,
+    ) -> Result<(), Top<SyncError>> {
+        if label.is_empty() {
+            return Ok(());
+        }
+
+        let node = TableSender::get(store, label)?;
+
+        if !budget.spend(&node)? {
+            return Ok(());
+        }
+
+        let recur = match &node {
+            Node::Internal(left, right) if label != base => {
+                let (base_left, base_right) = match TableSender::get(store, base)? {
+                    Node::Internal(base_left, base_right) => (base_left, base_right),
+                    _ => (Label::Empty, Label::Empty),
+                };
+
+                Some(((*left, base_left), (*right, base_right)))
+            }
+            _ => None,
+        };
+
+        collector.push(node);
+
+        if let Some(((left, base_left), (right, base_right))) = recur {
+            TableSender::delta(store, collector, budget, left, base_left)?;
+            TableSender::delta(store, collector, budget, right, base_right)?;
+        }
+
+        Ok(())
     }
 
-    /// Recursively grab nodes from the store and add them to the collector.
+    fn get(store: &mut Store<Key, Value>, label: Label) -> Result<Node<Key, Value>, Top<SyncError>> {
+        if label.is_empty() {
+            return Ok(Node::Empty);
+        }
+
+        match store.entry(label) {
+            Occupied(entry) => Ok(entry.get().node.clone()),
+            Vacant(..) => SyncError::MalformedQuestion.fail().spot(here!()),
+        }
+    }
+
+    /// Recursively grab nodes from the store and add them to the collector,
+    /// until `budget` is exhausted. Nodes left out are asked again by the receiver.
     fn grab(
         store: &mut Store<Key, Value>,
         collector: &mut Vec<Node<Key, Value>>,
+        budget: &mut Budget,
         label: Label,
         ttl: u8,
     ) -> Result<(), Top<SyncError>> {
         if !label.is_empty() {
-            let node = match store.entry(label) {
-                Occupied(entry) => {
-                    Ok(entry.get().node.clone())
-                }
-                Vacant(..) => SyncError::MalformedQuestion.fail().spot(here!()),
-            }?;
+            let node = TableSender::get(store, label)?;
+
+            if !budget.spend(&node)? {
+                return Ok(());
+            }
 
             // TODO why are don't add leaf nodes to the collector?
             let recur = match node {
@@ -74,8 +343,8 @@ where
             collector.push(node);
 
             if let Some((left, right)) = recur {
-                TableSender::grab(store, collector, left, ttl - 1)?;
-                TableSender::grab(store, collector, right, ttl - 1)?;
+                TableSender::grab(store, collector, budget, left, ttl - 1)?;
+                TableSender::grab(store, collector, budget, right, ttl - 1)?;
             }
 
             Ok(())
@@ -89,7 +358,7 @@ where
 mod tests {
     use super::*;
 
-    use crate::database::{store::MapId, Database};
+    use crate::database::{store::MapId, Database, Table, TableReceiver, TableStatus, TableTransaction};
 
     use std::collections::hash_map::Entry::Occupied;
 
@@ -100,7 +369,7 @@ mod tests {
 
             let send = table.send();
     
-            let answer = send.answer(&Question(vec![Label::Empty])).unwrap();
+            let answer = send.answer(&Question::labels(vec![Label::Empty])).unwrap();
     
             assert_eq!(answer, TableAnswer(vec!()));
         });        
@@ -115,7 +384,7 @@ mod tests {
             let leaf = leaf!(1u32, 1u32);
             let leaf_label = Label::Leaf(MapId::leaf(&wrap!(1u32).digest()), leaf.hash());
     
-            let question = Question(vec![leaf_label]);
+            let question = Question::labels(vec![leaf_label]);
             let answer = send.answer(&question);
     
             match answer {
@@ -132,7 +401,7 @@ mod tests {
             let table = database.table_with_records([(0u32, 0u32)]);
 
             let send = table.send();
-            let label = *send.0.root.read().unwrap();
+            let label = *send.handle.root.read().unwrap();
     
             let mut store = database.store.take();
             let node = match store.entry(label) {
@@ -141,7 +410,7 @@ mod tests {
             };
             database.store.restore(store);
     
-            let answer = send.answer(&Question(vec![label])).unwrap();
+            let answer = send.answer(&Question::labels(vec![label])).unwrap();
     
             assert_eq!(answer, TableAnswer(vec!(node)));
         });     
@@ -153,7 +422,7 @@ mod tests {
             let table = database.table_with_records([(0u32, 0u32), (4u32, 4u32)]);
 
             let send = table.send();
-            let label0 = *send.0.root.read().unwrap();
+            let label0 = *send.handle.root.read().unwrap();
 
             let mut store = database.store.take();
             let n0 = match store.entry(label0) {
@@ -176,9 +445,170 @@ mod tests {
             };
             database.store.restore(store);
 
-            let answer = send.answer(&Question(vec![label0])).unwrap();
+            let answer = send.answer(&Question::labels(vec![label0])).unwrap();
 
             assert_eq!(answer, TableAnswer(vec!(n0, n1, n2)));
         })
     }
+
+    #[test]
+    fn budget() {
+        Database::<u32, u32>::test_database(|database| {
+            let table = database.table_with_records((0..256).map(|i| (i, i)));
+
+            let mut send = table.send();
+            let full = send.hello();
+
+            send.settings.max_answer_nodes = 3;
+            let answer = send.hello();
+
+            assert_eq!(answer.0.len(), 3);
+            assert_eq!(answer.0[..], full.0[..3]);
+
+            send.settings.max_answer_nodes = usize::MAX;
+            send.settings.max_answer_bytes = bincode::serialized_size(&full).unwrap() as usize - 1;
+            let answer = send.hello();
+
+            assert_eq!(answer.0.len(), full.0.len() - 1);
+            assert!(bincode::serialized_size(&answer).unwrap() as usize <= send.settings.max_answer_bytes);
+        })
+    }
+
+    #[test]
+    fn answer_depth() {
+        Database::<u32, u32>::test_database(|database| {
+            let table = database.table_with_records((0..256).map(|i| (i, i)));
+
+            let mut send = table.send();
+            send.settings.answer_depth = 0;
+            assert_eq!(send.hello().0.len(), 1);
+
+            send.settings.answer_depth = 4;
+            assert_eq!(send.hello().0.len(), 31);
+        })
+    }
+
+    fn receive_with(
+        mut receiver: TableReceiver<u32, u32>,
+        sender: &TableSender<u32, u32>,
+        first: TableAnswer<u32, u32>,
+    ) -> (Table<u32, u32>, usize) {
+        let mut answer = first;
+        let mut round_trips = 1;
+
+        loop {
+            match receiver.learn(answer).unwrap() {
+                TableStatus::Complete(table) => return (table, round_trips),
+                TableStatus::Incomplete(next, question) => {
+                    answer = sender.answer(&question).unwrap();
+                    receiver = next;
+                    round_trips += 1;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn delta() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let table = alice.table_with_records((0..1024).map(|i| (i, i)));
+        let snapshot = (*table).clone();
+
+        let sender = table.send();
+        let (base, _) = receive_with(bob.receive(), &sender, sender.hello());
+
+        let mut transaction = TableTransaction::default();
+        transaction.set(7, 70).unwrap();
+        transaction.set(2048, 2048).unwrap();
+        transaction.remove(512).unwrap();
+        table.execute(transaction);
+
+        let sender = table.send();
+        let delta = sender.delta_from(snapshot.commit()).unwrap();
+
+        // Only the changed paths (and their neighbours) are sent
+        assert!(delta.0.len() < 128);
+
+        let (received, round_trips) = receive_with(bob.receive(), &sender, delta);
+
+        assert_eq!(round_trips, 1);
+        assert_eq!(received.commit(), table.commit());
+        received.assert_records(table.collect_records());
+
+        bob.check_correctness([&base, &received], []);
+    }
+
+    #[test]
+    fn delta_from_self() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let table = alice.table_with_records((0..256).map(|i| (i, i)));
+        let sender = table.send();
+
+        let (base, _) = receive_with(bob.receive(), &sender, sender.hello());
+
+        let delta = sender.delta_from(table.commit()).unwrap();
+        assert_eq!(delta.0.len(), 1);
+
+        let (received, round_trips) = receive_with(bob.receive(), &sender, delta);
+
+        assert_eq!(round_trips, 1);
+        bob.check_correctness([&base, &received], []);
+    }
+
+    #[test]
+    fn delta_from_unknown() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let table = alice.table_with_records((0..256).map(|i| (i, i)));
+        let other = alice.table_with_records((0..256).map(|i| (i, i + 1)));
+
+        // `other` is not held by Bob: the receive still completes
+        let mut sender = table.send();
+        sender.settings.max_answer_nodes = 16;
+
+        let delta = sender.delta_from(other.commit()).unwrap();
+        assert_eq!(delta.0.len(), 16);
+
+        let (received, round_trips) = receive_with(bob.receive(), &sender, delta);
+
+        assert!(round_trips > 1);
+        received.assert_records((0..256).map(|i| (i, i)));
+        bob.check_correctness([&received], []);
+
+        // Neither Alice nor Bob hold this base
+        let unknown = talk::crypto::primitives::hash::hash(&0u32).unwrap();
+
+        match sender.delta_from(unknown) {
+            Err(e) if *e.top() == SyncError::BaseUnknown => (),
+            _ => panic!("Sender computes a delta from a base it does not hold"),
+        }
+
+        // The sender is still usable
+        let (received, _) = receive_with(bob.receive(), &sender, sender.hello());
+        received.assert_records((0..256).map(|i| (i, i)));
+    }
+
+    #[test]
+    fn delta_from_small() {
+        let alice: Database<u32, u32> = Database::new("test");
+        let bob: Database<u32, u32> = Database::new("test2");
+
+        let table = alice.table_with_records((0..64).map(|i| (i, i)));
+        let sender = table.send();
+
+        for base in [alice.empty_table("empty"), alice.table_with_records([(0u32, 0u32)])] {
+            // No subtree is shared with the base: the whole table is sent
+            let delta = sender.delta_from(base.commit()).unwrap();
+
+            let (received, round_trips) = receive_with(bob.receive(), &sender, delta);
+
+            assert_eq!(round_trips, 1);
+            received.assert_records((0..64).map(|i| (i, i)));
+        }
+    }
 }
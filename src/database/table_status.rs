@@ -3,6 +3,7 @@ use crate::{
     database::{Question, Table, TableReceiver},
 };
 
+#[allow(clippy::large_enum_variant)]
 pub enum TableStatus<Key: Field, Value: Field> {
     Complete(Table<Key, Value>),
     Incomplete(TableReceiver<Key, Value>, Question),
@@ -0,0 +1,35 @@
+const DEFAULT_MAX_NODES: usize = 1 << 24;
+const DEFAULT_MAX_DEPTH: u8 = 128;
+
+/// Limits of the deserialization of a [`Map`] (see [`Map::deserialize_with`]).
+///
+/// Encodings of more than `max_nodes` nodes, or with nodes more than
+/// `max_depth` levels deep, are rejected with
+/// [`DeserializeError::LimitExceeded`].
+///
+/// Nodes are read recursively, one level of the tree at a time: `max_depth`
+/// bounds the stack used by deserialization. Keys are identified by 256-bit
+/// paths, so no map is deeper than 256 levels. However, for a map of `n`
+/// keys to be deeper than `log2(n) + d` levels, some two keys must share
+/// `d` bits of path, which happens with probability about `2^-d`: the default
+/// `max_depth` is far beyond the depth of any map that can be built.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::deserialize_with`]: crate::map::Map::deserialize_with
+/// [`DeserializeError::LimitExceeded`]: crate::map::errors::DeserializeError::LimitExceeded
+#[derive(Debug, Clone)]
+pub struct DeserializeSettings {
+    /// Maximum number of nodes (including `Empty` nodes and `Stub`s) in a map.
+    pub max_nodes: usize,
+    /// Maximum depth of a node (the root lies at depth 0).
+    pub max_depth: u8,
+}
+
+impl Default for DeserializeSettings {
+    fn default() -> Self {
+        DeserializeSettings {
+            max_nodes: DEFAULT_MAX_NODES,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
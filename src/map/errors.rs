@@ -22,4 +22,6 @@ pub enum TopologyError {
 pub enum DeserializeError {
     #[doom(description("Flawed topology: {}", source))]
     FlawedTopology { source: TopologyError },
+    #[doom(description("Exceeded the deserialization limits"))]
+    LimitExceeded,
 }
@@ -0,0 +1,235 @@
+use crate::{
+    common::{
+        store::Field,
+        tree::{Direction, Path},
+    },
+    map::{errors::MapError, store::Node},
+};
+
+use doomstack::{here, Doom, ResultExt, Top};
+
+// Returns the children of `node` at `depth`: a leaf is pushed one level down,
+// towards its key path
+fn expand<Key, Value>(node: &Node<Key, Value>, depth: u8) -> (Node<Key, Value>, Node<Key, Value>)
+where
+    Key: Field,
+    Value: Field,
+{
+    match node {
+        Node::Internal(internal) => (internal.left().clone(), internal.right().clone()),
+        Node::Leaf(leaf) => {
+            if Path::from(leaf.key().digest())[depth] == Direction::Left {
+                (node.clone(), Node::Empty)
+            } else {
+                (Node::Empty, node.clone())
+            }
+        }
+        _ => (Node::Empty, Node::Empty),
+    }
+}
+
+// Joins the results `left` and `right` of an operation on the children
+// `lho_left` and `lho_right`, lifting a lone leaf to preserve compactness
+fn join<Key, Value>(
+    left: Node<Key, Value>,
+    right: Node<Key, Value>,
+    lho_left: &Node<Key, Value>,
+    lho_right: &Node<Key, Value>,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    match (left, right) {
+        (Node::Empty, Node::Empty) => Ok(Node::Empty),
+        (Node::Leaf(leaf), Node::Empty) | (Node::Empty, Node::Leaf(leaf)) => Ok(Node::Leaf(leaf)),
+        // A lone `Stub` might replace a leaf, which would have to be lifted
+        (Node::Stub(_), Node::Empty) | (Node::Empty, Node::Stub(_))
+            if !lho_left.is_empty() && !lho_right.is_empty() =>
+        {
+            MapError::BranchUnknown.fail().spot(here!())
+        }
+        (left, right) => Ok(Node::internal(left, right)),
+    }
+}
+
+fn same_key<Key, Value>(lho: &Node<Key, Value>, rho: &Node<Key, Value>) -> bool
+where
+    Key: Field,
+    Value: Field,
+{
+    match (lho, rho) {
+        (Node::Leaf(lho), Node::Leaf(rho)) => lho.key().digest() == rho.key().digest(),
+        _ => false,
+    }
+}
+
+fn union_recur<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+    depth: u8,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    if lho.hash() == rho.hash() || rho.is_empty() || same_key(lho, rho) {
+        return Ok(lho.clone());
+    }
+
+    if lho.is_empty() {
+        return Ok(rho.clone());
+    }
+
+    if lho.is_stub() || rho.is_stub() {
+        return MapError::BranchUnknown.fail().spot(here!());
+    }
+
+    let (lho_left, lho_right) = expand(lho, depth);
+    let (rho_left, rho_right) = expand(rho, depth);
+
+    let left = union_recur(&lho_left, &rho_left, depth + 1)?;
+    let right = union_recur(&lho_right, &rho_right, depth + 1)?;
+
+    join(left, right, &lho_left, &lho_right)
+}
+
+fn intersection_recur<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+    depth: u8,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    if lho.hash() == rho.hash() || same_key(lho, rho) {
+        return Ok(lho.clone());
+    }
+
+    if lho.is_empty() || rho.is_empty() || (lho.is_leaf() && rho.is_leaf()) {
+        return Ok(Node::Empty);
+    }
+
+    if lho.is_stub() || rho.is_stub() {
+        return MapError::BranchUnknown.fail().spot(here!());
+    }
+
+    let (lho_left, lho_right) = expand(lho, depth);
+    let (rho_left, rho_right) = expand(rho, depth);
+
+    let left = intersection_recur(&lho_left, &rho_left, depth + 1)?;
+    let right = intersection_recur(&lho_right, &rho_right, depth + 1)?;
+
+    join(left, right, &lho_left, &lho_right)
+}
+
+fn difference_recur<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+    depth: u8,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    if lho.hash() == rho.hash() || lho.is_empty() || same_key(lho, rho) {
+        return Ok(Node::Empty);
+    }
+
+    if rho.is_empty() || (lho.is_leaf() && rho.is_leaf()) {
+        return Ok(lho.clone());
+    }
+
+    if lho.is_stub() || rho.is_stub() {
+        return MapError::BranchUnknown.fail().spot(here!());
+    }
+
+    let (lho_left, lho_right) = expand(lho, depth);
+    let (rho_left, rho_right) = expand(rho, depth);
+
+    let left = difference_recur(&lho_left, &rho_left, depth + 1)?;
+    let right = difference_recur(&lho_right, &rho_right, depth + 1)?;
+
+    join(left, right, &lho_left, &lho_right)
+}
+
+fn is_subset_recur<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+    depth: u8,
+) -> Result<bool, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    if lho.hash() == rho.hash() || lho.is_empty() || same_key(lho, rho) {
+        return Ok(true);
+    }
+
+    // A (compact) `Internal` node has at least two leaves below it
+    if rho.is_empty() || (rho.is_leaf() && !lho.is_stub()) {
+        return Ok(false);
+    }
+
+    if lho.is_stub() || rho.is_stub() {
+        return MapError::BranchUnknown.fail().spot(here!());
+    }
+
+    let (lho_left, lho_right) = expand(lho, depth);
+    let (rho_left, rho_right) = expand(rho, depth);
+
+    Ok(is_subset_recur(&lho_left, &rho_left, depth + 1)?
+        && is_subset_recur(&lho_right, &rho_right, depth + 1)?)
+}
+
+/// Returns the root of a tree holding the keys of both `lho` and `rho`. Keys
+/// held by both trees keep their `lho` value.
+pub(crate) fn union<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    union_recur(lho, rho, 0)
+}
+
+/// Returns the root of a tree holding the entries of `lho` whose key is
+/// also held by `rho`.
+pub(crate) fn intersection<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    intersection_recur(lho, rho, 0)
+}
+
+/// Returns the root of a tree holding the entries of `lho` whose key is
+/// not held by `rho`.
+pub(crate) fn difference<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+) -> Result<Node<Key, Value>, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    difference_recur(lho, rho, 0)
+}
+
+/// Returns `true` if every key held by `lho` is also held by `rho`.
+pub(crate) fn is_subset<Key, Value>(
+    lho: &Node<Key, Value>,
+    rho: &Node<Key, Value>,
+) -> Result<bool, Top<MapError>>
+where
+    Key: Field,
+    Value: Field,
+{
+    is_subset_recur(lho, rho, 0)
+}
@@ -12,6 +12,14 @@ use crate::{
 
 use doomstack::{here, Doom, ResultExt, Top};
 
+/// An `Update` within a batch, which holds the previous value at its key
+/// once applied.
+pub(crate) struct Slot<Key: Field, Value: Field> {
+    pub path: Path,
+    pub action: Option<Action<Key, Value>>,
+    pub previous: Option<Value>,
+}
+
 fn branch<Key, Value>(
     left: Node<Key, Value>,
     right: Node<Key, Value>,
@@ -20,7 +28,7 @@ fn branch<Key, Value>(
 ) -> (Node<Key, Value>, Result<Option<Value>, Top<MapError>>)
 where
     Key: Field,
-    Value: Field,
+    Value: Field + Clone,
 {
     let (left, right, get) = if update.path[depth] == Direction::Left {
         let (left, get) = recur(left, depth + 1, update);
@@ -47,7 +55,7 @@ fn recur<Key, Value>(
 ) -> (Node<Key, Value>, Result<Option<Value>, Top<MapError>>)
 where
     Key: Field,
-    Value: Field,
+    Value: Field + Clone,
 {
     match (node, update) {
         (
@@ -115,7 +123,129 @@ pub(crate) fn apply<Key, Value>(
 ) -> (Node<Key, Value>, Result<Option<Value>, Top<MapError>>)
 where
     Key: Field,
-    Value: Field,
+    Value: Field + Clone,
 {
     recur(root, 0, update)
 }
+
+impl<Key, Value> Slot<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub fn new(update: Update<Key, Value>) -> Self {
+        Slot {
+            path: update.path,
+            action: Some(update.action),
+            previous: None,
+        }
+    }
+}
+
+// `slots` are sorted by `path`: those going right come first
+#[allow(clippy::type_complexity)]
+fn split<Key, Value>(
+    slots: &mut [Slot<Key, Value>],
+    depth: u8,
+) -> (&mut [Slot<Key, Value>], &mut [Slot<Key, Value>])
+where
+    Key: Field,
+    Value: Field,
+{
+    let partition = slots.partition_point(|slot| slot.path[depth] == Direction::Right);
+    let (right, left) = slots.split_at_mut(partition);
+    (left, right)
+}
+
+// Checks that no slot leads to a `Stub`
+fn reachable<Key, Value>(node: &Node<Key, Value>, depth: u8, slots: &mut [Slot<Key, Value>]) -> bool
+where
+    Key: Field,
+    Value: Field,
+{
+    if slots.is_empty() {
+        return true;
+    }
+
+    match node {
+        Node::Internal(internal) => {
+            let (left, right) = split(slots, depth);
+            reachable(internal.left(), depth + 1, left)
+                && reachable(internal.right(), depth + 1, right)
+        }
+        Node::Stub(_) => false,
+        _ => true,
+    }
+}
+
+fn recur_batch<Key, Value>(
+    node: Node<Key, Value>,
+    depth: u8,
+    slots: &mut [Slot<Key, Value>],
+) -> Node<Key, Value>
+where
+    Key: Field,
+    Value: Field + Clone,
+{
+    match slots {
+        [] => node,
+        [slot] => {
+            let update = Update {
+                path: slot.path,
+                action: slot.action.take().unwrap(),
+            };
+
+            let (node, previous) = recur(node, depth, update);
+            slot.previous = previous.unwrap(); // `reachable` excludes `Stub`s
+
+            node
+        }
+        slots => {
+            let (left, right) = match node {
+                Node::Internal(internal) => internal.children(),
+                Node::Leaf(leaf) => {
+                    if Path::from(leaf.key().digest())[depth] == Direction::Left {
+                        (Node::Leaf(leaf), Node::Empty)
+                    } else {
+                        (Node::Empty, Node::Leaf(leaf))
+                    }
+                }
+                Node::Empty => (Node::Empty, Node::Empty),
+                Node::Stub(_) => unreachable!(),
+            };
+
+            let (left_slots, right_slots) = split(slots, depth);
+
+            let (left, right) = rayon::join(
+                move || recur_batch(left, depth + 1, left_slots),
+                move || recur_batch(right, depth + 1, right_slots),
+            );
+
+            match (&left, &right) {
+                (Node::Empty, Node::Empty) => Node::Empty,
+                (Node::Leaf { .. }, Node::Empty) => left,
+                (Node::Empty, Node::Leaf { .. }) => right,
+                _ => Node::internal(left, right),
+            }
+        }
+    }
+}
+
+/// Applies all `slots` (sorted by `path`, without duplicates) to the tree
+/// rooted at `root`, filling in their previous values.
+///
+/// If any slot leads to a `Stub`, no slot is applied.
+pub(crate) fn apply_batch<Key, Value>(
+    root: Node<Key, Value>,
+    slots: &mut [Slot<Key, Value>],
+) -> (Node<Key, Value>, Result<(), Top<MapError>>)
+where
+    Key: Field,
+    Value: Field + Clone,
+{
+    if !reachable(&root, 0, slots) {
+        return (root, MapError::BranchUnknown.fail().spot(here!()));
+    }
+
+    (recur_batch(root, 0, slots), Ok(()))
+}
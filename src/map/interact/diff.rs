@@ -0,0 +1,46 @@
+use crate::{
+    common::{
+        data::Bytes,
+        store::Field,
+        tree::{Tree, Visit},
+    },
+    map::store::Node,
+};
+
+use std::marker::PhantomData;
+
+/// Walks the (possibly partial) tree of a `Map`: `Stub`s are `Unknown`.
+pub(crate) struct Walk<'a, Key: Field, Value: Field>(PhantomData<&'a Node<Key, Value>>);
+
+impl<'a, Key, Value> Default for Walk<'a, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn default() -> Self {
+        Walk(PhantomData)
+    }
+}
+
+impl<'a, Key, Value> Tree for Walk<'a, Key, Value>
+where
+    Key: Field + Clone,
+    Value: Field + Clone,
+{
+    type Ref = &'a Node<Key, Value>;
+    type Key = Key;
+    type Value = Value;
+
+    fn hash(&self, node: &&'a Node<Key, Value>) -> Bytes {
+        node.hash()
+    }
+
+    fn visit(&mut self, node: &'a Node<Key, Value>) -> Visit<&'a Node<Key, Value>, Key, Value> {
+        match node {
+            Node::Empty => Visit::Empty,
+            Node::Internal(internal) => Visit::Internal(internal.left(), internal.right()),
+            Node::Leaf(leaf) => Visit::Leaf(leaf.key().inner().clone(), leaf.value().inner().clone()),
+            Node::Stub(_) => Visit::Unknown,
+        }
+    }
+}
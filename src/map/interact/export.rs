@@ -11,7 +11,7 @@ use crate::{
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-fn split(paths: &[Path], depth: u8) -> (&[Path], &[Path]) {
+pub(crate) fn split(paths: &[Path], depth: u8) -> (&[Path], &[Path]) {
     let partition = paths.partition_point(|path| path[depth] == Direction::Right); // This is because `Direction::Right < Direction::Left`
 
     let right = &paths[..partition];
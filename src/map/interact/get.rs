@@ -1,6 +1,9 @@
 use crate::{
-    common::{store::Field, tree::Direction},
-    map::{errors::MapError, interact::Query, store::Node},
+    common::{
+        store::Field,
+        tree::{Direction, Path},
+    },
+    map::{errors::MapError, interact::Query, store::Node, UnknownBranch},
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
@@ -44,3 +47,42 @@ where
 {
     recur(root, 0, query)
 }
+
+/// Returns the `Stub` that prevents updating the key at `path`, if any: either
+/// the `Stub` met along `path` or, if the key is to be removed, a `Stub`
+/// sibling of its leaf (whose lifting cannot be decided).
+pub(crate) fn unknown_branch<Key, Value>(
+    root: &Node<Key, Value>,
+    path: Path,
+    remove: bool,
+) -> Option<UnknownBranch>
+where
+    Key: Field,
+    Value: Field,
+{
+    let mut node = root;
+    let mut sibling = None;
+    let mut depth = 0;
+
+    while let Node::Internal(internal) = node {
+        let (next, other) = if path[depth] == Direction::Left {
+            (internal.left(), internal.right())
+        } else {
+            (internal.right(), internal.left())
+        };
+
+        node = next;
+        sibling = Some(other);
+        depth += 1;
+    }
+
+    match (node, sibling) {
+        (Node::Stub(stub), _) => Some(UnknownBranch::new(depth, stub.hash().into())),
+        (Node::Leaf(leaf), Some(Node::Stub(stub)))
+            if remove && path.reaches(leaf.key().digest()) =>
+        {
+            Some(UnknownBranch::new(depth, stub.hash().into()))
+        }
+        _ => None,
+    }
+}
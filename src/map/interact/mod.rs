@@ -1,15 +1,21 @@
 mod action;
+mod algebra;
 mod apply;
+mod diff;
 mod export;
 mod get;
 mod import;
+mod prune;
 mod query;
 mod update;
 
-pub(crate) use apply::apply;
+pub(crate) use algebra::{difference, intersection, is_subset, union};
+pub(crate) use apply::{apply, apply_batch, Slot};
+pub(crate) use diff::Walk;
 pub(crate) use export::export;
-pub(crate) use get::get;
+pub(crate) use get::{get, unknown_branch};
 pub(crate) use import::import;
+pub(crate) use prune::{forget, retain};
 
 pub(crate) use action::Action;
 pub(crate) use query::Query;
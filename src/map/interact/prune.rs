@@ -0,0 +1,102 @@
+use crate::{
+    common::{store::Field, tree::Path},
+    map::{interact::export::split, store::Node},
+};
+
+// Replaces an `Internal` node with a `Stub` if its children are `Stub`s (or,
+// if `empty` is set, `Stub`s and `Empty`s)
+fn collapse<Key, Value>(node: &mut Node<Key, Value>, empty: bool)
+where
+    Key: Field,
+    Value: Field,
+{
+    let collapsible = match node {
+        Node::Internal(internal) => match (internal.left(), internal.right()) {
+            (Node::Stub(_), Node::Stub(_)) => true,
+            (Node::Stub(_), Node::Empty) | (Node::Empty, Node::Stub(_)) => empty,
+            _ => false,
+        },
+        _ => false,
+    };
+
+    if collapsible {
+        *node = Node::stub(node.hash());
+    }
+}
+
+fn forget_recur<Key, Value>(node: &mut Node<Key, Value>, depth: u8, paths: &[Path])
+where
+    Key: Field,
+    Value: Field,
+{
+    if paths.is_empty() {
+        return;
+    }
+
+    match node {
+        Node::Internal(internal) => {
+            let (left_paths, right_paths) = split(paths, depth);
+
+            if !left_paths.is_empty() {
+                forget_recur(internal.left_mut(), depth + 1, left_paths);
+            }
+
+            if !right_paths.is_empty() {
+                forget_recur(internal.right_mut(), depth + 1, right_paths);
+            }
+
+            collapse(node, true);
+        }
+        Node::Leaf(leaf) => {
+            // Leaves of other keys are proofs of exclusion for `paths`, and are kept
+            if paths.iter().any(|path| path.reaches(leaf.key().digest())) {
+                *node = Node::stub(leaf.hash());
+            }
+        }
+        Node::Empty | Node::Stub(_) => {}
+    }
+}
+
+fn retain_recur<Key, Value>(node: &mut Node<Key, Value>, depth: u8, paths: &[Path])
+where
+    Key: Field,
+    Value: Field,
+{
+    if node.is_empty() || node.is_stub() {
+        return;
+    }
+
+    if paths.is_empty() {
+        *node = Node::stub(node.hash());
+        return;
+    }
+
+    if let Node::Internal(internal) = node {
+        let (left_paths, right_paths) = split(paths, depth);
+
+        retain_recur(internal.left_mut(), depth + 1, left_paths);
+        retain_recur(internal.right_mut(), depth + 1, right_paths);
+
+        // `Empty`s along `paths` prove that keys are absent
+        collapse(node, false);
+    }
+}
+
+/// Replaces the leaves of the keys at `paths` (sorted) with `Stub`s, then
+/// collapses the `Internal` nodes left with only `Stub` and `Empty` children.
+pub(crate) fn forget<Key, Value>(root: &mut Node<Key, Value>, paths: &[Path])
+where
+    Key: Field,
+    Value: Field,
+{
+    forget_recur(root, 0, paths)
+}
+
+/// Replaces all branches not along `paths` (sorted) with `Stub`s.
+pub(crate) fn retain<Key, Value>(root: &mut Node<Key, Value>, paths: &[Path])
+where
+    Key: Field,
+    Value: Field,
+{
+    retain_recur(root, 0, paths)
+}
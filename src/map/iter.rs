@@ -0,0 +1,59 @@
+use crate::{common::store::Field, map::store::Node};
+
+/// An iterator over the known entries of a [`Map`], in path order (see
+/// [`Map::iter`]).
+///
+/// Branches of the map that are replaced by `Stub`s (e.g., in a map obtained by
+/// [`Map::export`]) cannot be iterated over, and are skipped: [`Iter::stubs`]
+/// counts the `Stub`s met so far.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::iter`]: crate::map::Map::iter
+/// [`Map::export`]: crate::map::Map::export
+pub struct Iter<'a, Key: Field, Value: Field> {
+    stack: Vec<&'a Node<Key, Value>>,
+    stubs: usize,
+}
+
+impl<'a, Key, Value> Iter<'a, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    pub(crate) fn new(root: &'a Node<Key, Value>) -> Self {
+        Iter {
+            stack: vec![root],
+            stubs: 0,
+        }
+    }
+
+    /// Returns the number of `Stub`s skipped so far. Once the iterator is
+    /// exhausted, this is zero if and only if all entries were yielded.
+    pub fn stubs(&self) -> usize {
+        self.stubs
+    }
+}
+
+impl<'a, Key, Value> Iterator for Iter<'a, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    type Item = (&'a Key, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                Node::Empty => {}
+                Node::Internal(internal) => {
+                    self.stack.push(internal.right());
+                    self.stack.push(internal.left());
+                }
+                Node::Leaf(leaf) => return Some((leaf.key().inner(), leaf.value().inner())),
+                Node::Stub(_) => self.stubs += 1,
+            }
+        }
+
+        None
+    }
+}
@@ -1,19 +1,29 @@
 use crate::{
-    common::{data::Bytes, store::Field, tree::Path},
+    common::{
+        data::Bytes,
+        store::Field,
+        tree::{self, Path},
+    },
     map::{
         errors::MapError,
-        interact::{self, Query, Update},
+        interact::{self, Action, Query, Slot, Update, Walk},
         store::{self, Node},
+        DeserializeSettings, Iter, UnknownBranch,
     },
 };
 
-use doomstack::{here, ResultExt, Top};
+use doomstack::{here, Doom, ResultExt, Top};
+
+use rayon::prelude::*;
 
-use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::{
     borrow::{Borrow, BorrowMut},
+    collections::HashMap,
     fmt::{Debug, Error, Formatter},
+    hash::Hash as StdHash,
+    iter::FromIterator,
 };
 
 use talk::{
@@ -109,6 +119,16 @@ use talk::{
 /// on serialization ([`Serialize`]) and recomputed on deserialization
 /// ([`Deserialize`]), thus ensuring that they are locally valid at all
 /// times, in spite of any prior malicious tampering that might have happened.
+/// Deserialization is bounded in depth (one level per bit of path) and in
+/// number of nodes (see [`Map::deserialize_with`]), so that maps can be
+/// safely received from untrusted peers.
+///
+/// # Structural sharing
+///
+/// Clones of a map share their nodes: cloning a map ([`Clone`]) is O(1),
+/// regardless of its size. Nodes are copied on write, i.e., modifying a map
+/// only copies the nodes along the path of the modification, leaving the
+/// rest of the tree shared with its clones.
 ///
 /// # One-to-one mapping of key-value pairs.
 ///
@@ -270,7 +290,10 @@ where
     /// assert_eq!(map.insert("Alice", 3).unwrap(), Some(2));
     /// assert_eq!(map.get(&"Alice").unwrap(), Some(&3));
     /// ```
-    pub fn insert(&mut self, key: Key, value: Value) -> Result<Option<Value>, Top<MapError>> {
+    pub fn insert(&mut self, key: Key, value: Value) -> Result<Option<Value>, Top<MapError>>
+    where
+        Value: Clone,
+    {
         let update = Update::insert(key, value).pot(MapError::HashError, here!())?;
         self.update(update)
     }
@@ -300,12 +323,207 @@ where
     /// assert_eq!(map.remove(&1).unwrap(), Some("a"));
     /// assert_eq!(map.remove(&1).unwrap(), None);
     /// ```
-    pub fn remove(&mut self, key: &Key) -> Result<Option<Value>, Top<MapError>> {
+    pub fn remove(&mut self, key: &Key) -> Result<Option<Value>, Top<MapError>>
+    where
+        Value: Clone,
+    {
         let update = Update::remove(key).pot(MapError::HashError, here!())?;
         self.update(update)
     }
 
-    fn update(&mut self, update: Update<Key, Value>) -> Result<Option<Value>, Top<MapError>> {
+    /// Applies a batch of updates to the map, returning, for each key, its value
+    /// before the batch. An update `(key, Some(value))` inserts `value` at `key`,
+    /// while `(key, None)` removes `key`. If a key is updated more than once,
+    /// its last update is applied.
+    ///
+    /// Updates are sorted by key path, then applied to the branches of the tree
+    /// in parallel. Building a large map with a single batch is much faster than
+    /// with repeated calls to [`insert`].
+    ///
+    /// [`insert`]: Map::insert
+    ///
+    /// # Errors
+    ///
+    /// If the `Key` or `Value` cannot be hashed (via `drop::crypto::hash`), [`HashError`] is returned.
+    ///
+    /// If the portion of the map pertaining to any of the keys is incomplete, i.e. there is
+    /// a `Stub` on the key's path, [`BranchUnknown`] is returned.
+    ///
+    /// In both cases, the map is left unchanged.
+    ///
+    /// [`HashError`]: errors/enum.MapError.html
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let mut map: Map<u32, u32> = Map::new();
+    /// map.insert(1, 10).unwrap();
+    ///
+    /// let previous = map
+    ///     .apply_batch((0..4).map(|key| (key, Some(key))).chain([(3, None)]))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(previous[&0], None);
+    /// assert_eq!(previous[&1], Some(10));
+    ///
+    /// assert_eq!(map.get(&1).unwrap(), Some(&1));
+    /// assert_eq!(map.get(&3).unwrap(), None);
+    /// ```
+    pub fn apply_batch<I>(&mut self, updates: I) -> Result<HashMap<Key, Option<Value>>, Top<MapError>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone,
+        I: IntoIterator<Item = (Key, Option<Value>)>,
+    {
+        let updates = Self::updates(updates)?;
+        Ok(self.batch(updates)?.into_iter().collect())
+    }
+
+    /// Applies the updates of a batch whose keys' branches are known, as
+    /// [`apply_batch`] would. Unlike [`apply_batch`], updates blocked by a
+    /// `Stub` do not fail the batch: they are skipped, and the `Stub` is
+    /// reported as an [`UnknownBranch`].
+    ///
+    /// Returns, for each update in order, either the value of its key before
+    /// the batch, or the [`UnknownBranch`] that blocked it.
+    ///
+    /// Since a `Map` can only [`import`] maps with a matching commitment, the
+    /// missing branches should be imported in a copy of the map taken before
+    /// the batch (cloning a `Map` is cheap), on which the batch is then retried.
+    ///
+    /// [`apply_batch`]: Map::apply_batch
+    /// [`import`]: Map::import
+    /// [`UnknownBranch`]: crate::map::UnknownBranch
+    ///
+    /// # Errors
+    ///
+    /// If the `Key` or `Value` cannot be hashed (via `drop::crypto::hash`), [`HashError`] is
+    /// returned, and the map is left unchanged.
+    ///
+    /// [`HashError`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let map: Map<u32, u32> = (0..256).map(|key| (key, key)).collect();
+    ///
+    /// let partial = map.export([0]).unwrap();
+    /// let mut attempt = partial.clone();
+    ///
+    /// let results = attempt.try_apply_batch([(0, Some(10)), (2, Some(12))]).unwrap();
+    ///
+    /// assert_eq!(results[0], Ok(Some(0)));
+    /// assert_eq!(attempt.get(&0).unwrap(), Some(&10));
+    ///
+    /// // Key 2 lies in a missing branch, which can be exported from `map`
+    /// assert!(results[1].is_err());
+    ///
+    /// let mut attempt = partial.clone();
+    /// attempt.import(map.export([2]).unwrap()).unwrap();
+    ///
+    /// let results = attempt.try_apply_batch([(0, Some(10)), (2, Some(12))]).unwrap();
+    /// assert_eq!(results, vec![Ok(Some(0)), Ok(Some(2))]);
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn try_apply_batch<I>(
+        &mut self,
+        updates: I,
+    ) -> Result<Vec<Result<Option<Value>, UnknownBranch>>, Top<MapError>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone,
+        I: IntoIterator<Item = (Key, Option<Value>)>,
+    {
+        let updates = Self::updates(updates)?;
+
+        let mut keys = Vec::with_capacity(updates.len());
+        let mut reachable = Vec::with_capacity(updates.len());
+
+        for (key, update) in updates {
+            let remove = matches!(update.action, Action::Remove);
+
+            match interact::unknown_branch(self.root(), update.path, remove) {
+                Some(branch) => keys.push(Err(branch)),
+                None => {
+                    keys.push(Ok(key.clone()));
+                    reachable.push((key, update));
+                }
+            }
+        }
+
+        let previous = self.batch(reachable)?.into_iter().collect::<HashMap<_, _>>();
+
+        Ok(keys
+            .into_iter()
+            .map(|key| key.map(|key| previous[&key].clone()))
+            .collect())
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn updates<I>(updates: I) -> Result<Vec<(Key, Update<Key, Value>)>, Top<MapError>>
+    where
+        Key: Clone,
+        I: IntoIterator<Item = (Key, Option<Value>)>,
+    {
+        updates
+            .into_iter()
+            .map(|(key, value)| {
+                let update = match value {
+                    Some(value) => Update::insert(key.clone(), value),
+                    None => Update::remove(&key),
+                }
+                .pot(MapError::HashError, here!())?;
+
+                Ok((key, update))
+            })
+            .collect()
+    }
+
+    // Applies `updates`, returning the previous value for each tag
+    fn batch<Tag>(
+        &mut self,
+        updates: Vec<(Tag, Update<Key, Value>)>,
+    ) -> Result<Vec<(Tag, Option<Value>)>, Top<MapError>>
+    where
+        Value: Clone,
+        Tag: Send,
+    {
+        let mut updates = updates.into_iter().enumerate().collect::<Vec<_>>();
+
+        // Among updates to the same key, the last comes first
+        updates.par_sort_unstable_by(|(lho_index, (_, lho)), (rho_index, (_, rho))| {
+            lho.path.cmp(&rho.path).then(rho_index.cmp(lho_index))
+        });
+
+        updates.dedup_by(|(_, (_, next)), (_, (_, first))| next.path == first.path);
+
+        let (tags, mut slots): (Vec<Tag>, Vec<Slot<Key, Value>>) = updates
+            .into_iter()
+            .map(|(_, (tag, update))| (tag, Slot::new(update)))
+            .unzip();
+
+        let root = self.root.take();
+        let (root, result) = interact::apply_batch(root, &mut slots);
+        self.root.restore(root);
+
+        result?;
+
+        Ok(tags
+            .into_iter()
+            .zip(slots)
+            .map(|(tag, slot)| (tag, slot.previous))
+            .collect())
+    }
+
+    fn update(&mut self, update: Update<Key, Value>) -> Result<Option<Value>, Top<MapError>>
+    where
+        Value: Clone,
+    {
         let root = self.root.take();
         let (root, result) = interact::apply(root, update);
         self.root.restore(root);
@@ -356,18 +574,7 @@ where
         I: IntoIterator<Item = K>,
         K: Borrow<Key>,
     {
-        let paths: Result<Vec<Path>, Top<MapError>> = keys
-            .into_iter()
-            .map(|key| {
-                hash::hash(key.borrow())
-                    .map(|digest| Path::from(Bytes::from(digest)))
-                    .pot(MapError::HashError, here!())
-            })
-            .collect();
-
-        let mut paths = paths?;
-        paths.sort();
-
+        let paths = Self::paths(keys)?;
         let root = interact::export(self.root.borrow(), &paths)?;
 
         Ok(Map {
@@ -420,6 +627,272 @@ where
     pub fn import(&mut self, mut other: Map<Key, Value>) -> Result<(), Top<MapError>> {
         interact::import(self.root.borrow_mut(), other.root.take())
     }
+
+    /// Replaces the branches of the given keys with `Stub`s, shrinking the map
+    /// without affecting its commitment. This is the inverse of [`import`]:
+    /// keys whose branch is already a `Stub` are ignored. Entries of other keys
+    /// are kept, but empty branches left next to forgotten ones are forgotten too.
+    ///
+    /// [`import`]: Map::import
+    ///
+    /// # Errors
+    ///
+    /// If a key cannot be hashed, [`HashError`] is returned, and the map is left unchanged.
+    ///
+    /// [`HashError`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let mut map: Map<u32, u32> = (0..4).map(|key| (key, key)).collect();
+    /// let commit = map.commit();
+    ///
+    /// map.forget([0, 1]).unwrap();
+    ///
+    /// assert_eq!(map.commit(), commit);
+    /// assert!(map.get(&0).is_err());
+    /// assert_eq!(map.get(&2).unwrap(), Some(&2));
+    /// ```
+    pub fn forget<I, K>(&mut self, keys: I) -> Result<(), Top<MapError>>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+    {
+        let paths = Self::paths(keys)?;
+        interact::forget(self.root.borrow_mut(), &paths);
+
+        Ok(())
+    }
+
+    /// Replaces all branches except those of the given keys with `Stub`s, as
+    /// [`export`] would, shrinking the map without affecting its commitment.
+    /// Keys whose branch is already a `Stub` are ignored.
+    ///
+    /// [`export`]: Map::export
+    ///
+    /// # Errors
+    ///
+    /// If a key cannot be hashed, [`HashError`] is returned, and the map is left unchanged.
+    ///
+    /// [`HashError`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let mut map: Map<u32, u32> = (0..4).map(|key| (key, key)).collect();
+    /// let commit = map.commit();
+    ///
+    /// map.retain_only([0]).unwrap();
+    ///
+    /// assert_eq!(map.commit(), commit);
+    /// assert_eq!(map.get(&0).unwrap(), Some(&0));
+    /// assert_eq!(map.known_len(), 1);
+    /// ```
+    pub fn retain_only<I, K>(&mut self, keys: I) -> Result<(), Top<MapError>>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+    {
+        let paths = Self::paths(keys)?;
+        interact::retain(self.root.borrow_mut(), &paths);
+
+        Ok(())
+    }
+
+    // Returns the (sorted) paths of `keys`
+    fn paths<I, K>(keys: I) -> Result<Vec<Path>, Top<MapError>>
+    where
+        I: IntoIterator<Item = K>,
+        K: Borrow<Key>,
+    {
+        let paths: Result<Vec<Path>, Top<MapError>> = keys
+            .into_iter()
+            .map(|key| {
+                hash::hash(key.borrow())
+                    .map(|digest| Path::from(Bytes::from(digest)))
+                    .pot(MapError::HashError, here!())
+            })
+            .collect();
+
+        let mut paths = paths?;
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    /// Returns, for each key whose value differs between `lho` and `rho`, its
+    /// values in `lho` and `rho` (`None` if absent). Branches whose hashes
+    /// match are not visited.
+    ///
+    /// # Errors
+    ///
+    /// If a branch that differs between `lho` and `rho` is missing (i.e., replaced
+    /// by a `Stub`) in either map, [`BranchUnknown`] is returned.
+    ///
+    /// [`BranchUnknown`]: errors/enum.MapError.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let mut lho: Map<u32, u32> = Map::new();
+    /// lho.insert(1, 10).unwrap();
+    /// lho.insert(2, 20).unwrap();
+    ///
+    /// let mut rho = lho.clone();
+    /// rho.insert(2, 21).unwrap();
+    /// rho.insert(3, 30).unwrap();
+    ///
+    /// let diff = Map::diff(&lho, &rho).unwrap();
+    ///
+    /// assert_eq!(diff.len(), 2);
+    /// assert_eq!(diff[&2], (Some(20), Some(21)));
+    /// assert_eq!(diff[&3], (None, Some(30)));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn diff(
+        lho: &Map<Key, Value>,
+        rho: &Map<Key, Value>,
+    ) -> Result<HashMap<Key, (Option<Value>, Option<Value>)>, Top<MapError>>
+    where
+        Key: Clone + Eq + StdHash,
+        Value: Clone + Eq,
+    {
+        match tree::diff(
+            &mut Walk::default(),
+            lho.root(),
+            &mut Walk::default(),
+            rho.root(),
+        ) {
+            Some(diff) => Ok(diff),
+            None => MapError::BranchUnknown.fail().spot(here!()),
+        }
+    }
+
+    /// Returns an iterator over the known entries of the map, in path order.
+    ///
+    /// Branches replaced by `Stub`s are skipped (see [`Iter::stubs`]).
+    ///
+    /// [`Iter::stubs`]: crate::map::Iter::stubs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let map: Map<u32, u32> = (0..4).map(|key| (key, key + 1)).collect();
+    ///
+    /// let mut entries = map.iter().map(|(key, value)| (*key, *value)).collect::<Vec<_>>();
+    /// entries.sort();
+    ///
+    /// assert_eq!(entries, vec![(0, 1), (1, 2), (2, 3), (3, 4)]);
+    ///
+    /// let export = map.export([0, 1]).unwrap();
+    /// let mut iter = export.iter();
+    ///
+    /// assert_eq!(iter.by_ref().count(), 2);
+    /// assert!(iter.stubs() > 0);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, Key, Value> {
+        Iter::new(self.root.borrow())
+    }
+
+    /// Returns an iterator over the known keys of the map, in path order.
+    pub fn keys(&self) -> impl Iterator<Item = &Key> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Returns an iterator over the known values of the map, in path order.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Returns the number of known entries in the map. Entries in branches
+    /// replaced by `Stub`s are not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Map;
+    ///
+    /// let map: Map<u32, u32> = (0..4).map(|key| (key, key)).collect();
+    /// assert_eq!(map.known_len(), 4);
+    ///
+    /// let export = map.export([0]).unwrap();
+    /// assert_eq!(export.known_len(), 1);
+    /// assert!(!export.is_complete());
+    /// ```
+    pub fn known_len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if the map is known to contain no entries.
+    pub fn is_empty(&self) -> bool {
+        self.root().is_empty()
+    }
+
+    /// Returns `true` if no branch of the map is replaced by a `Stub`, i.e., if
+    /// [`known_len`] is the number of entries in the map.
+    ///
+    /// [`known_len`]: Map::known_len
+    pub fn is_complete(&self) -> bool {
+        let mut iter = self.iter();
+        iter.by_ref().for_each(|_| ());
+        iter.stubs() == 0
+    }
+
+    /// Deserializes a `Map` (see [`Deserialize`]) within the limits of
+    /// `settings`. Encodings exceeding those limits (e.g., encodings received
+    /// from an untrusted peer, holding an overly deep chain of nodes) are
+    /// rejected with [`LimitExceeded`], without being deserialized further.
+    ///
+    /// [`Deserialize`]: https://docs.serde.rs/serde/trait.Deserialize.html
+    /// [`LimitExceeded`]: crate::map::errors::DeserializeError::LimitExceeded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bincode::Options;
+    /// use tenaciouszebra::map::{DeserializeSettings, Map};
+    ///
+    /// let map: Map<u32, u32> = (0..16).map(|key| (key, key)).collect();
+    /// let serialized = bincode::options().serialize(&map).unwrap();
+    ///
+    /// let settings = DeserializeSettings::default();
+    /// let mut deserializer = bincode::Deserializer::from_slice(&serialized, bincode::options());
+    /// let deserialized = Map::<u32, u32>::deserialize_with(&mut deserializer, &settings).unwrap();
+    /// assert_eq!(deserialized.commit(), map.commit());
+    ///
+    /// let settings = DeserializeSettings {
+    ///     max_nodes: 8,
+    ///     ..Default::default()
+    /// };
+    /// let mut deserializer = bincode::Deserializer::from_slice(&serialized, bincode::options());
+    /// assert!(Map::<u32, u32>::deserialize_with(&mut deserializer, &settings).is_err());
+    /// ```
+    pub fn deserialize_with<'de, D>(
+        deserializer: D,
+        settings: &DeserializeSettings,
+    ) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Deserializes, computes node hashes and checks correctness of tree topology
+        let root = store::deserialize::deserialize(deserializer, settings)?;
+
+        Ok(Map {
+            root: Lender::new(root),
+        }) // If a `Map` is `Deserialize`d, then it is correct
+    }
+
+    pub(crate) fn root(&self) -> &Node<Key, Value> {
+        self.root.borrow()
+    }
 }
 
 impl<Key, Value> Debug for Map<Key, Value>
@@ -434,8 +907,8 @@ where
 
 impl<Key, Value> Clone for Map<Key, Value>
 where
-    Key: Field + Clone,
-    Value: Field + Clone,
+    Key: Field,
+    Value: Field,
 {
     fn clone(&self) -> Self {
         let root: &Node<Key, Value> = self.root.borrow();
@@ -443,6 +916,61 @@ where
     }
 }
 
+impl<'a, Key, Value> IntoIterator for &'a Map<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    type Item = (&'a Key, &'a Value);
+    type IntoIter = Iter<'a, Key, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Inserts all entries with a single [`Map::apply_batch`].
+///
+/// # Panics
+///
+/// Panics if a `Key` or `Value` cannot be hashed, or if the branch of a key is
+/// replaced by a `Stub`.
+impl<Key, Value> Extend<(Key, Value)> for Map<Key, Value>
+where
+    Key: Field,
+    Value: Field + Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (Key, Value)>,
+    {
+        let updates = iter
+            .into_iter()
+            .map(|(key, value)| ((), Update::insert(key, value).unwrap()))
+            .collect();
+
+        self.batch(updates).unwrap();
+    }
+}
+
+/// # Panics
+///
+/// Panics if a `Key` or `Value` cannot be hashed.
+impl<Key, Value> FromIterator<(Key, Value)> for Map<Key, Value>
+where
+    Key: Field,
+    Value: Field + Clone,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (Key, Value)>,
+    {
+        let mut map = Map::new();
+        map.extend(iter);
+        map
+    }
+}
+
 impl<Key, Value> Serialize for Map<Key, Value>
 where
     Key: Field,
@@ -465,14 +993,7 @@ where
     where
         D: Deserializer<'de>,
     {
-        let root = Node::deserialize(deserializer)?; // Deserializes and computes node hashes
-
-        store::check(&root) // Checks correctness of tree topology
-            .map_err(DeError::custom)?;
-
-        Ok(Map {
-            root: Lender::new(root),
-        }) // If a `Map` is `Deserialize`d, then it is correct
+        Map::deserialize_with(deserializer, &DeserializeSettings::default())
     }
 }
 
@@ -485,12 +1006,19 @@ mod tests {
         map::store::{self, Internal},
     };
 
+    use bincode::Options;
+
     use std::{
         collections::{HashMap, HashSet},
         fmt::Debug,
         hash::Hash,
+        ptr, thread,
     };
 
+    // Half the default stack of a thread, too small to read 255 levels of
+    // `Internal` nodes (at least in debug builds)
+    const STACK_SIZE: usize = 1 << 20;
+
     impl<Key, Value> Map<Key, Value>
     where
         Key: Field,
@@ -1228,4 +1756,544 @@ mod tests {
         let serialized = bincode::serialize(&original).unwrap();
         assert!(bincode::deserialize::<Map<u32, u32>>(&serialized).is_err());
     }
+
+    // With `bincode`'s default options, a `Map` is encoded as a `u64` number of
+    // nodes, followed by the nodes in pre-order, each starting with a `u32` variant
+    // index (`Empty` is 0, `Internal` is 1)
+
+    #[test]
+    fn serialize_deep_chain() {
+        // A chain of `Internal` nodes, deeper than any path
+        let mut serialized = Vec::new();
+        for _ in 0..(1 << 20) {
+            serialized.extend_from_slice(&1u32.to_le_bytes());
+        }
+
+        assert!(bincode::deserialize::<Map<u32, u32>>(&serialized).is_err());
+    }
+
+    #[test]
+    fn serialize_max_nodes() {
+        let original: Map<u32, u32> = (0..1024).map(|i| (i, i)).collect();
+        let serialized = bincode::serialize(&original).unwrap();
+
+        let deserialize = |max_nodes| {
+            let options = bincode::DefaultOptions::new().with_fixint_encoding();
+            let mut deserializer = bincode::Deserializer::from_slice(&serialized, options);
+
+            let settings = DeserializeSettings {
+                max_nodes,
+                ..Default::default()
+            };
+            Map::<u32, u32>::deserialize_with(&mut deserializer, &settings)
+        };
+
+        assert!(deserialize(1024).is_err());
+
+        let deserialized = deserialize(DeserializeSettings::default().max_nodes).unwrap();
+        assert_eq!(original.commit(), deserialized.commit());
+        deserialized.check_tree();
+    }
+
+    // A chain of `Internal` nodes, each with an `Empty` right child, ending
+    // with two `Stub`s at depth `depth`
+    fn deep_chain(depth: u8) -> Node<u32, u32> {
+        let stub = || Node::stub(hash::leaf(hash::empty(), hash::empty()));
+        let mut node = Node::internal(stub(), stub());
+
+        for _ in 1..depth {
+            node = Node::internal(node, Node::Empty);
+        }
+
+        node
+    }
+
+    #[test]
+    fn serialize_max_depth() {
+        let deserialize = |serialized: &[u8], max_depth| {
+            let mut deserializer =
+                bincode::Deserializer::from_slice(serialized, bincode::DefaultOptions::new());
+
+            let settings = DeserializeSettings {
+                max_depth,
+                ..Default::default()
+            };
+
+            Map::<u32, u32>::deserialize_with(&mut deserializer, &settings)
+        };
+
+        let serialized = bincode::options().serialize(&deep_chain(16)).unwrap();
+
+        assert!(deserialize(&serialized, 15).is_err());
+        assert!(deserialize(&serialized, 16).is_ok());
+    }
+
+    #[test]
+    fn serialize_max_depth_small_stack() {
+        let default = DeserializeSettings::default().max_depth;
+
+        let accepted = bincode::serialize(&deep_chain(default)).unwrap();
+        let rejected = bincode::serialize(&deep_chain(u8::MAX)).unwrap();
+
+        // The stack used by deserialization is bounded by `max_depth`, not
+        // by the depth of the encoding
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let map = bincode::deserialize::<Map<u32, u32>>(&accepted).unwrap();
+                assert_eq!(map.root().hash(), deep_chain(default).hash());
+
+                assert!(bincode::deserialize::<Map<u32, u32>>(&rejected).is_err());
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn serialize_truncated() {
+        let original: Map<u32, u32> = (0..16).map(|i| (i, i)).collect();
+        let serialized = bincode::serialize(&original).unwrap();
+
+        let truncated = &serialized[..serialized.len() - 1];
+        assert!(bincode::deserialize::<Map<u32, u32>>(truncated).is_err());
+    }
+
+    #[test]
+    fn serialize_encoding() {
+        let original: Map<u32, u32> = (0..256).map(|i| (i, i)).collect();
+
+        // The encoding is that of the nodes of the map
+        let serialized = bincode::serialize(&original).unwrap();
+        assert_eq!(serialized, bincode::serialize(original.root()).unwrap());
+
+        let node = bincode::deserialize::<Node<u32, u32>>(&serialized).unwrap();
+        assert_eq!(node.hash(), original.root().hash());
+
+        // Self-describing formats encode `Internal` nodes as maps
+        let serialized = serde_json::to_string(&original).unwrap();
+        let deserialized: Map<u32, u32> = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized.commit(), original.commit());
+        deserialized.check_tree();
+    }
+
+    #[test]
+    fn diff() {
+        let mut lho = Map::new();
+        let mut rho = Map::new();
+
+        for key in 0..1024 {
+            lho.insert(key, key).unwrap();
+        }
+
+        for key in 512..1536 {
+            let value = if key < 768 { key + 1 } else { key };
+            rho.insert(key, value).unwrap();
+        }
+
+        let diff = Map::diff(&lho, &rho).unwrap();
+        assert_eq!(diff.len(), 1280);
+
+        for key in 0..1536 {
+            if key < 512 {
+                assert_eq!(diff[&key], (Some(key), None));
+            } else if key < 768 {
+                assert_eq!(diff[&key], (Some(key), Some(key + 1)));
+            } else if key < 1024 {
+                assert_eq!(diff.get(&key), None);
+            } else {
+                assert_eq!(diff[&key], (None, Some(key)));
+            }
+        }
+
+        assert!(Map::diff(&lho, &lho).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_stub() {
+        let mut lho = Map::new();
+
+        for key in 0..256 {
+            lho.insert(key, key).unwrap();
+        }
+
+        let mut rho = lho.clone();
+        rho.insert(0, 1).unwrap();
+
+        let lho_export = lho.export([&0]).unwrap();
+        let rho_export = rho.export([&0]).unwrap();
+
+        let diff = Map::diff(&lho_export, &rho_export).unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[&0], (Some(0), Some(1)));
+
+        let rho_export = rho.export([&1]).unwrap();
+        assert!(Map::diff(&lho_export, &rho_export).is_err());
+    }
+
+    #[test]
+    fn apply_batch() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        let previous = map
+            .apply_batch((0..1024).map(|key| (key, Some(key))))
+            .unwrap();
+
+        assert_eq!(previous.len(), 1024);
+        assert!(previous.values().all(Option::is_none));
+
+        map.check_tree();
+        map.assert_records((0..1024).map(|key| (key, key)));
+
+        let mut reference: Map<u32, u32> = Map::new();
+        for key in 0..1024 {
+            reference.insert(key, key).unwrap();
+        }
+
+        assert_eq!(map.commit(), reference.commit());
+
+        // Overwrite 0..256, remove 256..768, insert 1024..1280
+        let previous = map
+            .apply_batch(
+                (0..256)
+                    .map(|key| (key, Some(key + 1)))
+                    .chain((256..768).map(|key| (key, None)))
+                    .chain((1024..1280).map(|key| (key, Some(key)))),
+            )
+            .unwrap();
+
+        for key in 0..1280 {
+            if key < 768 {
+                assert_eq!(previous[&key], Some(key));
+            } else if key < 1024 {
+                assert!(!previous.contains_key(&key));
+            } else {
+                assert_eq!(previous[&key], None);
+            }
+        }
+
+        map.check_tree();
+        map.assert_records(
+            (0..256)
+                .map(|key| (key, key + 1))
+                .chain((768..1280).map(|key| (key, key))),
+        );
+
+        let previous = map.apply_batch((0..1280).map(|key| (key, None))).unwrap();
+        assert_eq!(previous.values().filter(|value| value.is_some()).count(), 768);
+
+        map.check_tree();
+        assert_eq!(map.commit(), Map::<u32, u32>::new().commit());
+    }
+
+    #[test]
+    fn apply_batch_duplicates() {
+        let mut map: Map<u32, u32> = Map::new();
+        map.insert(0, 0).unwrap();
+
+        let previous = map
+            .apply_batch([(0, Some(1)), (1, Some(1)), (0, None), (1, Some(2)), (2, None)])
+            .unwrap();
+
+        assert_eq!(previous.len(), 3);
+        assert_eq!(previous[&0], Some(0));
+        assert_eq!(previous[&1], None);
+        assert_eq!(previous[&2], None);
+
+        map.check_tree();
+        map.assert_records([(1, 2)]);
+    }
+
+    #[test]
+    fn apply_batch_stub() {
+        let mut map: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            map.insert(key, key).unwrap();
+        }
+
+        let mut export = map.export([0, 1]).unwrap();
+        let commit = export.commit();
+
+        let previous = export
+            .apply_batch([(0, Some(1)), (1, None)])
+            .unwrap();
+
+        assert_eq!(previous[&0], Some(0));
+        assert_eq!(previous[&1], Some(1));
+        export.assert_records([(0, 1)]);
+
+        let commit_after = export.commit();
+        assert_ne!(commit_after, commit);
+
+        // One of the keys is behind a stub: no update is applied
+        assert!(export.apply_batch([(0, Some(2)), (2, Some(2))]).is_err());
+        assert_eq!(export.commit(), commit_after);
+        assert_eq!(export.get(&0).unwrap(), Some(&1));
+    }
+
+    #[test]
+    fn try_apply_batch() {
+        let map: Map<u32, u32> = (0..256).map(|key| (key, key)).collect();
+        let original = map.export([0]).unwrap();
+        let mut partial = original.clone();
+
+        let updates = (0..8).map(|key| (key, if key == 0 { None } else { Some(key + 1) }));
+
+        let mut attempt = original.clone();
+        let results = attempt.try_apply_batch(updates.clone()).unwrap();
+
+        assert_eq!(results.len(), 8);
+
+        // The sibling of the leaf of key 0 is not exported: removing key 0
+        // could require lifting it
+        assert!(results[0].is_err());
+        assert_eq!(attempt.get(&0).unwrap(), Some(&0));
+
+        let path = |key: u32| Path::from(talk::crypto::primitives::hash::hash(&key).unwrap());
+
+        for (key, result) in (0..8).zip(results) {
+            match result {
+                Ok(previous) => {
+                    assert_eq!(previous, Some(key));
+                    assert_eq!(attempt.get(&key).unwrap(), Some(&(key + 1)));
+                }
+                Err(branch) => {
+                    assert_eq!(
+                        interact::unknown_branch(original.root(), path(key), key == 0),
+                        Some(branch)
+                    );
+
+                    // Exporting the keys that cross the branch fills it in
+                    let crossing = (0..256).filter(|other| {
+                        interact::unknown_branch(original.root(), path(*other), false)
+                            == Some(branch)
+                    });
+
+                    partial.import(map.export(crossing).unwrap()).unwrap();
+                }
+            }
+        }
+
+        // Once all branches are known, the whole batch goes through
+        let results = partial.try_apply_batch(updates.clone()).unwrap();
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(partial.get(&0).unwrap(), None);
+
+        let mut reference = map.clone();
+        reference.apply_batch(updates).unwrap();
+        assert_eq!(partial.commit(), reference.commit());
+    }
+
+    #[test]
+    fn clone_shares_nodes() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        for key in 0..1024 {
+            original.insert(key, key).unwrap();
+        }
+
+        let mut clone = original.clone();
+        clone.insert(0, 1).unwrap();
+        clone.insert(1024, 1024).unwrap();
+
+        original.check_tree();
+        clone.check_tree();
+
+        original.assert_records((0..1024).map(|key| (key, key)));
+        clone.assert_records((0..1025).map(|key| (key, if key == 0 { 1 } else { key })));
+
+        // Only the paths to keys 0 and 1024 were copied
+        fn count(lho: &Node<u32, u32>, rho: &Node<u32, u32>) -> (usize, usize) {
+            match (lho, rho) {
+                (Node::Internal(lho), Node::Internal(rho)) => {
+                    let (left_shared, left_copied) = if ptr::eq(lho.left(), rho.left()) {
+                        (1, 0)
+                    } else {
+                        count(lho.left(), rho.left())
+                    };
+
+                    let (right_shared, right_copied) = if ptr::eq(lho.right(), rho.right()) {
+                        (1, 0)
+                    } else {
+                        count(lho.right(), rho.right())
+                    };
+
+                    (left_shared + right_shared, left_copied + right_copied + 1)
+                }
+                _ => (0, 1),
+            }
+        }
+
+        let (shared, copied) = count(original.root.borrow(), clone.root.borrow());
+
+        assert!(shared > 0);
+        assert!(copied < 64);
+    }
+
+    #[test]
+    fn clone_then_modify() {
+        let mut original: Map<u32, u32> = Map::new();
+
+        for key in 0..256 {
+            original.insert(key, key).unwrap();
+        }
+
+        let snapshot = original.clone();
+
+        for key in 0..128 {
+            original.remove(&key).unwrap();
+        }
+
+        for key in 128..256 {
+            assert_eq!(original.insert(key, key + 1).unwrap(), Some(key));
+        }
+
+        original.check_tree();
+        snapshot.check_tree();
+
+        original.assert_records((128..256).map(|key| (key, key + 1)));
+        snapshot.assert_records((0..256).map(|key| (key, key)));
+    }
+
+    #[test]
+    fn iter() {
+        let map: Map<u32, u32> = (0..1024).map(|key| (key, key + 1)).collect();
+
+        map.check_tree();
+        map.assert_records((0..1024).map(|key| (key, key + 1)));
+
+        assert_eq!(map.known_len(), 1024);
+        assert!(map.is_complete());
+        assert!(!map.is_empty());
+
+        let entries = map.iter().collect::<Vec<_>>();
+        assert!(entries.iter().all(|(key, value)| **value == **key + 1));
+
+        // Path order: `Direction::Left` (bit 1) comes first
+        let paths = entries
+            .iter()
+            .map(|(key, _)| Path::from(talk::crypto::primitives::hash::hash(*key).unwrap()))
+            .collect::<Vec<_>>();
+
+        assert!(paths.windows(2).all(|window| window[0] > window[1]));
+
+        assert_eq!(map.keys().copied().collect::<HashSet<_>>(), (0..1024).collect());
+        assert_eq!(map.values().copied().collect::<HashSet<_>>(), (1..1025).collect());
+        assert_eq!((&map).into_iter().count(), 1024);
+    }
+
+    #[test]
+    fn iter_stubs() {
+        let empty: Map<u32, u32> = Map::new();
+
+        assert!(empty.is_empty());
+        assert!(empty.is_complete());
+        assert_eq!(empty.known_len(), 0);
+
+        let stub: Map<u32, u32> = Map::root_stub(empty.commit());
+
+        assert!(!stub.is_empty());
+        assert!(!stub.is_complete());
+        assert_eq!(stub.known_len(), 0);
+
+        let map: Map<u32, u32> = (0..256).map(|key| (key, key)).collect();
+        let export = map.export([0, 1, 2]).unwrap();
+
+        let mut iter = export.iter();
+        let keys = iter.by_ref().map(|(key, _)| *key).collect::<HashSet<_>>();
+
+        assert!(iter.stubs() > 0);
+        assert!(keys.is_superset(&HashSet::from([0, 1, 2])));
+        assert_eq!(export.known_len(), keys.len());
+        assert!(!export.is_complete());
+    }
+
+    #[test]
+    fn extend() {
+        let mut map: Map<u32, u32> = (0..512).map(|key| (key, key)).collect();
+        map.extend((256..1024).map(|key| (key, key + 1)));
+
+        map.check_tree();
+        map.assert_records((0..1024).map(|key| (key, if key < 256 { key } else { key + 1 })));
+
+        let mut reference: Map<u32, u32> = Map::new();
+        for key in 0..1024 {
+            reference.insert(key, if key < 256 { key } else { key + 1 }).unwrap();
+        }
+
+        assert_eq!(map.commit(), reference.commit());
+    }
+
+    #[test]
+    fn forget() {
+        let mut map: Map<u32, u32> = (0..1024).map(|key| (key, key)).collect();
+        let original = map.clone();
+        let commit = map.commit();
+
+        map.forget(0..512).unwrap();
+
+        map.check_tree();
+        assert_eq!(map.commit(), commit);
+
+        for key in 0..512 {
+            assert!(map.get(&key).is_err());
+        }
+
+        for key in 512..1024 {
+            assert_eq!(map.get(&key).unwrap(), Some(&key));
+        }
+
+        assert_eq!(map.known_len(), 512);
+
+        map.forget([1024, 1025]).unwrap();
+        assert_eq!(map.commit(), commit);
+        assert_eq!(map.known_len(), 512);
+
+        // `original` shares its nodes with `map`, but is not affected
+        original.check_tree();
+        original.assert_records((0..1024).map(|key| (key, key)));
+
+        map.import(original.export(0..512).unwrap()).unwrap();
+        map.assert_records((0..1024).map(|key| (key, key)));
+        assert!(map.is_complete());
+
+        map.forget(0..1024).unwrap();
+        assert!(map.root().is_stub());
+        assert_eq!(map.commit(), commit);
+    }
+
+    #[test]
+    fn retain_only() {
+        let mut map: Map<u32, u32> = (0..1024).map(|key| (key, key)).collect();
+        let commit = map.commit();
+
+        let export = map.export(0..16).unwrap();
+        map.retain_only(0..16).unwrap();
+
+        map.check_tree();
+        assert_eq!(map.commit(), commit);
+        assert_eq!(map.known_len(), export.known_len());
+        assert_eq!(
+            map.keys().collect::<HashSet<_>>(),
+            export.keys().collect::<HashSet<_>>()
+        );
+
+        for key in 0..16 {
+            assert_eq!(map.get(&key).unwrap(), Some(&key));
+        }
+
+        // Keys behind `Stub`s are ignored
+        map.retain_only(0..1024).unwrap();
+        assert_eq!(map.known_len(), export.known_len());
+
+        map.retain_only([0]).unwrap();
+        assert_eq!(map.get(&0).unwrap(), Some(&0));
+        assert!(map.known_len() < export.known_len());
+
+        map.retain_only(Vec::<u32>::new()).unwrap();
+        assert!(map.root().is_stub());
+        assert_eq!(map.commit(), commit);
+    }
 }
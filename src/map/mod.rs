@@ -1,13 +1,19 @@
 #![allow(dead_code)] // TODO: Remove this attribute, make sure there is no dead code.
 
-mod interact;
+pub(crate) mod interact;
 
+mod deserialize_settings;
+mod iter;
 mod map_impl;
 mod set;
+mod unknown_branch;
 
 pub(crate) mod store;
 
 pub mod errors;
 
+pub use deserialize_settings::DeserializeSettings;
+pub use iter::Iter;
 pub use map_impl::Map;
 pub use set::Set;
+pub use unknown_branch::UnknownBranch;
@@ -1,6 +1,6 @@
 use crate::{
     common::store::Field,
-    map::{errors::MapError, Map},
+    map::{errors::MapError, interact, Map},
 };
 
 use doomstack::Top;
@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     borrow::Borrow,
     fmt::{Debug, Error, Formatter},
+    iter::FromIterator,
 };
 
 use talk::crypto::primitives::hash::Hash;
@@ -69,6 +70,98 @@ where
     pub fn import(&mut self, other: Set<Item>) -> Result<(), Top<MapError>> {
         self.0.import(other.0)
     }
+
+    /// Returns an iterator over the known items of the set, in path order
+    /// (see [`Map::iter`]).
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self.0.keys()
+    }
+
+    /// Returns the number of known items in the set (see [`Map::known_len`]).
+    pub fn known_len(&self) -> usize {
+        self.0.known_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.0.is_complete()
+    }
+
+    /// Returns the set of items held by `self` or `other`.
+    ///
+    /// Subtrees whose commitments match in `self` and `other` are
+    /// shared by the result as they are, without visiting their items.
+    /// If a branch that differs between `self` and `other` is replaced by a
+    /// `Stub` in either set, [`BranchUnknown`] is returned. The same holds
+    /// for [`Set::intersection`], [`Set::difference`] and [`Set::is_subset`].
+    ///
+    /// [`BranchUnknown`]: crate::map::errors::MapError::BranchUnknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tenaciouszebra::map::Set;
+    ///
+    /// let lho: Set<u32> = (0..4).collect();
+    /// let rho: Set<u32> = (2..6).collect();
+    ///
+    /// let union = lho.union(&rho).unwrap();
+    ///
+    /// assert_eq!(union.commit(), (0..6).collect::<Set<u32>>().commit());
+    /// assert!(lho.is_subset(&union).unwrap());
+    /// ```
+    pub fn union(&self, other: &Set<Item>) -> Result<Set<Item>, Top<MapError>> {
+        interact::union(self.0.root(), other.0.root()).map(|root| Set(Map::raw(root)))
+    }
+
+    /// Returns the set of items held by both `self` and `other`.
+    pub fn intersection(&self, other: &Set<Item>) -> Result<Set<Item>, Top<MapError>> {
+        interact::intersection(self.0.root(), other.0.root()).map(|root| Set(Map::raw(root)))
+    }
+
+    /// Returns the set of items held by `self` but not by `other`.
+    pub fn difference(&self, other: &Set<Item>) -> Result<Set<Item>, Top<MapError>> {
+        interact::difference(self.0.root(), other.0.root()).map(|root| Set(Map::raw(root)))
+    }
+
+    /// Returns `true` if every item held by `self` is also held by `other`.
+    pub fn is_subset(&self, other: &Set<Item>) -> Result<bool, Top<MapError>> {
+        interact::is_subset(self.0.root(), other.0.root())
+    }
+}
+
+/// # Panics
+///
+/// Panics if an `Item` cannot be hashed, or if the branch of an item is
+/// replaced by a `Stub`.
+impl<Item> Extend<Item> for Set<Item>
+where
+    Item: Field,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Item>,
+    {
+        self.0.extend(iter.into_iter().map(|item| (item, ())));
+    }
+}
+
+/// # Panics
+///
+/// Panics if an `Item` cannot be hashed.
+impl<Item> FromIterator<Item> for Set<Item>
+where
+    Item: Field,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Item>,
+    {
+        Set(iter.into_iter().map(|item| (item, ())).collect())
+    }
 }
 
 impl<Item> Debug for Set<Item>
@@ -79,3 +172,87 @@ where
         write!(f, "Set(commitment: {:?})", self.commit())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn iter() {
+        let mut set: Set<u32> = (0..256).collect();
+        set.extend(128..512);
+
+        assert_eq!(set.known_len(), 512);
+        assert!(set.is_complete());
+        assert_eq!(set.iter().copied().collect::<HashSet<_>>(), (0..512).collect());
+
+        let export = set.export([0]).unwrap();
+        assert!(export.known_len() < 512);
+        assert!(!export.is_complete());
+    }
+
+    fn check(lho: &Set<u32>, rho: &Set<u32>) {
+        let lho_items = lho.iter().copied().collect::<HashSet<_>>();
+        let rho_items = rho.iter().copied().collect::<HashSet<_>>();
+
+        let union = lho_items.union(&rho_items).copied().collect::<Set<_>>();
+        let intersection = lho_items.intersection(&rho_items).copied().collect::<Set<_>>();
+        let difference = lho_items.difference(&rho_items).copied().collect::<Set<_>>();
+
+        assert_eq!(lho.union(rho).unwrap().commit(), union.commit());
+        assert_eq!(lho.intersection(rho).unwrap().commit(), intersection.commit());
+        assert_eq!(lho.difference(rho).unwrap().commit(), difference.commit());
+        assert_eq!(lho.is_subset(rho).unwrap(), lho_items.is_subset(&rho_items));
+    }
+
+    #[test]
+    fn algebra() {
+        let sets: Vec<Set<u32>> = vec![
+            Set::new(),
+            (0..1).collect(),
+            (1..2).collect(),
+            (0..256).collect(),
+            (128..384).collect(),
+            (0..512).filter(|item| item % 3 == 0).collect(),
+            (0..1024).collect(),
+        ];
+
+        for lho in sets.iter() {
+            for rho in sets.iter() {
+                check(lho, rho);
+            }
+        }
+    }
+
+    #[test]
+    fn algebra_nested() {
+        let lho: Set<u32> = (0..1024).collect();
+        let mut rho = lho.clone();
+        rho.insert(1024).unwrap();
+
+        let intersection = lho.intersection(&rho).unwrap();
+        assert_eq!(intersection.commit(), lho.commit());
+
+        assert!(lho.is_subset(&rho).unwrap());
+        assert!(!rho.is_subset(&lho).unwrap());
+        assert_eq!(rho.difference(&lho).unwrap().iter().collect::<Vec<_>>(), vec![&1024]);
+    }
+
+    #[test]
+    fn algebra_stub() {
+        let set: Set<u32> = (0..256).collect();
+        let export = set.export([0]).unwrap();
+
+        // Identical branches need not be known
+        assert_eq!(export.union(&export).unwrap().commit(), set.commit());
+        assert!(export.is_subset(&set).unwrap());
+        assert_eq!(export.difference(&set).unwrap().commit(), Set::<u32>::new().commit());
+
+        let other: Set<u32> = (1..257).collect();
+        assert!(export.union(&other).is_err());
+        assert!(export.difference(&other).is_err());
+        assert!(other.is_subset(&export).is_err());
+    }
+}
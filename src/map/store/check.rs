@@ -11,7 +11,9 @@ use crate::{
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-fn check_internal<Key, Value>(internal: &Internal<Key, Value>) -> Result<(), Top<TopologyError>>
+pub(crate) fn check_internal<Key, Value>(
+    internal: &Internal<Key, Value>,
+) -> Result<(), Top<TopologyError>>
 where
     Key: Field,
     Value: Field,
@@ -24,7 +26,7 @@ where
     }
 }
 
-fn check_leaf<Key, Value>(
+pub(crate) fn check_leaf<Key, Value>(
     leaf: &Leaf<Key, Value>,
     location: Prefix,
 ) -> Result<(), Top<TopologyError>>
@@ -39,30 +41,26 @@ where
     }
 }
 
-fn recursion<Key, Value>(
-    node: &Node<Key, Value>,
-    location: Prefix,
-) -> Result<(), Top<TopologyError>>
+pub(crate) fn check<Key, Value>(node: &Node<Key, Value>) -> Result<(), Top<TopologyError>>
 where
     Key: Field,
     Value: Field,
 {
-    match node {
-        Node::Internal(internal) => {
-            check_internal(internal)?;
+    // Iterative, as `node` might be arbitrarily deep
+    let mut stack = vec![(node, Prefix::root())];
+
+    while let Some((node, location)) = stack.pop() {
+        match node {
+            Node::Internal(internal) => {
+                check_internal(internal)?;
 
-            recursion(internal.left(), location.left())?;
-            recursion(internal.right(), location.right())
+                stack.push((internal.right(), location.right()));
+                stack.push((internal.left(), location.left()));
+            }
+            Node::Leaf(leaf) => check_leaf(leaf, location)?,
+            Node::Empty | Node::Stub(_) => {}
         }
-        Node::Leaf(leaf) => check_leaf(leaf, location),
-        Node::Empty | Node::Stub(_) => Ok(()),
     }
-}
 
-pub(crate) fn check<Key, Value>(node: &Node<Key, Value>) -> Result<(), Top<TopologyError>>
-where
-    Key: Field,
-    Value: Field,
-{
-    recursion(node, Prefix::root())
+    Ok(())
 }
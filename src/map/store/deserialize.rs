@@ -0,0 +1,335 @@
+use crate::{
+    common::{store::Field, tree::Prefix},
+    map::{
+        errors::DeserializeError,
+        store::{check, Internal, Leaf, Node, Stub},
+        DeserializeSettings,
+    },
+};
+
+use doomstack::{here, Doom, ResultExt};
+
+use serde::{
+    de::{
+        DeserializeSeed, EnumAccess, Error as DeError, MapAccess, SeqAccess, Unexpected,
+        VariantAccess, Visitor,
+    },
+    Deserialize, Deserializer,
+};
+
+use std::{
+    fmt::{self, Formatter},
+    marker::PhantomData,
+};
+
+// Reads the encoding derived for `Node`, computing hashes and checking the
+// topology of each node as soon as it is read.
+//
+// `serde` nests one call per level of the encoding, so the depth of the
+// recursion is that of the tree. It is bounded by the location of each node:
+// an `Internal` node at depth `max_depth` is rejected before its children are
+// read, so that the stack used is bounded by `DeserializeSettings` rather than
+// by the input.
+
+const VARIANTS: &[&str] = &["Empty", "Internal", "Leaf", "Stub"];
+const FIELDS: &[&str] = &["left", "right"];
+
+struct Budget {
+    nodes: usize,
+    max_nodes: usize,
+    max_depth: u8,
+}
+
+struct NodeSeed<'b, Key: Field, Value: Field> {
+    budget: &'b mut Budget,
+    location: Prefix,
+    _phantom: PhantomData<(Key, Value)>,
+}
+
+struct ChildrenSeed<'b, Key: Field, Value: Field> {
+    budget: &'b mut Budget,
+    location: Prefix,
+    _phantom: PhantomData<(Key, Value)>,
+}
+
+enum Variant {
+    Empty,
+    Internal,
+    Leaf,
+    Stub,
+}
+
+enum Child {
+    Left,
+    Right,
+}
+
+struct VariantVisitor;
+struct ChildVisitor;
+
+fn limit_exceeded<T, E>() -> Result<T, E>
+where
+    E: DeError,
+{
+    DeserializeError::LimitExceeded
+        .fail()
+        .spot(here!())
+        .map_err(E::custom)
+}
+
+impl<'b, Key, Value> NodeSeed<'b, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn new(budget: &'b mut Budget, location: Prefix) -> Self {
+        NodeSeed {
+            budget,
+            location,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'de, 'b, Key, Value> DeserializeSeed<'de> for NodeSeed<'b, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    type Value = Node<Key, Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if self.budget.nodes == self.budget.max_nodes {
+            return limit_exceeded();
+        }
+
+        self.budget.nodes += 1;
+        deserializer.deserialize_enum("Node", VARIANTS, self)
+    }
+}
+
+impl<'de, 'b, Key, Value> Visitor<'de> for NodeSeed<'b, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    type Value = Node<Key, Value>;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a map node")
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (variant, access) = data.variant::<Variant>()?;
+
+        match variant {
+            Variant::Empty => {
+                access.unit_variant()?;
+                Ok(Node::Empty)
+            }
+            Variant::Internal => {
+                if self.location.depth() >= self.budget.max_depth {
+                    return limit_exceeded();
+                }
+
+                let internal = access.newtype_variant_seed(ChildrenSeed {
+                    budget: self.budget,
+                    location: self.location,
+                    _phantom: PhantomData,
+                })?;
+
+                check::check_internal(&internal).map_err(A::Error::custom)?;
+                Ok(Node::Internal(internal))
+            }
+            Variant::Leaf => {
+                let leaf = access.newtype_variant::<Leaf<Key, Value>>()?;
+
+                check::check_leaf(&leaf, self.location).map_err(A::Error::custom)?;
+                Ok(Node::Leaf(leaf))
+            }
+            Variant::Stub => Ok(Node::Stub(access.newtype_variant::<Stub>()?)),
+        }
+    }
+}
+
+impl<'de, 'b, Key, Value> DeserializeSeed<'de> for ChildrenSeed<'b, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    type Value = Internal<Key, Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Children", FIELDS, self)
+    }
+}
+
+impl<'de, 'b, Key, Value> Visitor<'de> for ChildrenSeed<'b, Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    type Value = Internal<Key, Value>;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "the children of an internal map node")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let left = seq
+            .next_element_seed(NodeSeed::new(self.budget, self.location.left()))?
+            .ok_or_else(|| A::Error::invalid_length(0, &"struct Children with 2 elements"))?;
+
+        let right = seq
+            .next_element_seed(NodeSeed::new(self.budget, self.location.right()))?
+            .ok_or_else(|| A::Error::invalid_length(1, &"struct Children with 2 elements"))?;
+
+        Ok(Internal::new(left, right))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut left = None;
+        let mut right = None;
+
+        while let Some(child) = map.next_key::<Child>()? {
+            let (slot, location, name) = match child {
+                Child::Left => (&mut left, self.location.left(), "left"),
+                Child::Right => (&mut right, self.location.right(), "right"),
+            };
+
+            if slot.is_some() {
+                return Err(A::Error::duplicate_field(name));
+            }
+
+            *slot = Some(map.next_value_seed(NodeSeed::new(self.budget, location))?);
+        }
+
+        let left = left.ok_or_else(|| A::Error::missing_field("left"))?;
+        let right = right.ok_or_else(|| A::Error::missing_field("right"))?;
+
+        Ok(Internal::new(left, right))
+    }
+}
+
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(VariantVisitor)
+    }
+}
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = Variant;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "a map node variant")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        match value {
+            0 => Ok(Variant::Empty),
+            1 => Ok(Variant::Internal),
+            2 => Ok(Variant::Leaf),
+            3 => Ok(Variant::Stub),
+            _ => Err(E::invalid_value(
+                Unexpected::Unsigned(value),
+                &"variant index 0 <= i < 4",
+            )),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        match value {
+            "Empty" => Ok(Variant::Empty),
+            "Internal" => Ok(Variant::Internal),
+            "Leaf" => Ok(Variant::Leaf),
+            "Stub" => Ok(Variant::Stub),
+            _ => Err(E::unknown_variant(value, VARIANTS)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Child {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(ChildVisitor)
+    }
+}
+
+impl<'de> Visitor<'de> for ChildVisitor {
+    type Value = Child;
+
+    fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "`left` or `right`")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        match value {
+            0 => Ok(Child::Left),
+            1 => Ok(Child::Right),
+            _ => Err(E::invalid_value(
+                Unexpected::Unsigned(value),
+                &"field index 0 <= i < 2",
+            )),
+        }
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: DeError,
+    {
+        match value {
+            "left" => Ok(Child::Left),
+            "right" => Ok(Child::Right),
+            _ => Err(E::unknown_field(value, FIELDS)),
+        }
+    }
+}
+
+/// Deserializes the nodes of a `Map`, computing their hashes and checking
+/// their topology along the way, within the limits of `settings`.
+pub(crate) fn deserialize<'de, Key, Value, D>(
+    deserializer: D,
+    settings: &DeserializeSettings,
+) -> Result<Node<Key, Value>, D::Error>
+where
+    Key: Field,
+    Value: Field,
+    D: Deserializer<'de>,
+{
+    let mut budget = Budget {
+        nodes: 0,
+        max_nodes: settings.max_nodes,
+        max_depth: settings.max_depth,
+    };
+
+    NodeSeed::new(&mut budget, Prefix::root()).deserialize(deserializer)
+}
@@ -1,9 +1,10 @@
 #![allow(dead_code)] // TODO: Remove this attribute, make sure there is no dead code.
 
 mod check;
+pub(crate) mod deserialize;
 mod node;
 mod wrap;
 
 pub(crate) use check::check;
-pub(crate) use node::{Internal, Leaf, Node};
+pub(crate) use node::{Internal, Leaf, Node, Stub};
 pub(crate) use wrap::Wrap;
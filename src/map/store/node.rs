@@ -8,7 +8,12 @@ use crate::{
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Clone, Serialize, Deserialize)]
+use std::sync::Arc;
+
+// `Clone` is implemented by hand throughout, as cloning a node only copies
+// `Arc`s (and `derive` would require `Key: Clone` and `Value: Clone`)
+
+#[derive(Serialize, Deserialize)]
 pub(crate) enum Node<Key: Field, Value: Field> {
     Empty,
     #[serde(bound(deserialize = ""))]
@@ -18,27 +23,26 @@ pub(crate) enum Node<Key: Field, Value: Field> {
     Stub(Stub),
 }
 
-#[derive(Clone)]
 pub(crate) struct Internal<Key: Field, Value: Field> {
     hash: Bytes,
     children: Children<Key, Value>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+// Children are shared between the clones of a `Map`, and copied on write
+#[derive(Serialize, Deserialize)]
 struct Children<Key: Field, Value: Field> {
     #[serde(bound(deserialize = ""))]
-    left: Box<Node<Key, Value>>,
+    left: Arc<Node<Key, Value>>,
     #[serde(bound(deserialize = ""))]
-    right: Box<Node<Key, Value>>,
+    right: Arc<Node<Key, Value>>,
 }
 
-#[derive(Clone)]
 pub(crate) struct Leaf<Key: Field, Value: Field> {
     hash: Bytes,
     fields: Fields<Key, Value>,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 struct Fields<Key: Field, Value: Field> {
     #[serde(bound(deserialize = ""))]
     key: Wrap<Key>,
@@ -101,8 +105,8 @@ where
 {
     pub fn new(left: Node<Key, Value>, right: Node<Key, Value>) -> Self {
         Internal::from_children(Children {
-            left: Box::new(left),
-            right: Box::new(right),
+            left: Arc::new(left),
+            right: Arc::new(right),
         })
     }
 
@@ -115,8 +119,8 @@ where
         Internal {
             hash,
             children: Children {
-                left: Box::new(left),
-                right: Box::new(right),
+                left: Arc::new(left),
+                right: Arc::new(right),
             },
         }
     }
@@ -125,8 +129,12 @@ where
         self.hash
     }
 
+    /// Unless shared, children are moved out rather than cloned.
     pub fn children(self) -> (Node<Key, Value>, Node<Key, Value>) {
-        (*self.children.left, *self.children.right)
+        (
+            Arc::try_unwrap(self.children.left).unwrap_or_else(|left| (*left).clone()),
+            Arc::try_unwrap(self.children.right).unwrap_or_else(|right| (*right).clone()),
+        )
     }
 
     pub fn left(&self) -> &Node<Key, Value> {
@@ -134,7 +142,7 @@ where
     }
 
     pub fn left_mut(&mut self) -> &mut Node<Key, Value> {
-        &mut self.children.left
+        Arc::make_mut(&mut self.children.left)
     }
 
     pub fn right(&self) -> &Node<Key, Value> {
@@ -142,7 +150,7 @@ where
     }
 
     pub fn right_mut(&mut self) -> &mut Node<Key, Value> {
-        &mut self.children.right
+        Arc::make_mut(&mut self.children.right)
     }
 }
 
@@ -194,6 +202,53 @@ impl Stub {
     }
 }
 
+impl<Key, Value> Clone for Node<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Node::Empty => Node::Empty,
+            Node::Internal(internal) => Node::Internal(internal.clone()),
+            Node::Leaf(leaf) => Node::Leaf(leaf.clone()),
+            Node::Stub(stub) => Node::Stub(stub.clone()),
+        }
+    }
+}
+
+impl<Key, Value> Clone for Internal<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn clone(&self) -> Self {
+        Internal {
+            hash: self.hash,
+            children: Children {
+                left: self.children.left.clone(),
+                right: self.children.right.clone(),
+            },
+        }
+    }
+}
+
+impl<Key, Value> Clone for Leaf<Key, Value>
+where
+    Key: Field,
+    Value: Field,
+{
+    fn clone(&self) -> Self {
+        Leaf {
+            hash: self.hash,
+            fields: Fields {
+                key: self.fields.key.clone(),
+                value: self.fields.value.clone(),
+            },
+        }
+    }
+}
+
 impl<Key, Value> Serialize for Internal<Key, Value>
 where
     Key: Field,
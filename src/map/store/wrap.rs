@@ -4,12 +4,14 @@ use doomstack::Top;
 
 use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize, Serializer};
 
+use std::sync::Arc;
+
 use talk::crypto::primitives::{hash, hash::HashError};
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub(crate) struct Wrap<Inner: Field> {
     digest: Bytes,
-    inner: Inner,
+    inner: Arc<Inner>,
 }
 
 impl<Inner> Wrap<Inner>
@@ -19,16 +21,23 @@ where
     pub fn new(inner: Inner) -> Result<Self, Top<HashError>> {
         Ok(Wrap {
             digest: hash::hash(&inner)?.into(),
-            inner,
+            inner: Arc::new(inner),
         })
     }
 
     pub fn raw(digest: Bytes, inner: Inner) -> Self {
-        Wrap { digest, inner }
+        Wrap {
+            digest,
+            inner: Arc::new(inner),
+        }
     }
 
-    pub fn take(self) -> Inner {
-        self.inner
+    /// Unless shared, `inner` is moved out rather than cloned.
+    pub fn take(self) -> Inner
+    where
+        Inner: Clone,
+    {
+        Arc::try_unwrap(self.inner).unwrap_or_else(|inner| (*inner).clone())
     }
 
     pub fn digest(&self) -> Bytes {
@@ -40,6 +49,18 @@ where
     }
 }
 
+impl<Inner> Clone for Wrap<Inner>
+where
+    Inner: Field,
+{
+    fn clone(&self) -> Self {
+        Wrap {
+            digest: self.digest,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 impl<Inner> PartialEq for Wrap<Inner>
 where
     Inner: Field,
@@ -0,0 +1,40 @@
+use talk::crypto::primitives::hash::Hash;
+
+/// A branch of a [`Map`] that is replaced by a `Stub`, blocking an update
+/// (see [`Map::try_apply_batch`]).
+///
+/// The branch lies along the path of the update's key or, if the update
+/// removes the key, is the sibling of the key's leaf (which might have to be
+/// lifted in its place). In both cases, the branch is rooted [`depth`] levels
+/// below the root of the map. Exporting any key whose path crosses the branch
+/// (in the first case, the update's key) from a complete copy of the map, e.g.,
+/// via [`Table::export`], and importing the result fills the branch in.
+///
+/// [`Map`]: crate::map::Map
+/// [`Map::try_apply_batch`]: crate::map::Map::try_apply_batch
+/// [`depth`]: UnknownBranch::depth
+/// [`Table::export`]: crate::database::Table::export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownBranch {
+    depth: u8,
+    commitment: Hash,
+}
+
+impl UnknownBranch {
+    pub(crate) fn new(depth: u8, commitment: Hash) -> Self {
+        UnknownBranch { depth, commitment }
+    }
+
+    /// Returns the depth of the `Stub`, i.e., the length of its prefix.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the commitment (see [`Map::commit`]) to the contents of the
+    /// missing branch.
+    ///
+    /// [`Map::commit`]: crate::map::Map::commit
+    pub fn commitment(&self) -> Hash {
+        self.commitment
+    }
+}
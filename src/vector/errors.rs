@@ -4,6 +4,12 @@ use doomstack::Doom;
 pub enum VectorError {
     #[doom(description("Failed to hash item"))]
     HashError,
+    #[doom(description("Vector was stored with a different packing"))]
+    PackingMismatch,
+    #[doom(description("Stored vector is missing or corrupt"))]
+    CorruptStorage,
+    #[doom(description("Failed to access vector storage"))]
+    StorageError,
 }
 
 #[derive(Doom)]
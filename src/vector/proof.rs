@@ -1,83 +1,155 @@
-use bit_vec::BitVec;
-
 use crate::{
     common::tree::Direction,
-    vector::{errors::ProofError, Node},
+    vector::{errors::ProofError, Node, Vector},
 };
 
 use doomstack::{here, Doom, ResultExt, Top};
 
-use serde::{Deserialize, Serialize};
+use bit_vec::BitVec;
+
+use serde::{
+    de::{DeserializeOwned, Error as DeError},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
 use serde_bytes::ByteBuf;
 
 use talk::crypto::primitives::{hash, hash::Hash};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Proof {
+/// A proof of inclusion for an item of a [`Vector`].
+///
+/// On the wire, a `Proof` is encoded as a versioned envelope (currently
+/// `V1`). Decoding a proof with an unknown version fails.
+///
+/// # Compatibility
+///
+/// This is a breaking change to the API of `Proof`, which used to be a
+/// non-generic type encoded without an envelope:
+///
+/// * `Proof` is now generic over `Item` and `PACKING`, i.e., a proof
+///   returned by [`Vector::prove`] is typed after its vector.
+/// * `verify(root, item)` became [`verify(root, len, index, item)`]: a proof
+///   is checked against the position it claims, which the previous signature
+///   could not express. Callers pass the length of the vector and the index
+///   of `item`, as given to [`Vector::prove`].
+/// * Proofs in the previous encoding (e.g., persisted, or sent by peers that
+///   were not upgraded) can still be read with [`Proof::deserialize_legacy`],
+///   and are verified as usual.
+///
+/// [`Vector`]: crate::vector::Vector
+/// [`Vector::prove`]: crate::vector::Vector::prove
+/// [`verify(root, len, index, item)`]: Proof::verify
+#[derive(Debug, Clone)]
+pub struct Proof<Item, const PACKING: usize = 1> {
+    path: Vec<Direction>,
+    proof: Vec<Hash>,
+    siblings: Option<(Vec<Item>, usize)>,
+}
+
+#[derive(Serialize)]
+enum WireRef<'a, Item> {
+    V1 {
+        path: &'a [Direction],
+        proof: &'a [Hash],
+        siblings: &'a Option<(Vec<Item>, usize)>,
+    },
+}
+
+// Encoding of `Proof` before `Wire`
+#[derive(Deserialize)]
+struct Legacy {
     path: BitVec,
     proof: Vec<Hash>,
     siblings: Option<(Vec<ByteBuf>, usize)>,
 }
 
-impl Proof {
-    pub(in crate::vector) fn new<I, Item: Serialize>(
+#[derive(Deserialize)]
+enum Wire<Item> {
+    V1 {
+        path: Vec<Direction>,
+        proof: Vec<Hash>,
+        siblings: Option<(Vec<Item>, usize)>,
+    },
+}
+
+impl<Item, const PACKING: usize> Proof<Item, PACKING>
+where
+    Item: Serialize,
+{
+    pub(in crate::vector) fn new<I>(
         path: I,
         proof: Vec<Hash>,
-        siblings: Option<(Vec<&Item>, usize)>,
+        siblings: Option<(Vec<Item>, usize)>,
     ) -> Self
     where
         I: IntoIterator<Item = Direction>,
     {
-        let path = path
-            .into_iter()
-            .map(|direction| direction == Direction::Left)
-            .collect::<BitVec>();
-
-        let siblings = match siblings {
-            None => None,
-            Some((vec, pos)) => {
-                let vec: Vec<ByteBuf> = vec
-                    .into_iter()
-                    .map(|item| ByteBuf::from(bincode::serialize(item).unwrap()))
-                    .collect();
-                Some((vec, pos))
-            }
-        };
-
         Proof {
-            path,
+            path: path.into_iter().collect(),
             proof,
             siblings,
         }
     }
 
-    pub fn verify<Item: Serialize + for<'de> Deserialize<'de>>(
+    /// Verifies that `item` is the `index`-th item of the vector of `len`
+    /// items whose root is `root`.
+    ///
+    /// Verification never panics: a malformed proof (e.g., one received
+    /// from the network), including one whose path does not match the height
+    /// of a vector of `len` items, results in a [`ProofError`].
+    pub fn verify(
         &self,
         root: Hash,
+        len: usize,
+        index: usize,
         item: &Item,
     ) -> Result<(), Top<ProofError>> {
+        if index >= len {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
+        let path = Vector::<Item, PACKING>::path(len, index);
+
+        if self.path.len() != path.len() || self.proof.len() != path.len() {
+            return ProofError::Mislabled.fail().spot(here!());
+        }
+
+        if self.path != path {
+            return ProofError::OutOfPath.fail().spot(here!());
+        }
+
         let mut hash = match &self.siblings {
-            Some((vec, pos)) => {
-                let vec: Vec<Item> = vec
-                    .iter()
-                    .map(|item| bincode::deserialize::<Item>(item.as_ref()).unwrap())
-                    .collect();
-                let mut vec: Vec<&Item> = vec.iter().collect();
-                vec.insert(*pos, item);
-                hash::hash(&Node::<&[&Item]>::Item(vec.as_slice()))
+            Some((siblings, position)) if PACKING > 1 => {
+                let start = index - index % PACKING;
+                let chunk = std::cmp::min(PACKING, len - start);
+
+                if siblings.len() + 1 != chunk {
+                    return ProofError::Mislabled.fail().spot(here!());
+                }
+
+                if *position != index % PACKING {
+                    return ProofError::OutOfPath.fail().spot(here!());
+                }
+
+                let mut chunk = siblings.iter().collect::<Vec<&Item>>();
+                chunk.insert(*position, item);
+
+                hash::hash(&Node::<&[&Item]>::Item(chunk.as_slice()))
                     .pot(ProofError::HashError, here!())?
             }
-            None => hash::hash(&Node::<&Item>::Item(item)).pot(ProofError::HashError, here!())?,
+            None if PACKING == 1 => {
+                hash::hash(&Node::<&Item>::Item(item)).pot(ProofError::HashError, here!())?
+            }
+            _ => return ProofError::Mislabled.fail().spot(here!()),
         };
 
         for (direction, sibling_hash) in self.path.iter().zip(self.proof.iter().cloned()) {
             let parent = match direction {
-                true => Node::<Item>::Internal(hash, sibling_hash),
-                false => Node::<Item>::Internal(sibling_hash, hash),
+                Direction::Left => Node::<Item>::Internal(hash, sibling_hash),
+                Direction::Right => Node::<Item>::Internal(sibling_hash, hash),
             };
 
-            hash = hash::hash(&parent).unwrap();
+            hash = hash::hash(&parent).pot(ProofError::HashError, here!())?;
         }
 
         if root != hash {
@@ -86,4 +158,214 @@ impl Proof {
 
         Ok(())
     }
+
+    /// Deserializes a `Proof` from its pre-`V1` encoding, i.e., that of
+    /// `Proof` before it was made generic over `Item` and `PACKING`.
+    ///
+    /// In that encoding, packed siblings are `bincode`-serialized: siblings
+    /// that fail to decode as `Item`s result in an error.
+    pub fn deserialize_legacy<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        Item: DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        let legacy = Legacy::deserialize(deserializer)?;
+
+        // In the legacy encoding, set bits are left turns
+        let path = legacy
+            .path
+            .iter()
+            .map(|bit| if bit { Direction::Left } else { Direction::Right })
+            .collect();
+
+        let siblings = match legacy.siblings {
+            Some((siblings, position)) => {
+                let siblings = siblings
+                    .iter()
+                    .map(|sibling| bincode::deserialize::<Item>(sibling))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(DeError::custom)?;
+
+                Some((siblings, position))
+            }
+            None => None,
+        };
+
+        Ok(Proof {
+            path,
+            proof: legacy.proof,
+            siblings,
+        })
+    }
+}
+
+impl<Item, const PACKING: usize> Serialize for Proof<Item, PACKING>
+where
+    Item: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        WireRef::V1 {
+            path: &self.path,
+            proof: &self.proof,
+            siblings: &self.siblings,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, Item, const PACKING: usize> Deserialize<'de> for Proof<Item, PACKING>
+where
+    Item: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Wire::<Item>::deserialize(deserializer)? {
+            Wire::V1 {
+                path,
+                proof,
+                siblings,
+            } => Ok(Proof {
+                path,
+                proof,
+                siblings,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bincode::Options;
+
+    #[test]
+    fn verify_stress() {
+        for len in 1..64 {
+            let vector = Vector::<_, 3>::new((0..len as u32).collect()).unwrap();
+
+            for index in 0..len {
+                let proof = vector.prove(index);
+                proof
+                    .verify(vector.root(), len, index, &(index as u32))
+                    .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn wrong_index() {
+        let vector = Vector::<_>::new((0..16u32).collect()).unwrap();
+        let proof = vector.prove(3);
+
+        assert!(proof.verify(vector.root(), 16, 4, &3).is_err());
+        assert!(proof.verify(vector.root(), 16, 16, &3).is_err());
+    }
+
+    #[test]
+    fn wrong_height() {
+        let vector = Vector::<_>::new((0..16u32).collect()).unwrap();
+        let mut proof = vector.prove(3);
+
+        proof.path.pop();
+        proof.proof.pop();
+
+        assert!(proof.verify(vector.root(), 16, 3, &3).is_err());
+
+        // A proof for a vector of 16 items cannot prove membership in one of 17
+        let proof = vector.prove(3);
+        assert!(proof.verify(vector.root(), 17, 3, &3).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        let vector = Vector::<_, 2>::new((0..16u32).collect()).unwrap();
+
+        let mut proof = vector.prove(3);
+        proof.proof.pop();
+        assert!(proof.verify(vector.root(), 16, 3, &3).is_err());
+
+        let mut proof = vector.prove(3);
+        proof.siblings = Some((vec![2], 7));
+        assert!(proof.verify(vector.root(), 16, 3, &3).is_err());
+
+        let mut proof = vector.prove(3);
+        proof.siblings = Some((vec![2, 2, 2], 0));
+        assert!(proof.verify(vector.root(), 16, 3, &3).is_err());
+
+        let mut proof = vector.prove(3);
+        proof.siblings = None;
+        assert!(proof.verify(vector.root(), 16, 3, &3).is_err());
+    }
+
+    #[test]
+    fn serde() {
+        let vector = Vector::<_, 2>::new((0..16u32).collect()).unwrap();
+        let proof = vector.prove(5);
+
+        let serialized = bincode::serialize(&proof).unwrap();
+        let deserialized = bincode::deserialize::<Proof<u32, 2>>(&serialized).unwrap();
+
+        deserialized.verify(vector.root(), 16, 5, &5).unwrap();
+    }
+
+    #[test]
+    fn unknown_version() {
+        let vector = Vector::<_>::new((0..16u32).collect()).unwrap();
+
+        let mut serialized = bincode::serialize(&vector.prove(5)).unwrap();
+        serialized[0] = 1; // Variant index of a future `V2`
+
+        assert!(bincode::deserialize::<Proof<u32>>(&serialized).is_err());
+    }
+
+    #[derive(Serialize)]
+    struct LegacyRef {
+        path: BitVec,
+        proof: Vec<Hash>,
+        siblings: Option<(Vec<ByteBuf>, usize)>,
+    }
+
+    #[test]
+    fn legacy() {
+        let vector = Vector::<_, 2>::new((0..16u32).collect()).unwrap();
+        let proof = vector.prove(5);
+
+        let (siblings, position) = proof.siblings.clone().unwrap();
+
+        let legacy = LegacyRef {
+            path: proof.path.iter().map(|direction| *direction == Direction::Left).collect(),
+            proof: proof.proof.clone(),
+            siblings: Some((
+                siblings
+                    .iter()
+                    .map(|sibling| ByteBuf::from(bincode::serialize(sibling).unwrap()))
+                    .collect(),
+                position,
+            )),
+        };
+
+        // `bincode::serialize`'s options
+        let deserialize = |serialized: &[u8]| {
+            let options = bincode::DefaultOptions::new().with_fixint_encoding();
+            Proof::<u32, 2>::deserialize_legacy(&mut bincode::Deserializer::from_slice(
+                serialized, options,
+            ))
+        };
+
+        let serialized = bincode::serialize(&legacy).unwrap();
+        deserialize(&serialized).unwrap().verify(vector.root(), 16, 5, &5).unwrap();
+
+        // Siblings that are not `u32`s
+        let mut legacy = legacy;
+        legacy.siblings = Some((vec![ByteBuf::from(vec![0u8])], position));
+
+        let serialized = bincode::serialize(&legacy).unwrap();
+        assert!(deserialize(&serialized).is_err());
+    }
 }
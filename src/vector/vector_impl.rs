@@ -94,50 +94,60 @@ where
     }
 
     pub fn set(&mut self, index: usize, item: Item) -> Result<(), Top<VectorError>> {
-        assert!(index < self.items.len());
+        let hashes = self.trail_hashes(index, &item)?;
+        self.replace(index, item, hashes);
 
-        self.items[index] = item;
+        Ok(())
+    }
+
+    /// Returns the hashes along `trail(index)` once the item at `index` is
+    /// set to `item`, leaving the vector unchanged (see `replace`).
+    pub(crate) fn trail_hashes(
+        &self,
+        index: usize,
+        item: &Item,
+    ) -> Result<Vec<Hash>, Top<VectorError>> {
+        assert!(index < self.items.len());
 
         let mut node_hash = if PACKING == 1 {
-            hash::hash(&Node::<&Item>::Item(self.items.get(index).unwrap()))
-                .pot(VectorError::HashError, here!())?
+            hash::hash(&Node::<&Item>::Item(item)).pot(VectorError::HashError, here!())?
         } else {
             let chunk = ((index - index % PACKING)
                 ..std::cmp::min(index - index % PACKING + PACKING, self.items.len()))
-                .map(|index| self.items.get(index).unwrap())
+                .map(|position| if position == index { item } else { &self.items[position] })
                 .collect::<Vec<_>>();
 
             hash::hash(&Node::<&[&Item]>::Item(chunk.as_slice()))
                 .pot(VectorError::HashError, here!())?
         };
 
-        let node_index = index / PACKING;
+        let mut hashes = Vec::new();
 
-        let first_layer_len = self.layers[0].len();
-        let mut layers = self.layers.iter_mut();
-
-        let mut layer_index = if node_index < first_layer_len {
-            node_index
-        } else {
-            layers.next();
-            node_index - first_layer_len / 2
-        };
-
-        for layer in layers {
-            layer[layer_index] = node_hash;
+        // Siblings are off the trail: they are the same before and after `set`
+        for (layer, position) in self.trail(index) {
+            let layer = &self.layers[layer];
+            hashes.push(node_hash);
 
             if layer.len() > 1 {
-                node_hash = if layer_index % 2 == 0 {
-                    hash::hash(&Node::<Item>::Internal(node_hash, layer[layer_index + 1])).unwrap()
+                node_hash = if position % 2 == 0 {
+                    hash::hash(&Node::<Item>::Internal(node_hash, layer[position + 1])).unwrap()
                 } else {
-                    hash::hash(&Node::<Item>::Internal(layer[layer_index - 1], node_hash)).unwrap()
+                    hash::hash(&Node::<Item>::Internal(layer[position - 1], node_hash)).unwrap()
                 };
             }
-
-            layer_index /= 2;
         }
 
-        Ok(())
+        Ok(hashes)
+    }
+
+    /// Sets the item at `index` to `item`, given the `hashes` returned by
+    /// `trail_hashes(index, &item)`.
+    pub(crate) fn replace(&mut self, index: usize, item: Item, hashes: Vec<Hash>) {
+        self.items[index] = item;
+
+        for ((layer, position), hash) in self.trail(index).into_iter().zip(hashes) {
+            self.layers[layer][position] = hash;
+        }
     }
 
     pub fn len(&self) -> usize {
@@ -156,36 +166,87 @@ where
         &self.items
     }
 
-    pub fn prove(&self, index: usize) -> Proof {
-        assert!(index < self.items.len());
+    pub(crate) fn from_raw(layers: Vec<Vec<Hash>>, items: Vec<Item>) -> Self {
+        Vector { layers, items }
+    }
 
-        let mut path: Vec<Direction> = Vec::new();
-        let mut proof: Vec<Hash> = Vec::new();
+    pub(crate) fn layers(&self) -> &[Vec<Hash>] {
+        &self.layers
+    }
+
+    /// Returns the `(layer, position)` of every hash on the path from the
+    /// node holding `index` up to the root, i.e., the hashes `set` rewrites.
+    pub(crate) fn trail(&self, index: usize) -> Vec<(usize, usize)> {
+        Self::walk(self.layers[0].len(), self.layers.len(), index / PACKING)
+    }
+
+    /// Returns the path `prove(index)` produces on any vector of `len` items,
+    /// without building the vector.
+    pub(in crate::vector) fn path(len: usize, index: usize) -> Vec<Direction> {
+        let nodes = len.div_ceil(PACKING);
+
+        // Mirrors the layer construction in `with_packing`
+        let pow = std::cmp::max(1, nodes.checked_next_power_of_two().unwrap_or(usize::MAX) / 2);
+        let last_layer = std::cmp::max(1, 2 * (nodes - pow));
+
+        let (first_layer_len, height) = if nodes > last_layer {
+            (last_layer, pow.trailing_zeros() as usize + 2)
+        } else {
+            (nodes, nodes.trailing_zeros() as usize + 1)
+        };
 
-        let mut layers = self.layers.iter();
+        let mut walk = Self::walk(first_layer_len, height, index / PACKING);
+        walk.pop(); // The root has no sibling
 
-        let index_shift = index / PACKING;
+        walk.into_iter()
+            .map(|(_, position)| {
+                if position % 2 == 0 {
+                    Direction::Left
+                } else {
+                    Direction::Right
+                }
+            })
+            .collect()
+    }
 
-        let mut layer_index = if index_shift < self.layers[0].len() {
-            index_shift
+    fn walk(first_layer_len: usize, height: usize, node_index: usize) -> Vec<(usize, usize)> {
+        let (first_layer, mut position) = if node_index < first_layer_len {
+            (0, node_index)
         } else {
-            layers.next();
-            index_shift - self.layers[0].len() / 2
+            (1, node_index - first_layer_len / 2)
         };
 
-        for layer in layers {
+        (first_layer..height)
+            .map(|layer| {
+                let step = (layer, position);
+                position /= 2;
+                step
+            })
+            .collect()
+    }
+
+    pub fn prove(&self, index: usize) -> Proof<Item, PACKING>
+    where
+        Item: Clone,
+    {
+        assert!(index < self.items.len());
+
+        let mut path: Vec<Direction> = Vec::new();
+        let mut proof: Vec<Hash> = Vec::new();
+
+        for (layer, position) in self.trail(index) {
+            let layer = &self.layers[layer];
+
             if layer.len() > 1 {
-                let (direction, sibling) = if layer_index % 2 == 0 {
-                    (Direction::Left, layer[layer_index + 1])
+                let (direction, sibling) = if position % 2 == 0 {
+                    (Direction::Left, layer[position + 1])
                 } else {
-                    (Direction::Right, layer[layer_index - 1])
+                    (Direction::Right, layer[position - 1])
                 };
 
                 path.push(direction);
                 proof.push(sibling);
             }
-
-            layer_index /= 2;
         }
 
         let siblings = if PACKING == 1 {
@@ -196,7 +257,7 @@ where
                 ..std::cmp::min(index - index % PACKING + PACKING, self.items.len())
             {
                 if i != index {
-                    siblings.push(&self.items()[i])
+                    siblings.push(self.items()[i].clone())
                 }
             }
             Some((siblings, index % PACKING))
@@ -421,7 +482,7 @@ mod tests {
 
             for item in 0..len {
                 let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                proof.verify(vector.root(), len, item, &item).unwrap();
             }
         }
     }
@@ -433,7 +494,7 @@ mod tests {
 
             for item in 0..len {
                 let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                proof.verify(vector.root(), len, item, &item).unwrap();
             }
         }
     }
@@ -445,7 +506,7 @@ mod tests {
 
             for item in 0..len {
                 let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                proof.verify(vector.root(), len, item, &item).unwrap();
             }
         }
     }
@@ -457,7 +518,7 @@ mod tests {
 
             for item in 0..len {
                 let proof = vector.prove(item);
-                proof.verify(vector.root(), &item).unwrap();
+                proof.verify(vector.root(), len, item, &item).unwrap();
             }
         }
     }
@@ -476,6 +537,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn set_stress_3packed() {
+        for len in 1..128 {
+            let control = Vector::<_, 3>::new((0..len).collect()).unwrap();
+            let mut vector = Vector::<_, 3>::new(vec![0; len]).unwrap();
+
+            for index in 0..len {
+                // Computing the hashes of an update leaves the vector unchanged
+                let root = vector.root();
+                vector.trail_hashes(index, &index).unwrap();
+                assert_eq!(vector.root(), root);
+
+                vector.set(index, index).unwrap();
+            }
+
+            assert_eq!(vector.root(), control.root());
+        }
+    }
+
     #[test]
     fn serde() {
         let original = Vector::<_>::new((0..128).collect()).unwrap();